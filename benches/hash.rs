@@ -27,6 +27,19 @@ pub fn rescue(c: &mut Criterion) {
     });
 }
 
+pub fn rescue128(c: &mut Criterion) {
+    let v: [u8; 64] = [
+         1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15, 16,
+        17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+         1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15, 16,
+        17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    ];
+    let mut r = [0u8; 32];
+    c.bench_function("Rescue128", |bench| {
+        bench.iter(|| hash::rescue128(black_box(&v), black_box(&mut r)))
+    });
+}
+
 pub fn gmimc(c: &mut Criterion) {
     let v: [u8; 64] = [
          1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15, 16,
@@ -66,4 +79,4 @@ pub fn sha3(c: &mut Criterion) {
     });
 }
 
-criterion_group!(group, poseidon, rescue, gmimc, blake3, sha3);
+criterion_group!(group, poseidon, rescue, rescue128, gmimc, blake3, sha3);
@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use distaff::{ processor, StarkProof, VerifierPolicy, F128 };
+
+// Feeds arbitrary bytes to the verifier as a serialized proof; any input must either be rejected
+// or verified, but must never cause the verifier to panic. The input is decoded both as a bincode
+// serialized proof and as a proof in the compact binary format. The fuzzer is most effective when
+// the corpus is seeded with valid serialized proofs (e.g. proofs generated by the examples).
+fuzz_target!(|data: &[u8]| {
+    let policy = VerifierPolicy::with_security_level(0);
+    if let Ok(proof) = bincode::deserialize::<StarkProof<F128>>(data) {
+        let _ = processor::verify_with_policy(&[0u8; 32], &[1, 0], &[], &[3], &proof, &policy);
+    }
+    if let Ok(proof) = StarkProof::<F128>::from_bytes(data) {
+        let _ = processor::verify_with_policy(&[0u8; 32], &[1, 0], &[], &[3], &proof, &policy);
+    }
+});
@@ -1,4 +1,5 @@
-use crate::math::{ F64, FiniteField };
+use crate::math::{ F64, F128, FiniteField };
+use crate::stark::{ Hasher };
 use crate::utils::{ as_bytes };
 use sha3::Digest;
 
@@ -139,6 +140,45 @@ pub fn rescue(values: &[u8], result: &mut [u8]) {
     result.copy_from_slice(as_bytes(&state[..4]));
 }
 
+// ------------------------------------------------------------------------------------------------
+/// Rescue hash function over the 128-bit field with the same parameters as used by HASHR
+/// operation of the VM (see `Hasher`); the result consists of 2 field elements of the digest.
+///
+/// A 64-byte input which encodes 4 valid field elements fills the rate part of the state, so a
+/// pair of 32-byte nodes is hashed in the same way as MPATH and HASHR operations hash two nodes.
+/// Any other input is split into 15-byte limbs (each of which is a valid field element), and the
+/// last element of the state is set to the length of the input plus one; since this element is
+/// 0 for the first encoding, distinct inputs are always mapped to distinct states.
+pub fn rescue128(values: &[u8], result: &mut [u8]) {
+    debug_assert!(values.len() <= 64, "expected 64 or fewer input bytes but received {}", values.len());
+    debug_assert!(result.len() == 32, "expected result to be exactly 32 bytes but received {}", result.len());
+
+    let mut state = [F128::ZERO; <F128 as Hasher>::STATE_WIDTH];
+    if values.len() == 64 && values.chunks(16).all(|bytes| F128::from_bytes(bytes) < F128::MODULUS) {
+        for (element, bytes) in state.iter_mut().zip(values.chunks(16)) {
+            *element = F128::from_bytes(bytes);
+        }
+    }
+    else {
+        for (element, bytes) in state.iter_mut().zip(values.chunks(15)) {
+            let mut buf = [0u8; 16];
+            buf[..bytes.len()].copy_from_slice(bytes);
+            *element = F128::from_bytes(&buf);
+        }
+        state[<F128 as Hasher>::STATE_WIDTH - 1] = (values.len() + 1) as u128;
+    }
+
+    // apply the permutation in the same way as Hasher::digest() does
+    state.reverse();
+    for i in 0..<F128 as Hasher>::NUM_ROUNDS {
+        <F128 as Hasher>::apply_round(&mut state, i);
+    }
+    state.reverse();
+
+    // return the result
+    result.copy_from_slice(as_bytes(&state[..<F128 as Hasher>::DIGEST_SIZE]));
+}
+
 // ------------------------------------------------------------------------------------------------
 /// GMiMC_erf hash function
 pub fn gmimc(values: &[u8], result: &mut [u8]) {
@@ -221,6 +261,8 @@ fn apply_mds(state: &mut[u64; 12]) {
 #[cfg(test)]
 mod tests {
 
+    use crate::math::{ F128, FiniteField };
+    use crate::stark::{ Hasher };
     use crate::utils::{ as_bytes };
 
     #[test]
@@ -247,6 +289,37 @@ mod tests {
             result);
     }
 
+    #[test]
+    fn rescue128() {
+        let value = [1u128, 2, 3, 4];
+        let mut result = [0; 32];
+        super::rescue128(as_bytes(&value), &mut result);
+
+        // the result is the same as the digest computed by the VM hasher
+        let expected = <F128 as Hasher>::digest(&value);
+        assert_eq!(as_bytes(&expected), &result);
+
+        // distinct inputs result in distinct digests, including inputs which differ only by
+        // a multiple of the modulus, trailing zeros, or the encoding used for them
+        let inputs: Vec<Vec<u8>> = vec![
+            as_bytes(&[1u128 + F128::MODULUS, 2, 3, 4]).to_vec(),
+            as_bytes(&[1u128, 2, 3, 4 + F128::MODULUS]).to_vec(),
+            as_bytes(&[1u128, 2, 3]).to_vec(),
+            as_bytes(&[0u128; 4]).to_vec(),
+            vec![],
+            vec![0],
+            vec![1],
+            vec![1, 0],
+        ];
+        let mut digests = vec![result];
+        for input in inputs.iter() {
+            let mut digest = [0; 32];
+            super::rescue128(input, &mut digest);
+            assert!(!digests.contains(&digest));
+            digests.push(digest);
+        }
+    }
+
     #[test]
     fn gmimc() {
         let value = [1u64, 2, 3, 4, 5, 6, 7, 8];
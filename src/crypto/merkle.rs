@@ -1,8 +1,13 @@
-use std::slice;
+use std::{ cmp, slice };
 use std::collections::{ HashMap, BTreeSet };
 use serde::{ Serialize, Deserialize };
+use crossbeam_utils::thread;
 use crate::crypto::{ HashFunction };
 
+// CONSTANTS
+// ================================================================================================
+const MIN_CONCURRENT_BATCH_SIZE: usize = 1024;
+
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct MerkleTree {
@@ -23,10 +28,18 @@ impl MerkleTree {
 
     /// Creates a new merkle tree from the provide leaves and using the provided hash function.
     pub fn new(leaves: Vec<[u8; 32]>, hash: HashFunction) -> MerkleTree {
+        return MerkleTree::new_concurrent(leaves, hash, 1);
+    }
+
+    /// Creates a new merkle tree from the provide leaves and using the provided hash function;
+    /// each level of the tree is hashed in batches which are distributed across `num_threads`
+    /// threads.
+    pub fn new_concurrent(leaves: Vec<[u8; 32]>, hash: HashFunction, num_threads: usize) -> MerkleTree {
         assert!(leaves.len().is_power_of_two(), "number of leaves must be a power of 2");
         assert!(leaves.len() >= 2, "a tree must contain at least 2 leaves");
+        assert!(num_threads > 0, "number of threads must be greater than 0");
 
-        let nodes = build_merkle_nodes(&leaves, hash);
+        let nodes = build_merkle_nodes(&leaves, hash, num_threads);
         return MerkleTree {
             values  : leaves,
             nodes   : nodes
@@ -38,6 +51,20 @@ impl MerkleTree {
         return &self.nodes[1];
     }
 
+    /// Returns the Merkle cap of the tree: all nodes at level `cap_height` below the root. If the
+    /// tree is not deep enough, the cap consists of the parents of the leaves.
+    pub fn cap(&self, cap_height: u8) -> &[[u8; 32]] {
+        let depth = self.values.len().trailing_zeros() as u8;
+        let cap_size = MerkleTree::cap_size(depth, cap_height);
+        return &self.nodes[cap_size..(2 * cap_size)];
+    }
+
+    /// Returns the number of nodes in the cap of height `cap_height` for a tree of the
+    /// specified depth.
+    pub fn cap_size(depth: u8, cap_height: u8) -> usize {
+        return 1 << u8::min(cap_height, depth.saturating_sub(1));
+    }
+
     /// Returns leaf nodes of the tree
     pub fn leaves(&self) -> &[[u8; 32]] {
         return &self.values;
@@ -61,7 +88,11 @@ impl MerkleTree {
     }
 
     /// Computes merkle paths for the provided indexes and compresses the paths into a single proof.
-    pub fn prove_batch(&self, indexes: &[usize]) -> BatchMerkleProof {
+    /// Paths which overlap share nodes: a node which can be computed from other values of the
+    /// proof is never included, and every other node is included exactly once. The paths end at
+    /// the cap of height `cap_height` (see `cap()`), so nodes at or above the cap are not included
+    /// in the proof.
+    pub fn prove_batch(&self, indexes: &[usize], cap_height: u8) -> BatchMerkleProof {
         let n = self.values.len();
 
         let index_map = map_indexes(indexes, n);
@@ -99,7 +130,8 @@ impl MerkleTree {
 
         // add required internal nodes to the proof, skipping redundancies
         let depth = self.values.len().trailing_zeros() as u8;
-        for _ in 1..depth {
+        let cap_height = MerkleTree::cap_size(depth, cap_height).trailing_zeros() as u8;
+        for _ in (cap_height + 1)..depth {
             let indexes = next_indexes.clone();
             next_indexes.truncate(0);
 
@@ -131,7 +163,7 @@ impl MerkleTree {
         let r = index & 1;
         &buf[0..32].copy_from_slice(&proof[r]);
         &buf[32..64].copy_from_slice(&proof[1 - r]);
-        hash(&buf, &mut v);
+        hash.merge(&buf, &mut v);
 
         let mut index = (index + usize::pow(2, (proof.len() - 1) as u32)) >> 1;
         for i in 2..proof.len() {
@@ -143,7 +175,7 @@ impl MerkleTree {
                 &buf[0..32].copy_from_slice(&proof[i]);
                 &buf[32..64].copy_from_slice(&v);
             }
-            hash(&buf, &mut v);
+            hash.merge(&buf, &mut v);
             index = index >> 1;
         }
 
@@ -151,15 +183,27 @@ impl MerkleTree {
     }
 
     /// Checks whether the batch proof contains merkle paths for the of the specified indexes.
-    pub fn verify_batch(root: &[u8; 32], indexes: &[usize], proof: &BatchMerkleProof, hash: HashFunction) -> bool {
+    /// The proof must contain exactly the nodes which `prove_batch()` includes into it; a proof
+    /// with redundant nodes or values is rejected.
+    /// The paths must end at nodes of the specified `cap`; the height of the cap is implied by
+    /// its length, and a cap containing only the root is the same as the root.
+    pub fn verify_batch(cap: &[[u8; 32]], indexes: &[usize], proof: &BatchMerkleProof, hash: HashFunction) -> bool {
         let mut buf = [0u8; 64];
         let mut v: HashMap<usize, [u8; 32]> = HashMap::new();
 
-        // replace odd indexes, offset, and sort in ascending order
+        // make sure indexes are unique and fit into a tree of the specified depth; the proof may
+        // come from an untrusted source, so malformed proofs must fail verification gracefully
+        if proof.depth == 0 || proof.depth as u32 >= usize::BITS { return false; }
+        if !cap.len().is_power_of_two() || cap.len() > MerkleTree::cap_size(proof.depth, u8::MAX) { return false; }
+        let cap_height = cap.len().trailing_zeros() as u8;
         let offset = usize::pow(2, proof.depth as u32);
+        if indexes.len() == 0 || indexes.iter().any(|&index| index >= offset) { return false; }
+        if indexes.iter().collect::<BTreeSet<_>>().len() != indexes.len() { return false; }
+
+        // replace odd indexes, offset, and sort in ascending order
         let index_map = map_indexes(indexes, offset - 1);
         let indexes = normalize_indexes(indexes);
-        if indexes.len() != proof.nodes.len() { return false; }
+        if indexes.len() != proof.nodes.len() || index_map.len() != proof.values.len() { return false; }
 
         // for each index use values to compute parent nodes
         let mut next_indexes: Vec<usize> = Vec::new();
@@ -199,15 +243,15 @@ impl MerkleTree {
 
             // hash sibling nodes into their parent
             let mut parent = [0u8; 32];
-            hash(&buf, &mut parent);
+            hash.merge(&buf, &mut parent);
 
             let parent_index = offset + index >> 1;
             v.insert(parent_index, parent);
             next_indexes.push(parent_index);
         }
 
-        // iteratively move up, until we get to the root
-        for _ in 1..proof.depth {
+        // iteratively move up, until we get to the cap
+        for _ in (cap_height + 1)..proof.depth {
             let indexes = next_indexes.clone();
             next_indexes.truncate(0);
 
@@ -248,7 +292,7 @@ impl MerkleTree {
                     &buf[32..64].copy_from_slice(sibling);
                 }
                 let mut parent = [0u8; 32];
-                hash(&buf, &mut parent);
+                hash.merge(&buf, &mut parent);
 
                 // add the parent node to the next set of nodes
                 let parent_index = node_index >> 1;
@@ -258,15 +302,25 @@ impl MerkleTree {
                 i += 1;
             }
         }
-     
-        return *root == *v.get(&1).unwrap();
+
+        // nodes shared by overlapping paths are included in the proof only once, and thus, every
+        // node of every path must have been used; otherwise the proof would have many encodings
+        if proof_pointers.iter().zip(proof.nodes.iter()).any(|(&pointer, path)| pointer != path.len()) {
+            return false;
+        }
+
+        // all computed nodes at the level of the cap must match the cap
+        return next_indexes.into_iter().all(|index| match v.get(&index) {
+            Some(computed_node) => cap[index - cap.len()] == *computed_node,
+            None => false
+        });
     }
 }
 
 // HELPER FUNCTIONS
 // ================================================================================================
 
-fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 32]> {
+fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction, num_threads: usize) -> Vec<[u8; 32]> {
     let n = leaves.len() / 2;
 
     // create un-initialized array to hold all intermediate nodes
@@ -278,19 +332,44 @@ fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 32]>
     let two_leaves = unsafe { slice::from_raw_parts(leaves.as_ptr() as *const [u8; 64], n) };
 
     // build first row of internal nodes (parents of leaves)
-    for (i, j) in (0..n).zip(n..nodes.len()) {
-        hash(&two_leaves[i], &mut nodes[j]);
+    hash_level(two_leaves, &mut nodes[n..], hash, num_threads);
+
+    // calculate all other tree nodes one level at a time; nodes of a level with k nodes are
+    // located at [k, 2k), and their children - at [2k, 4k)
+    let mut level_size = n / 2;
+    while level_size > 0 {
+        let (parents, children) = nodes.split_at_mut(2 * level_size);
+
+        // re-interpret children as an array of two nodes fused together
+        let two_nodes = unsafe { slice::from_raw_parts(children.as_ptr() as *const [u8; 64], level_size) };
+        hash_level(two_nodes, &mut parents[level_size..], hash, num_threads);
+        level_size = level_size / 2;
     }
 
-    // re-interpret nodes as an array of two nodes fused together
-    let two_nodes = unsafe { slice::from_raw_parts(nodes.as_ptr() as *const [u8; 64], n) };
+    return nodes;
+}
 
-    // calculate all other tree nodes
-    for i in (1..n).rev() {
-        hash(&two_nodes[i], &mut nodes[i]);
+/// Hashes each pair of nodes in `inputs` into the corresponding node in `results`. Levels with
+/// many nodes are split into batches which are hashed in separate threads; small levels are
+/// not worth the overhead of spawning threads.
+fn hash_level(inputs: &[[u8; 64]], results: &mut [[u8; 32]], hash: HashFunction, num_threads: usize) {
+    let batch_size = cmp::max(MIN_CONCURRENT_BATCH_SIZE, results.len().div_ceil(num_threads));
+    if num_threads == 1 || results.len() <= batch_size {
+        for (input, result) in inputs.iter().zip(results.iter_mut()) {
+            hash.merge(input, result);
+        }
+        return;
     }
 
-    return nodes;
+    thread::scope(|s| {
+        for (inputs, results) in inputs.chunks(batch_size).zip(results.chunks_mut(batch_size)) {
+            s.spawn(move |_| {
+                for (input, result) in inputs.iter().zip(results.iter_mut()) {
+                    hash.merge(input, result);
+                }
+            });
+        }
+    }).unwrap();
 }
 
 fn map_indexes(indexes: &[usize], max_valid: usize) -> HashMap<usize, usize> {
@@ -316,7 +395,7 @@ fn normalize_indexes(indexes: &[usize]) -> Vec<usize> {
 #[cfg(test)]
 mod tests {
 
-    use crate::hash;
+    use crate::crypto::HashFunction;
 
     static LEAVES4: [[u8; 32]; 4] = [
         [166, 168,  47, 140, 153, 86, 156,  86, 226, 229, 149,  76,  70, 132, 209, 109, 166, 193, 113, 197,  42, 116, 170, 144,  74, 104,  29, 110, 220, 49, 224, 123],
@@ -339,21 +418,30 @@ mod tests {
     #[test]
     fn new_tree() {
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let root = [95, 106, 134, 217, 39, 37, 167, 72, 238, 190, 126, 165, 92, 32, 252, 249, 211, 114, 125, 2, 58, 67, 63, 250, 0, 41, 166, 223, 236, 169, 100, 249];
         assert_eq!(&root, tree.root());
 
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let root = [178, 193, 172, 21, 105, 143, 49, 120, 206, 97, 74, 61, 103, 254, 18, 90, 80, 101, 176, 85, 247, 160, 224, 76, 153, 15, 146, 88, 108, 131, 121, 242];
         assert_eq!(&root, tree.root());
     }
 
+    #[test]
+    fn new_tree_concurrent() {
+        // the tree must be large enough for its lower levels to be split across threads
+        let leaves = (0..(1 << 14)).map(|_| rand::random::<[u8; 32]>()).collect::<Vec<_>>();
+        let tree1 = super::MerkleTree::new(leaves.clone(), HashFunction::Blake3);
+        let tree2 = super::MerkleTree::new_concurrent(leaves, HashFunction::Blake3, 4);
+        assert_eq!(tree1.nodes, tree2.nodes);
+    }
+
     #[test]
     fn prove() {
         // depth 4
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
 
         let proof = vec![
             [243,  57,  40, 140, 185,  79, 188, 229, 232, 117, 143, 118, 235, 229,  73, 251, 163, 246, 151, 170, 14, 243, 255, 127, 175, 230, 94, 227, 214,  5,  89, 105],
@@ -371,7 +459,7 @@ mod tests {
 
         // depth 5
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
 
         let proof = vec![
             [ 52,  46,  37, 214,  24, 248, 121, 199, 229,  25, 171,  67,  65,  37,  98, 142, 182,  72, 202,  42, 223, 160, 136,  60,  38, 255, 222,  82,  26, 27, 130, 203],
@@ -394,30 +482,30 @@ mod tests {
     fn verify() {
         // depth 4
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let proof = tree.prove(1);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, HashFunction::Poseidon));
 
         let proof = tree.prove(2);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 2, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 2, &proof, HashFunction::Poseidon));
 
         // depth 5
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         let proof = tree.prove(1);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, HashFunction::Poseidon));
 
         let proof = tree.prove(6);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 6, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 6, &proof, HashFunction::Poseidon));
     }
 
     #[test]
     fn prove_batch() {
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
         
         // 1 index
-        let proof = tree.prove_batch(&[1]);
+        let proof = tree.prove_batch(&[1], 0);
         let expected_values = vec![
             [52, 46, 37, 214, 24, 248, 121, 199, 229, 25, 171, 67, 65, 37, 98, 142, 182, 72, 202, 42, 223, 160, 136, 60, 38, 255, 222, 82, 26, 27, 130, 203]
         ];
@@ -433,7 +521,7 @@ mod tests {
         assert_eq!(3, proof.depth);
 
         // 2 indexes
-        let proof = tree.prove_batch(&[1, 2]);
+        let proof = tree.prove_batch(&[1, 2], 0);
         let expected_values = vec![
             [52,  46,  37, 214, 24, 248, 121, 199, 229, 25, 171, 67, 65,  37, 98, 142, 182,  72, 202,  42, 223, 160, 136,  60, 38, 255, 222,  82,  26,  27, 130, 203],
             [130, 43, 231,   0, 59, 228, 152, 140,  18, 33,  87, 27, 49, 190, 44,  82, 188, 155, 163, 108, 166, 198, 106, 143, 83, 167, 201, 152, 106, 176, 242, 119]
@@ -452,7 +540,7 @@ mod tests {
         assert_eq!(3, proof.depth);
 
         // 2 indexes on opposite sides
-        let proof = tree.prove_batch(&[1, 6]);
+        let proof = tree.prove_batch(&[1, 6], 0);
         let expected_values = vec![
             [52, 46,  37, 214, 24, 248, 121, 199, 229,  25, 171,  67,  65,  37,  98, 142, 182, 72, 202,  42, 223, 160, 136,  60,  38, 255, 222,  82,  26,  27, 130, 203],
             [25, 96, 149, 179, 94,   8, 170, 214, 169, 135,  12, 212, 224, 157, 182, 127, 233, 93, 151, 214,  36, 183, 156, 212, 233, 152, 125, 244, 146, 161,  75, 128]
@@ -472,7 +560,7 @@ mod tests {
         assert_eq!(3, proof.depth);
 
         // all indexes
-        let proof = tree.prove_batch(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let proof = tree.prove_batch(&[0, 1, 2, 3, 4, 5, 6, 7], 0);
         let expected_values = LEAVES8.to_vec();
         let expected_nodes: Vec<Vec<[u8; 32]>> = vec![ vec![], vec![], vec![], vec![]];
         assert_eq!(expected_values, proof.values);
@@ -483,25 +571,100 @@ mod tests {
     #[test]
     fn verify_batch() {
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
-
-        let proof = tree.prove_batch(&[1]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[2], &proof, hash::poseidon));
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
+
+        let proof = tree.prove_batch(&[1], 0);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(0), &[1], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[2], &proof, HashFunction::Poseidon));
+
+        let proof = tree.prove_batch(&[1, 2], 0);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(0), &[1, 2], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 3], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 2, 3], &proof, HashFunction::Poseidon));
+
+        let proof = tree.prove_batch(&[1, 6], 0);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(0), &[1, 6], &proof, HashFunction::Poseidon));
+
+        let proof = tree.prove_batch(&[1, 3, 6], 0);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(0), &[1, 3, 6], &proof, HashFunction::Poseidon));
+
+        let proof = tree.prove_batch(&[0, 1, 2, 3, 4, 5, 6, 7], 0);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(0), &[0, 1, 2, 3, 4, 5, 6, 7], &proof, HashFunction::Poseidon));
+        // malformed indexes and proofs
+        let mut proof = tree.prove_batch(&[1, 6], 0);
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 8], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 1], &proof, HashFunction::Poseidon));
+
+        proof.depth = 0;
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 6], &proof, HashFunction::Poseidon));
+        proof.depth = 200;
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 6], &proof, HashFunction::Poseidon));
+    }
 
-        let proof = tree.prove_batch(&[1, 2]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 2], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 3], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 2, 3], &proof, hash::poseidon));
+    #[test]
+    fn batch_proof_shared_nodes() {
+        let leaves = LEAVES8.to_vec();
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
+
+        // paths for leaves 1, 3, and 6 overlap: separately they contain 9 nodes, but the batch
+        // proof contains only siblings which cannot be computed from other values of the proof
+        let proof = tree.prove_batch(&[1, 3, 6], 0);
+        let num_nodes: usize = proof.nodes.iter().map(|path| path.len()).sum();
+        assert_eq!(4, num_nodes);
+        for i in 0..proof.nodes.len() {
+            for node in proof.nodes[i].iter() {
+                let count = proof.nodes.iter().flatten().filter(|&other| other == node).count();
+                assert_eq!(1, count);
+            }
+        }
 
-        let proof = tree.prove_batch(&[1, 6]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 6], &proof, hash::poseidon));
+        // a proof with a duplicated node is rejected
+        let mut bad_proof = proof.clone();
+        let node = bad_proof.nodes[0][0];
+        bad_proof.nodes[1].push(node);
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 3, 6], &bad_proof, HashFunction::Poseidon));
 
-        let proof = tree.prove_batch(&[1, 3, 6]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 3, 6], &proof, hash::poseidon));
+        // a proof with a redundant value is rejected
+        let mut bad_proof = proof.clone();
+        bad_proof.values.push(LEAVES8[0]);
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1, 3, 6], &bad_proof, HashFunction::Poseidon));
+    }
 
-        let proof = tree.prove_batch(&[0, 1, 2, 3, 4, 5, 6, 7]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[0, 1, 2, 3, 4, 5, 6, 7], &proof, hash::poseidon));
+    #[test]
+    fn prove_verify_batch_with_cap() {
+        let leaves = LEAVES8.to_vec();
+        let tree = super::MerkleTree::new(leaves, HashFunction::Poseidon);
+
+        // a cap of height 0 is the root
+        assert_eq!(&[*tree.root()], tree.cap(0));
+
+        // a cap of height 1 removes the top level from the paths
+        let proof = tree.prove_batch(&[1], 1);
+        assert_eq!(2, tree.cap(1).len());
+        assert_eq!(2, proof.nodes[0].len());
+        assert_eq!(tree.prove_batch(&[1], 0).nodes[0][..2], proof.nodes[0][..]);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(1), &[1], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(0), &[1], &proof, HashFunction::Poseidon));
+
+        let proof = tree.prove_batch(&[1, 3, 6], 2);
+        assert_eq!(4, tree.cap(2).len());
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(2), &[1, 3, 6], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.cap(1), &[1, 3, 6], &proof, HashFunction::Poseidon));
+
+        // the cap cannot be below the parents of the leaves
+        assert_eq!(tree.cap(2), tree.cap(5));
+        let proof = tree.prove_batch(&[1, 6], 5);
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.cap(5), &[1, 6], &proof, HashFunction::Poseidon));
+
+        // malformed caps
+        let mut bad_cap = tree.cap(2).to_vec();
+        bad_cap[3] = [0u8; 32];
+        assert_eq!(false, super::MerkleTree::verify_batch(&bad_cap, &[1, 6], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(&bad_cap[..3], &[1, 6], &proof, HashFunction::Poseidon));
+        assert_eq!(false, super::MerkleTree::verify_batch(&[], &[1, 6], &proof, HashFunction::Poseidon));
+        let long_cap = vec![[0u8; 32]; 8];
+        assert_eq!(false, super::MerkleTree::verify_batch(&long_cap, &[1, 6], &proof, HashFunction::Poseidon));
     }
 }
\ No newline at end of file
@@ -1,6 +1,159 @@
+use std::convert::TryFrom;
+use serde::{ Serialize, Deserialize };
+
 pub mod hash;
 
 mod merkle;
 pub use merkle::{ MerkleTree, BatchMerkleProof };
 
-pub type HashFunction = fn(&[u8], &mut [u8]);
\ No newline at end of file
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Hash functions which can be used to generate STARK proofs. A hash function is serialized as
+/// a single byte which identifies the function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum HashFunction {
+    Blake3,
+    Sha3,
+    Poseidon,
+    Rescue,
+    GMiMC,
+    /// Rescue over the 128-bit field, same as used by HASHR operation; Merkle commitments and
+    /// pseudo-random challenges of proofs which use this function can be recomputed by a program.
+    Rescue128,
+}
+
+// HASH FUNCTION IMPLEMENTATION
+// ================================================================================================
+impl HashFunction {
+
+    /// Hashes `values` into `result`; `result` must be exactly 32 bytes long.
+    ///
+    /// Algebraic hash functions (Poseidon, Rescue, GMiMC, Rescue128) can absorb at most 64 bytes
+    /// at once, so inputs of any length are hashed by first hashing the length of the input, and
+    /// then iteratively hashing the result together with the next 32 bytes of the input (the last
+    /// chunk is padded with zeros). Since the length is always absorbed, inputs which differ only
+    /// by trailing zeros result in different hashes.
+    pub fn digest(&self, values: &[u8], result: &mut [u8]) {
+        let hash_fn = match self {
+            HashFunction::Blake3    => return hash::blake3(values, result),
+            HashFunction::Sha3      => return hash::sha3(values, result),
+            HashFunction::Poseidon  => hash::poseidon,
+            HashFunction::Rescue    => hash::rescue,
+            HashFunction::GMiMC     => hash::gmimc,
+            HashFunction::Rescue128 => hash::rescue128,
+        };
+
+        let mut buf = [0u8; 64];
+        hash_fn(&(values.len() as u64).to_le_bytes(), &mut buf[..32]);
+        for chunk in values.chunks(32) {
+            buf[32..(32 + chunk.len())].copy_from_slice(chunk);
+            for i in (32 + chunk.len())..64 { buf[i] = 0; }
+            let mut state = [0u8; 32];
+            hash_fn(&buf, &mut state);
+            buf[..32].copy_from_slice(&state);
+        }
+        result.copy_from_slice(&buf[..32]);
+    }
+
+    /// Hashes a pair of 32-byte nodes into `result` with a single invocation of the hash function;
+    /// `result` must be exactly 32 bytes long. This is used for inner nodes of Merkle trees, and
+    /// for Rescue128 is the same as hashing the two nodes with HASHR operation of the VM.
+    pub fn merge(&self, values: &[u8; 64], result: &mut [u8]) {
+        match self {
+            HashFunction::Blake3    => hash::blake3(values, result),
+            HashFunction::Sha3      => hash::sha3(values, result),
+            HashFunction::Poseidon  => hash::poseidon(values, result),
+            HashFunction::Rescue    => hash::rescue(values, result),
+            HashFunction::GMiMC     => hash::gmimc(values, result),
+            HashFunction::Rescue128 => hash::rescue128(values, result),
+        }
+    }
+}
+
+impl From<HashFunction> for u8 {
+    fn from(hash_fn: HashFunction) -> u8 {
+        return match hash_fn {
+            HashFunction::Blake3    => 0,
+            HashFunction::Sha3      => 1,
+            HashFunction::Poseidon  => 2,
+            HashFunction::Rescue    => 3,
+            HashFunction::GMiMC     => 4,
+            HashFunction::Rescue128 => 5,
+        };
+    }
+}
+
+impl TryFrom<u8> for HashFunction {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<HashFunction, String> {
+        return match value {
+            0 => Ok(HashFunction::Blake3),
+            1 => Ok(HashFunction::Sha3),
+            2 => Ok(HashFunction::Poseidon),
+            3 => Ok(HashFunction::Rescue),
+            4 => Ok(HashFunction::GMiMC),
+            5 => Ok(HashFunction::Rescue128),
+            _ => Err(String::from("unsupported hash function"))
+        };
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use super::{ HashFunction, hash };
+
+    static HASH_FUNCTIONS: [HashFunction; 6] = [
+        HashFunction::Blake3, HashFunction::Sha3, HashFunction::Poseidon, HashFunction::Rescue, HashFunction::GMiMC,
+        HashFunction::Rescue128
+    ];
+
+    #[test]
+    fn serialization() {
+        for &hash_fn in HASH_FUNCTIONS.iter() {
+            let bytes = bincode::serialize(&hash_fn).unwrap();
+            assert_eq!(1, bytes.len());
+            assert_eq!(hash_fn, bincode::deserialize::<HashFunction>(&bytes).unwrap());
+        }
+
+        let result = bincode::deserialize::<HashFunction>(&[6]);
+        assert_eq!("unsupported hash function", result.err().unwrap().to_string());
+    }
+
+    #[test]
+    fn digest() {
+        // inputs of different lengths result in different hashes
+        for &hash_fn in HASH_FUNCTIONS.iter() {
+            let mut result1 = [0u8; 32];
+            let mut result2 = [0u8; 32];
+            hash_fn.digest(&[1u8; 100], &mut result1);
+            hash_fn.digest(&[1u8; 101], &mut result2);
+            assert_ne!(result1, result2);
+
+            let mut result3 = [0u8; 32];
+            hash_fn.digest(&[1u8; 100], &mut result3);
+            assert_eq!(result1, result3);
+
+            // trailing zeros are not ambiguous with padding
+            hash_fn.digest(&[1u8], &mut result1);
+            hash_fn.digest(&[1u8, 0], &mut result2);
+            assert_ne!(result1, result2);
+        }
+    }
+
+    #[test]
+    fn merge() {
+        // pairs of nodes are hashed directly
+        let values = [1u8; 64];
+        let mut expected = [0u8; 32];
+        let mut result = [0u8; 32];
+        hash::poseidon(&values, &mut expected);
+        HashFunction::Poseidon.merge(&values, &mut result);
+        assert_eq!(expected, result);
+    }
+}
@@ -1,4 +1,4 @@
-use distaff::{ ProgramInputs, processor::opcodes::f128 as opcodes, FiniteField, F128, stark::Hasher };
+use distaff::{ ProgramInputs, processor, FiniteField, F128, stark::Hasher };
 use super::{ Example, utils::parse_args };
 
 pub fn get_example(args: &[String]) -> Example  {
@@ -8,7 +8,7 @@ pub fn get_example(args: &[String]) -> Example  {
     assert!(depth >= 2, "tree depth must be at least 2, but received {}", depth);
     
     // generate the program to verify Merkle path of given length
-    let program = generate_merkle_program(depth);
+    let program = processor::recursion::generate_merkle_path_program(depth);
     println!("Generated a program to verify Merkle proof for a tree of depth {}", depth);
 
     // generate a pseudo-random Merkle authentication path
@@ -36,49 +36,6 @@ pub fn get_example(args: &[String]) -> Example  {
     };
 }
 
-/// Returns a program to verify Merkle authentication path for a tree of depth `n`
-fn generate_merkle_program(n: usize) -> Vec<F128> {
-
-    // the program starts by reading the first two nodes in the Merkle
-    // path and pushing them onto the stack (each node is represented
-    // by two field elements). This part also pads the stack to prepare
-    // it for hashing.
-    let mut program = vec![
-        opcodes::BEGIN, opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
-        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
-        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
-        opcodes::READ2, opcodes::READ2, opcodes::DUP4,  opcodes::PAD2
-    ];
-
-    // this cycle of operation gets repeated once for each remaining node. It does
-    // roughly the following :
-    // 1. computes hash(p, v)
-    // 2. reads next bit of position index
-    // 3. computes hash(v, p)
-    // 4. based on position index bit, choses either hash(p, v) or hash(v, p)
-    // 5. reads the next nodes and pushes it onto the stack
-    let level_sub = vec![
-        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
-        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
-        opcodes::HASHR, opcodes::HASHR, opcodes::DROP4, opcodes::READ2,
-        opcodes::SWAP2, opcodes::SWAP4, opcodes::SWAP2, opcodes::PAD2,
-        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
-        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
-        opcodes::HASHR, opcodes::HASHR, opcodes::DROP4, opcodes::CHOOSE2,
-        opcodes::READ2, opcodes::DUP4,  opcodes::PAD2,  opcodes::NOOP
-    ];
-
-    for _ in 0..(n - 2) {
-        program.extend_from_slice(&level_sub);
-    }
-
-    // at the end, we use the same cycle except we don't need to read in
-    // any more nodes - so, we omit the last 4 operations.
-    program.extend_from_slice(&level_sub[..28]);
-
-    return program;
-}
-
 /// Converts Merkle authentication path for a node at the specified `index` into 
 /// a set of inputs which can be consumed by the program created by the function above.
 fn generate_program_inputs(path: &[Vec<F128>; 2], index: usize) -> ProgramInputs<F128> {
@@ -86,22 +43,16 @@ fn generate_program_inputs(path: &[Vec<F128>; 2], index: usize) -> ProgramInputs
     let mut a = Vec::new();
     let mut b = Vec::new();
     let n = path[0].len();
-    let mut index = index + usize::pow(2, (n - 1) as u32);
 
-    // push the leaf node of the authentication path onto secret input tapes A and B
-    a.push(path[0][0]);
-    b.push(path[1][0]);
+    // push the index of the leaf node onto secret input tape A; MPATH operation will
+    // consume one bit of the index for each level of the tree
+    a.push(F128::from_usize(index));
 
-    for i in 1..n {
-        // push the next node onto tapes A and B
+    // push the leaf node and all remaining nodes of the authentication path onto secret
+    // input tapes A and B
+    for i in 0..n {
         a.push(path[0][i]);
         b.push(path[1][i]);
-
-        // push next bit of the position index onto tapes A and B; we use both tapes
-        // here so that we can use READ2 instruction when reading inputs from the tapes
-        a.push(F128::ZERO);
-        b.push(F128::from_usize(index & 1));
-        index = index >> 1;
     }
 
     return ProgramInputs::new(&[], &a, &b);
@@ -1,7 +1,7 @@
 use std::env;
 use std::io::Write;
 use std::time::Instant;
-use distaff::{ StarkProof, processor, F128 };
+use distaff::{ StarkProof, VerifierPolicy, Program, F128 };
 
 mod examples;
 use examples::{ Example };
@@ -34,32 +34,32 @@ fn main() {
     let Example { program, inputs, num_outputs, options, expected_result } = ex;
     println!("--------------------------------");
 
-    // compute expected hash for the program
-    let program = processor::pad_program(&program);
-    let expected_hash = processor::hash_program(&program);
+    // pad the program and compute its hash
+    let program = Program::new(&program).unwrap();
 
     // execute the program and generate the proof of execution
     let now = Instant::now();
-    let (outputs, program_hash, proof) = processor::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = program.prove(&inputs, num_outputs, &options).unwrap();
     println!("--------------------------------");
     println!("Executed program with hash {} in {} ms", 
-        hex::encode(program_hash),
+        hex::encode(program.hash()),
         now.elapsed().as_millis());
     println!("Program output: {:?}", outputs);
     assert_eq!(expected_result, outputs, "Program result was computed incorrectly");
-    assert_eq!(expected_hash, program_hash, "Program hash was generated incorrectly");
 
     // serialize the proof to see how big it is
-    let proof_bytes = bincode::serialize(&proof).unwrap();
+    let proof_bytes = proof.to_bytes();
     println!("Execution proof size: {} KB", proof_bytes.len() / 1024);
     println!("Execution proof security: {} bits", options.security_level(true));
     println!("--------------------------------");
 
     // verify that executing a program with a given hash and given inputs
-    // results in the expected output
-    let proof = bincode::deserialize::<StarkProof<F128>>(&proof_bytes).unwrap();
+    // results in the expected output; the proof must provide at least the
+    // security level of the options we requested
+    let proof = StarkProof::<F128>::from_bytes(&proof_bytes).unwrap();
+    let policy = VerifierPolicy::with_security_level(options.security_level(true));
     let now = Instant::now();
-    match processor::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof) {
+    match program.verify_with_policy(inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy) {
         Ok(_) => println!("Execution verified in {} ms", now.elapsed().as_millis()),
         Err(msg) => println!("Failed to verify execution: {}", msg)
     }
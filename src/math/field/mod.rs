@@ -19,6 +19,9 @@ pub trait FiniteField: Copy + Eq + PartialOrd + Display + Debug + SampleUniform
     const ZERO: Self;
     const ONE: Self;
 
+    /// Generator of the multiplicative group of the field.
+    const GENERATOR: Self;
+
     // BASIC ARITHMETIC
     // --------------------------------------------------------------------------------------------
 
@@ -101,6 +104,16 @@ pub trait FiniteField: Copy + Eq + PartialOrd + Display + Debug + SampleUniform
         return result;
     }
 
+    /// Generates a vector with values [s, s * b, s * b^2, s * b^3, ..., s * b^length].
+    fn get_power_series_with_offset(b: Self, s: Self, length: usize) -> Vec<Self> {
+        let mut result = uninit_vector(length);
+        result[0] = s;
+        for i in 1..result.len() {
+            result[i] = Self::mul(result[i - 1], b);
+        }
+        return result;
+    }
+
     // RANDOMNESS
     // --------------------------------------------------------------------------------------------
 
@@ -118,7 +131,8 @@ pub trait FiniteField: Copy + Eq + PartialOrd + Display + Debug + SampleUniform
         return g.sample_iter(range).take(length).collect();
     }
 
-    /// Generates a pseudo-random field element from a given `seed`.
+    /// Generates a pseudo-random field element from a given `seed`. The output depends on the
+    /// version of the `rand` crate, and thus, must not be used to derive proof challenges.
     fn prng(seed: [u8; 32]) -> Self {
         let range = Uniform::from(Self::RANGE);
         let mut g = StdRng::from_seed(seed);
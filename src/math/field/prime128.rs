@@ -22,6 +22,8 @@ impl FiniteField for F128 {
 
     const ZERO: u128 = 0;
     const ONE: u128 = 1;
+
+    const GENERATOR: u128 = 3;
     
     // BASIC ARITHMETIC
     // --------------------------------------------------------------------------------------------
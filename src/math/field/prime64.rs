@@ -22,6 +22,8 @@ impl FiniteField for F64 {
 
     const ZERO: u64 = 0;
     const ONE: u64 = 1;
+
+    const GENERATOR: u64 = 3;
     
     // BASIC ARITHMETIC
     // --------------------------------------------------------------------------------------------
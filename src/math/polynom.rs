@@ -46,6 +46,23 @@ pub fn eval_fft_twiddles<T>(p: &mut [T], twiddles: &[T], unpermute: bool)
     }
 }
 
+/// Evaluates polynomial `p` over a multiplicative coset of the FFT domain (i.e. at points
+/// offset * g^i) using FFT algorithm; the evaluation is done in-place, meaning `p` is updated
+/// with results of the evaluation. The twiddles must be computed for the FFT domain itself.
+/// 
+/// If `unpermute` parameter is set to false, the evaluations will be left in permuted state.
+pub fn eval_fft_coset<T>(p: &mut [T], twiddles: &[T], offset: T, unpermute: bool)
+    where T: FiniteField
+{
+    // p(offset * x) is a polynomial with coefficients p_i * offset^i
+    let mut power_of_offset = T::ONE;
+    for coefficient in p.iter_mut() {
+        *coefficient = T::mul(*coefficient, power_of_offset);
+        power_of_offset = T::mul(power_of_offset, offset);
+    }
+    eval_fft_twiddles(p, twiddles, unpermute);
+}
+
 // POLYNOMIAL INTERPOLATION
 // ================================================================================================
 
@@ -114,6 +131,24 @@ pub fn interpolate_fft_twiddles<T>(v: &mut [T], inv_twiddles: &[T], unpermute: b
     }
 }
 
+/// Uses FFT algorithm to interpolate a polynomial from values `v` of the polynomial over
+/// a multiplicative coset of the FFT domain (i.e. at points offset * g^i); the interpolation
+/// is done in-place, meaning `v` is updated with polynomial coefficients. The inverse twiddles
+/// must be computed for the FFT domain itself.
+pub fn interpolate_fft_coset<T>(v: &mut [T], inv_twiddles: &[T], offset: T)
+    where T: FiniteField
+{
+    interpolate_fft_twiddles(v, inv_twiddles, true);
+
+    // coefficients of p(offset * x) are p_i * offset^i
+    let inv_offset = T::inv(offset);
+    let mut power_of_offset = T::ONE;
+    for coefficient in v.iter_mut() {
+        *coefficient = T::mul(*coefficient, power_of_offset);
+        power_of_offset = T::mul(power_of_offset, inv_offset);
+    }
+}
+
 // POLYNOMIAL MATH OPERATIONS
 // ================================================================================================
 
@@ -347,6 +382,29 @@ mod tests {
         assert_eq!(y1, y2);
     }
 
+    #[test]
+    fn eval_interpolate_fft_coset() {
+        let n: usize = 1024;
+        let root = F64::get_root_of_unity(n);
+        let offset = F64::GENERATOR;
+
+        // create a random polynomial
+        let poly = F64::rand_vector(n);
+
+        // evaluate polynomial over the coset using FFT
+        let mut y1 = poly.clone();
+        super::eval_fft_coset(&mut y1, &crate::math::fft::get_twiddles(root, n), offset, true);
+
+        // evaluate polynomial over the coset using simple evaluation
+        let xs = F64::get_power_series_with_offset(root, offset, n);
+        let y2 = xs.iter().map(|&x| super::eval(&poly, x)).collect::<Vec<F64>>();
+        assert_eq!(y1, y2);
+
+        // interpolate the evaluations back into the polynomial
+        super::interpolate_fft_coset(&mut y1, &crate::math::fft::get_inv_twiddles(root, n), offset);
+        assert_eq!(poly, y1);
+    }
+
     #[test]
     fn add() {
         let poly1: [F64; 3] = [384863712573444386, 7682273369345308472, 13294661765012277990];
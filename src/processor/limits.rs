@@ -0,0 +1,56 @@
+use crate::stark::{ MIN_TRACE_LENGTH };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Resource limits for program execution. A program is executed only if the number of cycles
+/// it consumes and the length of its execution trace are within these limits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExecutionLimits {
+    max_cycles          : usize,
+    max_trace_length    : usize,
+}
+
+// EXECUTION LIMITS IMPLEMENTATION
+// ================================================================================================
+impl ExecutionLimits {
+
+    pub fn new(max_cycles: usize, max_trace_length: usize) -> ExecutionLimits {
+        assert!(max_cycles > 1, "max_cycles must be greater than 1");
+        assert!(max_trace_length >= MIN_TRACE_LENGTH,
+            "max_trace_length cannot be smaller than {}", MIN_TRACE_LENGTH);
+        return ExecutionLimits { max_cycles, max_trace_length };
+    }
+
+    pub fn max_cycles(&self) -> usize {
+        return self.max_cycles;
+    }
+
+    pub fn max_trace_length(&self) -> usize {
+        return self.max_trace_length;
+    }
+
+    /// Returns an error if a program which consumes `cycles` cycles and results in an execution
+    /// trace of `trace_length` steps exceeds these limits.
+    pub fn check(&self, cycles: usize, trace_length: usize) -> Result<(), String> {
+        if cycles > self.max_cycles {
+            return Err(format!("program execution requires {} cycles, but at most {} cycles are allowed",
+                cycles, self.max_cycles));
+        }
+        if trace_length > self.max_trace_length {
+            return Err(format!("program execution requires trace of {} steps, but at most {} steps are allowed",
+                trace_length, self.max_trace_length));
+        }
+        return Ok(());
+    }
+}
+
+impl Default for ExecutionLimits {
+
+    fn default() -> ExecutionLimits {
+        return ExecutionLimits {
+            max_cycles          : usize::MAX,
+            max_trace_length    : usize::MAX,
+        };
+    }
+}
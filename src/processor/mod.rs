@@ -1,14 +1,35 @@
 use log::debug;
 use std::{ cmp, time::Instant };
 use crate::math::{ F128, FiniteField };
-use crate::stark::{ self, ProofOptions, StarkProof, ProgramInputs, MAX_OUTPUTS, MIN_TRACE_LENGTH };
+use crate::stark::{ self, ProofOptions, ProofEstimate, StarkProof, ProgramInputs, VerifierPolicy, SegmentState, SegmentProof, BatchItem };
+use crate::stark::{ MAX_OUTPUTS, MIN_TRACE_LENGTH, HASH_CYCLE_LENGTH };
 use crate::utils::{ as_bytes };
 
 pub mod opcodes;
 
+mod limits;
+pub use limits::ExecutionLimits;
+
+mod program;
+pub use program::Program;
+
+pub mod recursion;
+
 #[cfg(test)]
 mod tests;
 
+/// Outputs, program hash, and proof of execution of a program together with the number of cycles
+/// consumed by the program.
+pub type MeteredExecution = (Vec<F128>, [u8; 32], StarkProof<F128>, usize);
+
+/// Outputs, program hash, and proofs of execution of all segments of a program executed in
+/// segments.
+pub type SegmentedExecution = (Vec<F128>, [u8; 32], Vec<SegmentProof<F128>>);
+
+/// Segments must be long enough to hold at least one operation after the first operation of a
+/// segment is aligned with the hash cycle (see `split_program()`).
+pub const MIN_SEGMENT_LENGTH: usize = 2 * HASH_CYCLE_LENGTH;
+
 // TODO: transforming execute() into a fully generic version results in about 10% - 15% runtime
 // penalty (mostly in running FFT). So, keeping it non-generic for now.
 
@@ -17,21 +38,39 @@ mod tests;
 /// 
 /// * `inputs` specify the initial stack state the with inputs[0] being the top of the stack;
 /// * `num_outputs` specifies the number of elements from the top of the stack to be returned;
+///
+/// The program must consume all values of the public tape and all secret inputs of `inputs`;
+/// the proof binds the hash of the values read from the public tape (see `verify()`).
+///
+/// Panics if the program cannot be executed; use `execute_with_limits()` to handle such failures.
 pub fn execute(program: &[F128], inputs: &ProgramInputs<F128>, num_outputs: usize, options: &ProofOptions) -> (Vec<F128>, [u8; 32], StarkProof<F128>)
 {
-    assert!(program.len() > 1,
-        "expected a program with at last two operations, but received {}", program.len());
-    assert!(program[0] == F128::from(opcodes::BEGIN),
-        "a program must start with BEGIN operation");
-    assert!(num_outputs <= MAX_OUTPUTS, 
-        "cannot produce more than {} outputs, but requested {}", MAX_OUTPUTS, num_outputs);
+    let limits = ExecutionLimits::default();
+    return match execute_with_limits(program, inputs, num_outputs, options, &limits) {
+        Ok((outputs, program_hash, proof, _)) => (outputs, program_hash, proof),
+        Err(msg) => panic!("{}", msg)
+    };
+}
 
-    // pad the program with the appropriate number of NOOPs
-    let program = pad_program(program);
+/// Executes the specified `program` within the specified resource `limits` and returns the
+/// result together with program hash, STARK-based proof of execution, and the number of
+/// consumed cycles. Returns an error if the program is malformed, if executing it would exceed
+/// the limits, or if the program fails during execution (e.g. on stack underflow, when inputs
+/// run out, or on a failed ASSERT); the limits are checked before the execution trace is generated.
+pub fn execute_with_limits(
+    program     : &[F128],
+    inputs      : &ProgramInputs<F128>,
+    num_outputs : usize,
+    options     : &ProofOptions,
+    limits      : &ExecutionLimits) -> Result<MeteredExecution, String>
+{
+    let (program, cycles) = prepare_program(program, num_outputs, limits)?;
 
-    // execute the program to create an execution trace
+    // execute the program to create an execution trace; in zero-knowledge mode, trace
+    // polynomials are randomized, and thus, the LDE domain is larger
     let now = Instant::now();
-    let mut trace = stark::TraceTable::new(&program, inputs, options.extension_factor());
+    let extension_factor = options.domain_size(program.len()) / program.len();
+    let mut trace = stark::TraceTable::new(&program, inputs, extension_factor)?;
     debug!("Generated execution trace of {} registers and {} steps in {} ms",
         trace.register_count(),
         trace.unextended_length(),
@@ -47,14 +86,171 @@ pub fn execute(program: &[F128], inputs: &ProgramInputs<F128>, num_outputs: usiz
 
     // generate STARK proof
     let proof = stark::prove(&mut trace, inputs.get_public_inputs(), &outputs, options);
-    return (outputs, program_hash, proof);
+    return Ok((outputs, program_hash, proof, cycles));
+}
+
+/// Executes the specified `program` split into segments of `segment_length` steps each, and
+/// returns the result together with program hash and a chain of STARK-based proofs of execution,
+/// one for each segment. Each segment is proven separately, and only the execution trace of a
+/// single segment is kept in memory at a time; so, this can be used to prove programs which are
+/// too long to be proven with a single proof.
+///
+/// Segment proofs are linked to each other via public segment states (see `SegmentState`); the
+/// stack at the end of each segment is revealed to the verifier even in zero-knowledge mode.
+/// Every segment is executed with the same number of stack registers: the stack is padded with
+/// zeros to the max depth reached by the program.
+/// Program hash of a segmented execution depends on `segment_length` (see `hash_segments()`).
+pub fn execute_segments(
+    program         : &[F128],
+    inputs          : &ProgramInputs<F128>,
+    num_outputs     : usize,
+    segment_length  : usize,
+    options         : &ProofOptions) -> Result<SegmentedExecution, String>
+{
+    validate_program(program, num_outputs)?;
+    if !segment_length.is_power_of_two() || segment_length < MIN_SEGMENT_LENGTH {
+        return Err(format!("segment length must be a power of 2 and at least {}, but was {}",
+            MIN_SEGMENT_LENGTH, segment_length));
+    }
+
+    let segments = split_program(program, segment_length);
+    let program_hash = hash_segments(&segments);
+    let extension_factor = options.domain_size(segment_length) / segment_length;
+
+    // split public tape and secret inputs between the segments; each segment consumes the next
+    // values from the public tape and secret inputs
+    let segment_inputs = split_inputs(&segments, inputs)?;
+
+    // a segment state holds all stack registers of a segment, and is shared by two adjacent
+    // segments; so, all segments must have the same number of stack registers. Since the number
+    // of registers can only grow from one segment to the next, the segments are first executed
+    // without being proven to find the number of registers after the last segment
+    let public_inputs = inputs.get_public_inputs();
+    let mut state = SegmentState::from_inputs(public_inputs);
+    let mut depth = public_inputs.len();
+    for (segment, inputs) in segments.iter().zip(segment_inputs.iter()) {
+        let (trace, next_depth) = stark::TraceTable::new_segment(segment, &state, depth, inputs, 1)?;
+        state = trace.get_segment_state(trace.unextended_length() - 1);
+        depth = next_depth;
+    }
+    let mut init_stack = public_inputs.to_vec();
+    init_stack.resize(state.stack().len(), F128::ZERO);
+
+    // execute and prove the segments one by one; each segment starts in the state in which the
+    // previous segment ended, and the first segment starts with the inputs padded with zeros
+    let mut state = SegmentState::from_inputs(&init_stack);
+    let mut depth = public_inputs.len();
+    let mut proofs = Vec::with_capacity(segments.len());
+    for (i, (segment, inputs)) in segments.iter().zip(segment_inputs.iter()).enumerate() {
+        let now = Instant::now();
+
+        let (mut trace, next_depth) = stark::TraceTable::new_segment(segment, &state, depth, inputs, extension_factor)?;
+        let proof = stark::prove_segment(&mut trace, &state, options);
+        debug!("Executed and proved segment {} of {} in {} ms", i + 1, segments.len(), now.elapsed().as_millis());
+
+        state = proof.state().clone();
+        depth = next_depth;
+        proofs.push(proof);
+    }
+
+    // the stack after the last segment holds the outputs
+    let outputs = state.stack()[..num_outputs].to_vec();
+    return Ok((outputs, program_hash, proofs));
+}
+
+/// Executes the specified `program` within the specified resource `limits` without generating
+/// a proof of execution, and returns the result together with the number of consumed cycles.
+/// This can be used to check the outputs and the cost of a program before proving it. Returns
+/// an error in the same cases as `execute_with_limits()` function.
+pub fn run(program: &[F128], inputs: &ProgramInputs<F128>, num_outputs: usize, limits: &ExecutionLimits) -> Result<(Vec<F128>, usize), String>
+{
+    let (program, cycles) = prepare_program(program, num_outputs, limits)?;
+
+    // execute the program without extending the execution trace
+    let trace = stark::TraceTable::new(&program, inputs, 1)?;
+
+    // copy the user stack state at the last step to return as output
+    let last_state = trace.get_state(trace.unextended_length() - 1);
+    let outputs = last_state.get_user_stack()[0..num_outputs].to_vec();
+
+    return Ok((outputs, cycles));
+}
+
+/// Estimates the cost of proving execution of the specified `program` against the provided
+/// `inputs` using the specified `options`. The program is executed to determine the shape of
+/// its execution trace, but no proof is generated. Returns an error if the program is malformed
+/// or fails during execution.
+pub fn estimate(program: &[F128], inputs: &ProgramInputs<F128>, options: &ProofOptions) -> Result<ProofEstimate, String>
+{
+    let (program, _) = prepare_program(program, 0, &ExecutionLimits::default())?;
+
+    // execute the program without extending the execution trace
+    let trace = stark::TraceTable::new(&program, inputs, 1)?;
+
+    return Ok(ProofEstimate::new::<F128>(trace.unextended_length(), trace.register_count(), options));
 }
 
 /// Verifies that if a program with the specified `program_hash` is executed with the 
-/// provided `public_inputs` and some secret inputs, the result is equal to the `outputs`.
-pub fn verify(program_hash: &[u8; 32], public_inputs: &[F128], outputs: &[F128], proof: &StarkProof<F128>) -> Result<bool, String>
+/// provided `public_inputs`, `public_tape`, and some secret inputs, the result is equal
+/// to the `outputs`.
+///
+/// The `public_tape` must contain exactly the values consumed by the program in the order in
+/// which they were read: boundary constraints check that the public tape accumulator at the last
+/// step of the trace is equal to the hash of the entire `public_tape`. So, a proof is rejected if
+/// the program consumed only a part of the tape. Since the verifier knows only the program hash,
+/// it cannot tell which part of the public data does not match; `Program::verify()` checks the
+/// length of the tape against the program and reports a mismatch explicitly.
+/// 
+/// The proof is accepted only if it was generated with options satisfying the default verifier
+/// policy (see `verify_with_policy()`).
+pub fn verify(program_hash: &[u8; 32], public_inputs: &[F128], public_tape: &[F128], outputs: &[F128], proof: &StarkProof<F128>) -> Result<bool, String>
 {
-    return stark::verify(program_hash, public_inputs, outputs, proof);
+    return verify_with_policy(program_hash, public_inputs, public_tape, outputs, proof, &VerifierPolicy::default());
+}
+
+/// Verifies execution of a program in the same way as `verify()` function, but accepts the proof
+/// only if it was generated with options satisfying the specified `policy`; otherwise, the proof
+/// is rejected before any other verification work is done.
+pub fn verify_with_policy(
+    program_hash    : &[u8; 32],
+    public_inputs   : &[F128],
+    public_tape     : &[F128],
+    outputs         : &[F128],
+    proof           : &StarkProof<F128>,
+    policy          : &VerifierPolicy) -> Result<bool, String>
+{
+    return stark::verify(program_hash, public_inputs, public_tape, outputs, proof, policy);
+}
+
+/// Verifies a batch of program executions; each item of the batch consists of a program hash,
+/// public inputs, public tape, outputs, and a proof in the same order as parameters of `verify()`
+/// function. Proofs are verified in parallel using all available CPU cores, and setup work is
+/// shared among proofs with the same trace length and options. Results are returned in the same
+/// order as the items, so that one invalid proof does not affect verification of other proofs.
+///
+/// Proofs are accepted only if they were generated with options satisfying the default verifier
+/// policy (see `verify_batch_with_policy()`).
+pub fn verify_batch(items: &[BatchItem<F128>]) -> Vec<Result<bool, String>>
+{
+    return verify_batch_with_policy(items, &VerifierPolicy::default());
+}
+
+/// Verifies a batch of program executions in the same way as `verify_batch()` function, but
+/// accepts only the proofs generated with options satisfying the specified `policy`.
+pub fn verify_batch_with_policy(items: &[BatchItem<F128>], policy: &VerifierPolicy) -> Vec<Result<bool, String>>
+{
+    return stark::verify_many(items, policy, 0);
+}
+
+/// Verifies that if a program with the specified `program_hash` is executed in segments with the
+/// provided `public_inputs`, `public_tape`, and some secret inputs, the result is equal to the
+/// `outputs`. The `segments` must be ordered in the same way as returned by `execute_segments()`.
+///
+/// Each segment proof is accepted only if it was generated with options satisfying the default
+/// verifier policy.
+pub fn verify_segments(program_hash: &[u8; 32], public_inputs: &[F128], public_tape: &[F128], outputs: &[F128], segments: &[SegmentProof<F128>]) -> Result<bool, String>
+{
+    return stark::verify_chain(program_hash, public_inputs, public_tape, outputs, segments, &VerifierPolicy::default());
 }
 
 /// Pads the program with the appropriate number of NOOPs to ensure that:
@@ -63,6 +259,164 @@ pub fn verify(program_hash: &[u8; 32], public_inputs: &[F128], outputs: &[F128],
 /// 3. The program terminates with a NOOP.
 pub fn pad_program<T: FiniteField>(program: &[T]) -> Vec<T> {
     let mut program = program.to_vec();
+    program.resize(get_padded_length(&program), T::from(opcodes::NOOP));
+    return program;
+}
+
+/// Returns a hash value of the program.
+pub fn hash_program<T: stark::Accumulator>(program: &[T]) -> [u8; 32] {
+    assert!(program.len().is_power_of_two(), "program length must be a power of 2");
+    assert!(program.len() >= MIN_TRACE_LENGTH, "program must consist of at least {} operations", MIN_TRACE_LENGTH);
+    assert!(program[0] == T::from(opcodes::BEGIN), "program must start with BEGIN operation");
+    assert!(program[program.len() - 1] == T::from(opcodes::NOOP), "program must end with NOOP operation");
+    return T::digest(&program[..(program.len() - 1)]);
+}
+
+/// Splits the program into segments of `segment_length` operations each. Every segment starts
+/// with BEGIN and ends with NOOP, and operations of the program are placed into the segments in
+/// order with the following rules:
+/// 1. Every operation is placed at the same step of the hash cycle as it would be executed at if
+///    the program were not split; the steps before the first operation are filled with NOOPs.
+/// 2. PUSH operation and the value it pushes onto the stack are always placed into the same segment.
+/// 3. The steps after the last operation of a segment are filled with NOOPs.
+///
+/// If the entire program fits into a single segment, the segment is the same as the program
+/// padded with NOOPs to `segment_length`.
+pub fn split_program<T: FiniteField>(program: &[T], segment_length: usize) -> Vec<Vec<T>> {
+    assert!(segment_length.is_power_of_two(), "segment length must be a power of 2");
+    assert!(segment_length >= MIN_SEGMENT_LENGTH, "segment length must be at least {}", MIN_SEGMENT_LENGTH);
+    assert!(program.len() > 1, "program length must be greater than 1");
+    assert!(program[0] == T::from(opcodes::BEGIN), "program must start with BEGIN operation");
+
+    let mut segments = Vec::new();
+    let mut i = 1;
+    loop {
+        let mut segment = vec![T::from(opcodes::NOOP); segment_length];
+        segment[0] = T::from(opcodes::BEGIN);
+
+        // operation at index i of the program is executed at step i when the program is not split
+        let mut step = 1 + (i - 1) % HASH_CYCLE_LENGTH;
+        while i < program.len() {
+            let num_ops = if program[i] == T::from(opcodes::PUSH) && i + 1 < program.len() { 2 } else { 1 };
+            if step + num_ops > segment_length - 1 { break; }
+            segment[step..(step + num_ops)].copy_from_slice(&program[i..(i + num_ops)]);
+            step += num_ops;
+            i += num_ops;
+        }
+        segments.push(segment);
+
+        if i >= program.len() { break; }
+    }
+
+    return segments;
+}
+
+/// Returns a hash value of a program split into `segments`. The program hash accumulator is
+/// carried over from one segment to the next; within each segment, operations are absorbed in
+/// the same way as for a complete program (see `hash_program()`).
+pub fn hash_segments<T: stark::Accumulator>(segments: &[Vec<T>]) -> [u8; 32] {
+    let mut state = vec![T::ZERO; T::STATE_WIDTH];
+    for segment in segments.iter() {
+        for i in 0..(segment.len() - 1) {
+            T::apply_round(&mut state, segment[i], i);
+        }
+    }
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(as_bytes(&state[..T::DIGEST_SIZE]));
+    return result;
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Splits the public tape and secret inputs of the `inputs` between the `segments` so that each
+/// segment receives exactly the values consumed by its operations; public inputs are not a part
+/// of the result. Returns an error if the inputs do not match the values consumed by the segments.
+fn split_inputs(segments: &[Vec<F128>], inputs: &ProgramInputs<F128>) -> Result<Vec<ProgramInputs<F128>>, String> {
+    let public_tape = inputs.get_public_tape();
+    let [secret_inputs_a, secret_inputs_b] = inputs.get_secret_inputs();
+    let (mut tape_idx, mut a_idx, mut b_idx) = (0, 0, 0);
+
+    let mut result = Vec::with_capacity(segments.len());
+    for (i, segment) in segments.iter().enumerate() {
+        let (num_tape, num_a, num_b) = count_consumed_inputs(segment);
+        if tape_idx + num_tape > public_tape.len() {
+            return Err(format!("ran out of public tape inputs in segment {}", i));
+        }
+        if a_idx + num_a > secret_inputs_a.len() || b_idx + num_b > secret_inputs_b.len() {
+            return Err(format!("ran out of secret inputs in segment {}", i));
+        }
+        result.push(ProgramInputs::with_tape(&[],
+            &public_tape[tape_idx..(tape_idx + num_tape)],
+            &secret_inputs_a[a_idx..(a_idx + num_a)],
+            &secret_inputs_b[b_idx..(b_idx + num_b)]));
+        tape_idx += num_tape;
+        a_idx += num_a;
+        b_idx += num_b;
+    }
+
+    if tape_idx < public_tape.len() {
+        return Err(String::from("not all public tape inputs have been consumed"));
+    }
+    if a_idx < secret_inputs_a.len() || b_idx < secret_inputs_b.len() {
+        return Err(String::from("not all secret inputs have been consumed"));
+    }
+
+    return Ok(result);
+}
+
+/// Returns the number of values consumed from the public tape, and from secret input tapes A
+/// and B by the operations of the specified segment.
+fn count_consumed_inputs(segment: &[F128]) -> (usize, usize, usize) {
+    let (mut num_tape, mut num_a, mut num_b) = (0, 0, 0);
+    let mut i = 0;
+    while i < segment.len() {
+        match segment[i].as_u8() {
+            opcodes::PUSH   => i += 1,
+            opcodes::READP  => num_tape += 1,
+            opcodes::READ   | opcodes::EXP | opcodes::BINACC => num_a += 1,
+            opcodes::READ2  | opcodes::CMP | opcodes::MPATH  => { num_a += 1; num_b += 1; },
+            _ => ()
+        }
+        i += 1;
+    }
+    return (num_tape, num_a, num_b);
+}
+
+/// Validates the `program` against the resource `limits` and pads it with NOOPs. Returns the
+/// padded program together with the number of cycles consumed by the program. Only operations
+/// of the original program count as consumed cycles; padding NOOPs are not counted, but they
+/// do count toward the length of the execution trace.
+fn prepare_program(program: &[F128], num_outputs: usize, limits: &ExecutionLimits) -> Result<(Vec<F128>, usize), String> {
+    validate_program(program, num_outputs)?;
+
+    // make sure the program can be executed within the limits
+    let cycles = program.len();
+    let trace_length = get_padded_length(program);
+    limits.check(cycles, trace_length)?;
+
+    // pad the program with the appropriate number of NOOPs
+    return Ok((pad_program(program), cycles));
+}
+
+/// Makes sure the `program` is well-formed and can produce `num_outputs` outputs.
+fn validate_program(program: &[F128], num_outputs: usize) -> Result<(), String> {
+    if program.len() <= 1 {
+        return Err(format!("expected a program with at last two operations, but received {}", program.len()));
+    }
+    if program[0] != F128::from(opcodes::BEGIN) {
+        return Err(String::from("a program must start with BEGIN operation"));
+    }
+    if num_outputs > MAX_OUTPUTS {
+        return Err(format!("cannot produce more than {} outputs, but requested {}", MAX_OUTPUTS, num_outputs));
+    }
+    return Ok(());
+}
+
+/// Returns the length of the program after it has been padded by `pad_program()` function;
+/// this is also the length of the execution trace of the program.
+fn get_padded_length<T: FiniteField>(program: &[T]) -> usize {
     let trace_length = if program.len() == program.len().next_power_of_two() {
         if program[program.len() - 1] == T::from(opcodes::NOOP) {
             program.len()
@@ -74,15 +428,5 @@ pub fn pad_program<T: FiniteField>(program: &[T]) -> Vec<T> {
     else {
         program.len().next_power_of_two()
     };
-    program.resize(cmp::max(trace_length, MIN_TRACE_LENGTH), T::from(opcodes::NOOP));
-    return program;
-}
-
-/// Returns a hash value of the program.
-pub fn hash_program<T: stark::Accumulator>(program: &[T]) -> [u8; 32] {
-    assert!(program.len().is_power_of_two(), "program length must be a power of 2");
-    assert!(program.len() >= MIN_TRACE_LENGTH, "program must consist of at least {} operations", MIN_TRACE_LENGTH);
-    assert!(program[0] == T::from(opcodes::BEGIN), "program must start with BEGIN operation");
-    assert!(program[program.len() - 1] == T::from(opcodes::NOOP), "program must end with NOOP operation");
-    return T::digest(&program[..(program.len() - 1)]);
+    return cmp::max(trace_length, MIN_TRACE_LENGTH);
 }
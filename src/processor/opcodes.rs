@@ -1,11 +1,11 @@
 pub const NOOP: u8     = 0b000_00_000;
 pub const CMP: u8      = 0b000_00_001;
 pub const BINACC: u8   = 0b000_00_010;
-//pub const ??: u8     = 0b000_00_011;
+pub const EXP: u8      = 0b000_00_011;
 pub const INV: u8      = 0b000_00_100;
 pub const NEG: u8      = 0b000_00_101;
 pub const NOT: u8      = 0b000_00_110;  // same as: PUSH 1 SWAP NEG ADD
-//pub const ???: u8    = 0b000_00_111;
+pub const ISZERO: u8   = 0b000_00_111;
 
 pub const PUSH: u8     = 0b000_01_000;
 pub const READ: u8     = 0b000_01_001;  // same as: READ2 DROP
@@ -14,7 +14,7 @@ pub const DUP: u8      = 0b000_01_011;  // same as: DUP2 SWAP DROP
 pub const DUP2: u8     = 0b000_01_100;
 pub const DUP4: u8     = 0b000_01_101;
 pub const PAD2: u8     = 0b000_01_110;  // same as: PUSH 0 DUP
-//pub const ???: u8    = 0b000_01_111;
+pub const READP: u8    = 0b000_01_111;
 
 pub const ASSERT: u8   = 0b000_10_000;
 pub const DROP: u8     = 0b000_10_001;
@@ -26,7 +26,7 @@ pub const CHOOSE: u8   = 0b000_10_110;
 pub const CHOOSE2: u8  = 0b000_10_111;
 
 pub const HASHR: u8    = 0b000_11_000;
-//pub const ???: u8    = 0b000_11_001;
+pub const MPATH: u8    = 0b000_11_001;
 pub const SWAP: u8     = 0b000_11_010;
 pub const SWAP2: u8    = 0b000_11_011;  // same as: ROLL4 ROLL4
 pub const SWAP4: u8    = 0b000_11_100;  // same as: ROLL8 ROLL8 ROLL8 ROLL8
@@ -44,6 +44,7 @@ pub mod f128 {
     pub const PUSH    : u128 = super::PUSH as u128;
     pub const READ    : u128 = super::READ as u128;
     pub const READ2   : u128 = super::READ2 as u128;
+    pub const READP   : u128 = super::READP as u128;
 
     // stack manipulation ops
     pub const DUP     : u128 = super::DUP as u128;
@@ -68,12 +69,15 @@ pub mod f128 {
     pub const INV     : u128 = super::INV as u128;
     pub const NEG     : u128 = super::NEG as u128;
     pub const NOT     : u128 = super::NOT as u128;
+    pub const EXP     : u128 = super::EXP as u128;
 
     // comparison ops
     pub const EQ      : u128 = super::EQ as u128;
+    pub const ISZERO  : u128 = super::ISZERO as u128;
     pub const CMP     : u128 = super::CMP as u128;
     pub const BINACC  : u128 = super::BINACC as u128;
 
     // crypto ops
     pub const HASHR   : u128 = super::HASHR as u128;
+    pub const MPATH   : u128 = super::MPATH as u128;
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use crate::math::{ F128 };
+use crate::stark::{ ProofOptions, ProgramInputs, StarkProof, VerifierPolicy };
+use super::ExecutionLimits;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// A program for Distaff VM padded with NOOPs (see `pad_program()`) together with its hash. A
+/// proof of execution of the program can be verified directly against the program; this makes
+/// sure that the proof is checked against the hash of the same program which was executed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    opcodes     : Vec<F128>,
+    hash        : [u8; 32],
+}
+
+// PROGRAM IMPLEMENTATION
+// ================================================================================================
+impl Program {
+
+    /// Pads the specified `program` with NOOPs and computes hash of the padded program. Returns
+    /// an error if the program is not well-formed (e.g. does not start with BEGIN operation).
+    pub fn new(program: &[F128]) -> Result<Program, String> {
+        super::validate_program(program, 0)?;
+        let opcodes = super::pad_program(program);
+        let hash = super::hash_program(&opcodes);
+        return Ok(Program { opcodes, hash });
+    }
+
+    /// Returns opcodes of the program padded with NOOPs.
+    pub fn opcodes(&self) -> &[F128] {
+        return &self.opcodes;
+    }
+
+    pub fn hash(&self) -> &[u8; 32] {
+        return &self.hash;
+    }
+
+    /// Returns the length of the execution trace of the program; this is the same as the
+    /// length of the padded program.
+    pub fn trace_length(&self) -> usize {
+        return self.opcodes.len();
+    }
+
+    // PROVING AND VERIFICATION
+    // --------------------------------------------------------------------------------------------
+
+    /// Executes the program with the specified `inputs` and returns `num_outputs` values from
+    /// the top of the stack together with STARK-based proof of execution (see `execute_with_limits()`).
+    /// Returns an error if the program cannot be executed within the default execution limits, if
+    /// it fails during execution (e.g. runs out of inputs), or if more than `MAX_OUTPUTS` outputs
+    /// are requested.
+    pub fn prove(&self, inputs: &ProgramInputs<F128>, num_outputs: usize, options: &ProofOptions) -> Result<(Vec<F128>, StarkProof<F128>), String> {
+        let limits = ExecutionLimits::default();
+        let (outputs, program_hash, proof, _) = super::execute_with_limits(&self.opcodes, inputs, num_outputs, options, &limits)?;
+
+        // the proof can be verified only against the hash of the executed program; so, if the hashes
+        // differ, the proof would not be accepted by the `verify()` method of this program
+        if program_hash != self.hash {
+            return Err(String::from("program hash computed during execution is different from program hash"));
+        }
+        return Ok((outputs, proof));
+    }
+
+    /// Verifies that executing this program with the provided `public_inputs`, `public_tape`,
+    /// and some secret inputs results in the `outputs` (see `verify()`). The proof is rejected
+    /// if the length of its execution trace is different from the length of the program.
+    pub fn verify(&self, public_inputs: &[F128], public_tape: &[F128], outputs: &[F128], proof: &StarkProof<F128>) -> Result<bool, String> {
+        return self.verify_with_policy(public_inputs, public_tape, outputs, proof, &VerifierPolicy::default());
+    }
+
+    /// Verifies execution of this program in the same way as `verify()` method, but accepts the
+    /// proof only if it was generated with options satisfying the specified `policy`. The proof
+    /// is also rejected if the number of values on the `public_tape` is different from the number
+    /// of values read from the tape by the program (the program must consume the entire tape).
+    pub fn verify_with_policy(
+        &self,
+        public_inputs   : &[F128],
+        public_tape     : &[F128],
+        outputs         : &[F128],
+        proof           : &StarkProof<F128>,
+        policy          : &VerifierPolicy) -> Result<bool, String>
+    {
+        if proof.trace_length() != self.trace_length() {
+            return Err(format!("expected a proof of execution trace of {} steps, but the proof is for {} steps",
+                self.trace_length(), proof.trace_length()));
+        }
+        let (tape_length, _, _) = super::count_consumed_inputs(&self.opcodes);
+        if public_tape.len() != tape_length {
+            return Err(format!("expected a public tape of {} values consumed by the program, but received {} values",
+                tape_length, public_tape.len()));
+        }
+        return super::verify_with_policy(&self.hash, public_inputs, public_tape, outputs, proof, policy);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::math::{ F128, FiniteField };
+use crate::stark::{ ProgramInputs };
+use super::opcodes::f128 as opcodes;
+
+// PROGRAM GENERATORS
+// ================================================================================================
+
+/// Returns a program which verifies a Merkle authentication path consisting of `n` nodes (the
+/// leaf, its sibling, and `n - 2` nodes above them) and outputs the root to which the path
+/// resolves. The nodes are hashed by HASHR operations; so, if the tree was built using
+/// `HashFunction::Rescue128`, the root is the same as computed by `MerkleTree`. This can be used
+/// to check openings of trace and constraint commitments of a proof inside the VM.
+///
+/// The root is returned in reverse order as 2 values from the top of the stack; inputs for the
+/// program can be built with `get_merkle_path_inputs()` function.
+pub fn generate_merkle_path_program(n: usize) -> Vec<F128> {
+    assert!(n >= 2, "authentication path must contain at least 2 nodes, but contained {}", n);
+
+    // the program starts by reading the index of the leaf node and the leaf node itself
+    // (represented by two field elements) and pushing them onto the stack. This part also
+    // pads the stack to prepare it for hashing, and executes MPATH operation which reads
+    // the first node of the authentication path and arranges the nodes for hashing.
+    let mut program = vec![
+        opcodes::BEGIN, opcodes::READ,  opcodes::READ2, opcodes::PAD2,
+        opcodes::PAD2,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::MPATH
+    ];
+
+    // this cycle of operation gets repeated once for each remaining node. It computes the
+    // hash of the nodes arranged by the previous MPATH operation, and then executes MPATH
+    // operation again to arrange the result together with the next node of the path. The
+    // NOOPs are needed to make sure each sequence of HASHR operations starts on a step
+    // which is a multiple of 16.
+    let level_sub = vec![
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::MPATH
+    ];
+
+    for _ in 0..(n - 2) {
+        program.extend_from_slice(&level_sub);
+    }
+
+    // at the end, we compute the last hash, move the result to the top of the stack, and
+    // make sure that all bits of the index have been consumed (i.e. the index is now 0).
+    program.extend_from_slice(&level_sub[..10]);
+    program.extend_from_slice(&[
+        opcodes::DROP4, opcodes::PUSH,  0,              opcodes::ROLL4,
+        opcodes::EQ,    opcodes::ASSERT
+    ]);
+
+    return program;
+}
+
+/// Converts Merkle authentication `path` for a leaf at the specified `index` into a set of
+/// inputs which can be consumed by the program created by `generate_merkle_path_program()`
+/// function. The path is expected in the same form as returned by `MerkleTree::prove()`; each
+/// node must consist of two valid field elements, which is always the case for trees built
+/// using `HashFunction::Rescue128`.
+pub fn get_merkle_path_inputs(path: &[[u8; 32]], index: usize) -> ProgramInputs<F128> {
+    assert!(path.len() >= 2, "authentication path must contain at least 2 nodes, but contained {}", path.len());
+    assert!(index < (1 << (path.len() - 1)), "index {} is too large for a path of {} nodes", index, path.len());
+
+    // push the index of the leaf node onto secret input tape A; MPATH operation will
+    // consume one bit of the index for each level of the tree
+    let mut a = vec![F128::from_usize(index)];
+    let mut b = Vec::with_capacity(path.len());
+
+    // push both elements of the leaf node and all remaining nodes of the path onto secret
+    // input tapes A and B
+    for node in path.iter() {
+        let (v0, v1) = (F128::from_bytes(&node[..16]), F128::from_bytes(&node[16..]));
+        assert!(v0 < F128::MODULUS && v1 < F128::MODULUS, "authentication path contains invalid field elements");
+        a.push(v0);
+        b.push(v1);
+    }
+
+    return ProgramInputs::new(&[], &a, &b);
+}
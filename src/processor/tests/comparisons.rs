@@ -21,7 +21,7 @@ fn eq_operations() {
     assert_eq!(expected_result, outputs);
     assert_eq!(program_hash, expected_hash);
 
-    let result = verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -59,7 +59,7 @@ fn cmp_operation() {
     assert_eq!(program_hash, expected_hash);
 
     // verify execution proof
-    let result = verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -94,7 +94,7 @@ fn binacc_operation() {
     assert_eq!(program_hash, expected_hash);
 
     // verify execution proof
-    let result = verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
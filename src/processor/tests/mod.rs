@@ -1,4 +1,5 @@
-use crate::{ ProofOptions, ProgramInputs, opcodes::f128 as opcodes, F128, FiniteField, Accumulator, Hasher };
+use rand::prelude::*;
+use crate::{ stark, MerkleTree, ProofOptions, ProgramInputs, StarkProof, SegmentState, SegmentProof, ExecutionLimits, VerifierPolicy, crypto::HashFunction, opcodes::f128 as opcodes, F128, FiniteField, Accumulator, Hasher, as_bytes };
 
 mod comparisons;
 
@@ -20,7 +21,7 @@ fn execute_verify() {
     assert_eq!(outputs, [3]);
     assert_eq!(program_hash, expected_hash);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -42,21 +43,24 @@ fn execute_verify_fail() {
     assert_eq!(outputs, [3]);
     assert_eq!(program_hash, expected_hash);
 
+    // public data is absorbed into the transcript, so tampering with it changes all
+    // pseudo-random challenges and the proof-of-work check fails first
+
     // wrong inputs
-    let result = super::verify(&program_hash, &[1, 1], &outputs, &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
+    let result = super::verify(&program_hash, &[1, 1], &[], &outputs, &proof);
+    let err_msg = format!("seed proof-of-work verification failed");
     assert_eq!(Err(err_msg), result);
 
     // wrong outputs
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &[5], &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &[5], &proof);
+    let err_msg = format!("seed proof-of-work verification failed");
     assert_eq!(Err(err_msg), result);
 
     // wrong program hash
     let mut program_hash2 = program_hash.clone();
     program_hash2[0] = 1;
-    let result = super::verify(&program_hash2, inputs.get_public_inputs(), &outputs, &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
+    let result = super::verify(&program_hash2, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    let err_msg = format!("seed proof-of-work verification failed");
     assert_eq!(Err(err_msg), result);
 }
 
@@ -78,7 +82,7 @@ fn stack_operations() {
     assert_eq!(outputs, [3, 6, 3, 6, 7, 11, 3, 6]);
     assert_eq!(program_hash, expected_hash);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -101,7 +105,7 @@ fn logic_operations() {
     assert_eq!(outputs, [5, 6, 7, 8, 0, 0, 0, 0]);
     assert_eq!(program_hash, expected_hash);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 
     // CHOOSE2
@@ -121,7 +125,7 @@ fn logic_operations() {
     assert_eq!(outputs, [7, 8, 0, 0, 0, 0, 0, 0]);
     assert_eq!(program_hash, expected_hash);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -162,7 +166,7 @@ fn math_operations() {
     assert_eq!(expected_result, outputs);
     assert_eq!(program_hash, expected_hash);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -191,7 +195,7 @@ fn hash_operations() {
     let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
     assert_eq!(expected_hash, outputs);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 
     // double hash
@@ -222,7 +226,7 @@ fn hash_operations() {
     let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
     assert_eq!(expected_hash, outputs);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
@@ -242,11 +246,10 @@ fn read_operations() {
     let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
     assert_eq!(vec![5, 4, 3, 2, 1], outputs);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
-
 #[test]
 fn assert_operation() {
     let program = [
@@ -267,8 +270,912 @@ fn assert_operation() {
     assert_eq!(expected_result, outputs);
     assert_eq!(program_hash, expected_hash);
 
-    let result = super::verify(&program_hash, inputs.get_public_inputs(), &outputs, &proof);
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+}
+
+// EXECUTION LIMITS AND ERRORS
+// ================================================================================================
+
+#[test]
+fn execute_with_limits() {
+    let program = &fibonacci_program()[..13];
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let num_outputs = 1;
+
+    // program within limits: 13 cycles and 16 steps
+    let limits = ExecutionLimits::new(13, 16);
+    let (outputs, cycles) = super::run(&program, &inputs, num_outputs, &limits).unwrap();
+    assert_eq!(outputs, [3]);
+    assert_eq!(cycles, 13);
+
+    let (outputs, program_hash, proof, cycles) = super::execute_with_limits(
+        &program, &inputs, num_outputs, &options, &limits).unwrap();
+    assert_eq!(outputs, [3]);
+    assert_eq!(cycles, 13);
+
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // cycle limit exceeded
+    let limits = ExecutionLimits::new(12, 16);
+    let result = super::execute_with_limits(&program, &inputs, num_outputs, &options, &limits);
+    assert_eq!(Err(String::from("program execution requires 13 cycles, but at most 12 cycles are allowed")),
+        result.map(|(outputs, _, _, cycles)| (outputs, cycles)));
+
+    // trace length limit exceeded
+    let mut program = program.to_vec();
+    program.resize(17, opcodes::NOOP);
+    let limits = ExecutionLimits::new(32, 16);
+    let result = super::run(&program, &inputs, num_outputs, &limits);
+    assert_eq!(Err(String::from("program execution requires trace of 32 steps, but at most 16 steps are allowed")),
+        result);
+}
+
+#[test]
+fn execute_runtime_errors() {
+    let options = ProofOptions::default();
+    let limits = ExecutionLimits::default();
+
+    // public tape is exhausted
+    let program = [opcodes::BEGIN, opcodes::READP, opcodes::READP, opcodes::ADD];
+    let inputs = ProgramInputs::with_tape(&[], &[1], &[], &[]);
+    let err_msg = String::from("ran out of public tape inputs at step 2");
+    assert_eq!(Err(err_msg.clone()), super::run(&program, &inputs, 1, &limits));
+    let result = super::execute_with_limits(&program, &inputs, 1, &options, &limits);
+    assert_eq!(Some(err_msg), result.err());
+
+    // secret input tape is exhausted
+    let program = [opcodes::BEGIN, opcodes::READ, opcodes::READ, opcodes::ADD];
+    let inputs = ProgramInputs::new(&[], &[1], &[]);
+    let err_msg = String::from("ran out of secret inputs at step 2");
+    assert_eq!(Err(err_msg.clone()), super::run(&program, &inputs, 1, &limits));
+    let result = super::execute_with_limits(&program, &inputs, 1, &options, &limits);
+    assert_eq!(Some(err_msg), result.err());
+
+    // stack underflow
+    let program = [opcodes::BEGIN, opcodes::ADD, opcodes::ADD];
+    let inputs = ProgramInputs::from_public(&[1, 2]);
+    let err_msg = String::from("stack underflow at step 2");
+    assert_eq!(Err(err_msg.clone()), super::run(&program, &inputs, 1, &limits));
+    let result = super::execute_with_limits(&program, &inputs, 1, &options, &limits);
+    assert_eq!(Some(err_msg), result.err());
+
+    // failed assertion
+    let program = [opcodes::BEGIN, opcodes::ASSERT, opcodes::ASSERT];
+    let inputs = ProgramInputs::from_public(&[1, 2]);
+    let err_msg = String::from("ASSERT failed at step 2");
+    assert_eq!(Err(err_msg.clone()), super::run(&program, &inputs, 1, &limits));
+    let result = super::execute_with_limits(&program, &inputs, 1, &options, &limits);
+    assert_eq!(Some(err_msg), result.err());
+
+    // not all inputs are consumed
+    let program = [opcodes::BEGIN, opcodes::READ, opcodes::ADD];
+    let inputs = ProgramInputs::new(&[1], &[2, 3], &[]);
+    let err_msg = String::from("not all secret inputs have been consumed");
+    assert_eq!(Err(err_msg.clone()), super::run(&program, &inputs, 1, &limits));
+    let result = super::estimate(&program, &inputs, &options);
+    assert_eq!(Some(err_msg), result.err());
+}
+
+#[test]
+fn estimate_proof() {
+    let mut program = vec![opcodes::BEGIN];
+    for _ in 0..100 {
+        program.extend_from_slice(&[opcodes::SWAP, opcodes::DUP2, opcodes::DROP, opcodes::ADD]);
+    }
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    let estimate = super::estimate(&program, &inputs, &options).unwrap();
+    let (_, _, proof) = super::execute(&program, &inputs, 1, &options);
+
+    assert_eq!(proof.trace_length(), estimate.trace_length);
+    assert_eq!(proof.trace_evaluations()[0].len(), estimate.register_count);
+    assert_eq!(proof.domain_size(), estimate.domain_size);
+    assert_eq!(proof.degree_proof().layers.len(), estimate.fri_layers);
+
+    // proof size should be estimated within 10%
+    let proof_size = proof.to_bytes().len();
+    assert!(estimate.proof_size * 10 > proof_size * 9 && estimate.proof_size * 10 < proof_size * 11,
+        "estimated proof size {} differs from actual proof size {}", estimate.proof_size, proof_size);
+
+    // malformed programs are rejected
+    let result = super::estimate(&[opcodes::NOOP, opcodes::ADD], &inputs, &options);
+    assert_eq!(Some(String::from("a program must start with BEGIN operation")), result.err());
+}
+
+// VERIFICATION
+// ================================================================================================
+
+#[test]
+fn verify_with_policy() {
+    let program = fibonacci_program();
+
+    // generate a proof with options which provide very low security
+    let options = ProofOptions::new(32, 1, 0, HashFunction::Blake3);
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+
+    // the proof is rejected by the default policy
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    let err_msg = format!("proof options are not acceptable: proof security level of 2 bits is below the required 120 bits");
+    assert_eq!(Err(err_msg), result);
+
+    // the proof is accepted by a policy which does not require a high security level
+    let policy = VerifierPolicy::with_security_level(1);
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+
+    // the proof is rejected by a policy which requires more queries
+    let policy = VerifierPolicy::new(1, 16..=256, 2..=128, 0..=32, &[HashFunction::Blake3]);
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    let err_msg = format!("proof options are not acceptable: number of queries 1 is not within the allowed range [2, 128]");
+    assert_eq!(Err(err_msg), result);
+}
+
+#[test]
+fn verify_batch() {
+    let program1 = fibonacci_program();
+    let mut program2 = vec![opcodes::BEGIN];
+    for _ in 0..7 {
+        program2.extend_from_slice(&[opcodes::SWAP, opcodes::DUP2, opcodes::DROP, opcodes::ADD]);
+    }
+
+    // proofs of two different shapes, and with different options
+    let options1 = ProofOptions::new(32, 8, 0, HashFunction::Blake3);
+    let options2 = ProofOptions::new(16, 8, 0, HashFunction::Blake3);
+    let inputs1 = ProgramInputs::from_public(&[1, 0]);
+    let inputs2 = ProgramInputs::from_public(&[3, 5]);
+    let (outputs1, hash1, proof1) = super::execute(&program1, &inputs1, 1, &options1);
+    let (outputs2, hash2, proof2) = super::execute(&program2, &inputs2, 1, &options1);
+    let (outputs3, hash3, proof3) = super::execute(&program1, &inputs2, 1, &options2);
+    assert_ne!(proof1.trace_length(), proof2.trace_length());
+
+    // low-security proof which is not accepted by the policy
+    let options4 = ProofOptions::new(32, 1, 0, HashFunction::Blake3);
+    let (outputs4, hash4, proof4) = super::execute(&program1, &inputs1, 1, &options4);
+
+    let items: Vec<(&[u8; 32], &[F128], &[F128], &[F128], &StarkProof<F128>)> = vec![
+        (&hash1, inputs1.get_public_inputs(), &[], &outputs1, &proof1),
+        (&hash2, inputs2.get_public_inputs(), &[], &outputs2, &proof2),
+        (&hash1, inputs1.get_public_inputs(), &[], &[5], &proof1),       // wrong outputs
+        (&hash3, inputs2.get_public_inputs(), &[], &outputs3, &proof3),
+        (&hash2, inputs2.get_public_inputs(), &[], &outputs2, &proof1),  // wrong proof
+        (&hash4, inputs1.get_public_inputs(), &[], &outputs4, &proof4),
+        (&hash1, inputs1.get_public_inputs(), &[], &outputs1, &proof1),
+    ];
+
+    // results match results of verifying each proof individually
+    let policy = VerifierPolicy::with_security_level(8);
+    let expected = items.iter()
+        .map(|&(hash, inputs, tape, outputs, proof)| super::verify_with_policy(hash, inputs, tape, outputs, proof, &policy))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(true), expected[0]);
+    assert_eq!(Ok(true), expected[1]);
+    assert!(expected[2].is_err());
+    assert_eq!(Ok(true), expected[3]);
+    assert!(expected[4].is_err());
+    assert_eq!(Err(String::from("proof options are not acceptable: proof security level of 2 bits is below the required 8 bits")), expected[5]);
+    assert_eq!(Ok(true), expected[6]);
+
+    assert_eq!(expected, super::verify_batch_with_policy(&items, &policy));
+    for num_threads in 1..=items.len() + 1 {
+        assert_eq!(expected, stark::verify_many(&items, &policy, num_threads));
+    }
+
+    // by default, the proofs are checked against the default policy
+    let expected = items.iter()
+        .map(|&(hash, inputs, tape, outputs, proof)| super::verify(hash, inputs, tape, outputs, proof))
+        .collect::<Vec<_>>();
+    assert_eq!(expected, super::verify_batch(&items));
+
+    // an empty batch is valid
+    assert!(super::verify_batch(&[]).is_empty());
+}
+
+#[test]
+fn verify_malformed_proofs() {
+    let program = fibonacci_program();
+
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let policy = VerifierPolicy::with_security_level(0);
+
+    // proofs without FRI layers (domain of 256) and with FRI layers (domain of 512)
+    for &(extension_factor, max_remainder_degree) in [(16, 127), (32, 63)].iter() {
+        let options = ProofOptions::new(extension_factor, 4, 0, HashFunction::Blake3)
+            .with_max_remainder_degree(max_remainder_degree);
+        let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+        let proof_bytes = bincode::serialize(&proof).unwrap();
+
+        let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(),
+            inputs.get_public_tape(), &outputs, &proof, &policy);
+        assert_eq!(Ok(true), result);
+
+        // randomly corrupt, truncate, and extend the proof; verification must never panic
+        let mut rng = StdRng::from_seed([1u8; 32]);
+        for _ in 0..1000 {
+            let mut bad_bytes = proof_bytes.clone();
+            match rng.gen_range(0, 4) {
+                0 => bad_bytes.truncate(rng.gen_range(0, proof_bytes.len())),
+                1 => bad_bytes.insert(rng.gen_range(0, proof_bytes.len()), rng.gen()),
+                _ => for _ in 0..rng.gen_range(1, 4) {
+                    let idx = rng.gen_range(0, proof_bytes.len());
+                    bad_bytes[idx] = rng.gen();
+                }
+            }
+
+            if let Ok(proof) = bincode::deserialize::<StarkProof<F128>>(&bad_bytes) {
+                let _ = super::verify_with_policy(&program_hash, inputs.get_public_inputs(),
+                    inputs.get_public_tape(), &outputs, &proof, &policy);
+            }
+        }
+    }
+}
+
+// PROGRAMS
+// ================================================================================================
+
+#[test]
+fn program_prove_verify() {
+    let program = super::Program::new(&fibonacci_program()[..13]).unwrap();
+    assert_eq!(16, program.trace_length());
+    assert_eq!(&super::pad_program(program.opcodes()), program.opcodes());
+    assert_eq!(&super::hash_program(program.opcodes()), program.hash());
+
+    let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3);
+    let policy = VerifierPolicy::with_security_level(8);
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, proof) = program.prove(&inputs, 1, &options).unwrap();
+    assert_eq!(outputs, [3]);
+
+    let result = program.verify_with_policy(inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+
+    // the proof is checked against the default policy
+    let result = program.verify(inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert!(result.unwrap_err().starts_with("proof options are not acceptable"));
+
+    // a proof of a different program is rejected
+    let mut opcodes = program.opcodes().to_vec();
+    opcodes[1] = opcodes::NOOP;
+    let program2 = super::Program::new(&opcodes).unwrap();
+    assert_ne!(program.hash(), program2.hash());
+    let result = program2.verify_with_policy(inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert!(result.is_err());
+
+    // a proof of a program with a different length is rejected before any other checks
+    let mut opcodes = program.opcodes().to_vec();
+    opcodes.resize(32, opcodes::NOOP);
+    let program3 = super::Program::new(&opcodes).unwrap();
+    let result = program3.verify_with_policy(inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    let err_msg = format!("expected a proof of execution trace of 32 steps, but the proof is for 16 steps");
+    assert_eq!(Err(err_msg), result);
+
+    // a program which does not start with BEGIN is rejected
+    let result = super::Program::new(&[opcodes::NOOP, opcodes::ADD]);
+    assert_eq!(Err(String::from("a program must start with BEGIN operation")), result);
+
+    // requesting too many outputs results in an error
+    let result = program.prove(&inputs, stark::MAX_OUTPUTS + 1, &options);
+    assert!(result.is_err());
+
+    // execution failures are returned as errors
+    let program = super::Program::new(&[opcodes::BEGIN, opcodes::READ, opcodes::ADD]).unwrap();
+    let inputs = ProgramInputs::new(&[1], &[], &[]);
+    let result = program.prove(&inputs, 1, &options);
+    assert_eq!(Some(String::from("ran out of secret inputs at step 1")), result.err());
+
+    let inputs = ProgramInputs::new(&[1], &[2, 3], &[]);
+    let result = program.prove(&inputs, 1, &options);
+    assert_eq!(Some(String::from("not all secret inputs have been consumed")), result.err());
+}
+
+#[test]
+fn split_program() {
+    let mut program = vec![opcodes::BEGIN];
+    program.resize(30, opcodes::ADD);
+    program.extend_from_slice(&[opcodes::PUSH, 3, opcodes::DROP]);
+
+    // PUSH does not fit into the first segment together with its value, and the second segment
+    // starts at the same step of the hash cycle as the PUSH would be executed at
+    let segments = super::split_program(&program, 32);
+    assert_eq!(2, segments.len());
+    assert_eq!(&program[..30], &segments[0][..30]);
+    assert_eq!(vec![opcodes::NOOP; 2], &segments[0][30..]);
+
+    let mut expected = vec![opcodes::NOOP; 32];
+    expected[0] = opcodes::BEGIN;
+    expected[14..17].copy_from_slice(&[opcodes::PUSH, 3, opcodes::DROP]);
+    assert_eq!(expected, segments[1]);
+
+    // segment length must be a power of 2
+    let options = ProofOptions::default();
+    let result = super::execute_segments(&program, &ProgramInputs::none(), 1, 48, &options);
+    assert_eq!(Err(String::from("segment length must be a power of 2 and at least 32, but was 48")), result.map(|_| ()));
+}
+
+#[test]
+fn execute_verify_segments() {
+    let program = [
+        opcodes::BEGIN, opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::DROP4, opcodes::NOOP,
+        opcodes::PAD2,  opcodes::DUP2,  opcodes::READ,  opcodes::PUSH,
+            7,          opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::DROP4,
+        opcodes::READ2, opcodes::READP, opcodes::MUL,   opcodes::ADD,
+    ];
+    let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3);
+    let inputs = ProgramInputs::with_tape(&[0, 0, 4, 3, 2, 1], &[5], &[1, 2], &[3]);
+    let num_outputs = 4;
+    let policy = VerifierPolicy::with_security_level(0);
+
+    // hashing is executed in the same way regardless of how the program is split
+    let (expected_outputs, _, _) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, program_hash, segments) = super::execute_segments(&program, &inputs, num_outputs, 32, &options).unwrap();
+    assert_eq!(expected_outputs, outputs);
+    assert_eq!(3, segments.len());
+    assert_eq!(program_hash, super::hash_segments(&super::split_program(&program, 32)));
+
+    let public_inputs = inputs.get_public_inputs();
+    let public_tape = inputs.get_public_tape();
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &outputs, &segments, &policy);
+    assert_eq!(Ok(true), result);
+
+    // segment proofs can be serialized and de-serialized
+    let segment_bytes = bincode::serialize(&segments).unwrap();
+    let segments: Vec<SegmentProof<F128>> = bincode::deserialize(&segment_bytes).unwrap();
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &outputs, &segments, &policy);
+    assert_eq!(Ok(true), result);
+
+    // wrong outputs, public tape, or program hash
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &[1, 2], &segments, &policy);
+    assert_eq!(Err(String::from("stack of the last segment does not match the outputs")), result);
+
+    let result = stark::verify_chain(&program_hash, public_inputs, &[6], &outputs, &segments, &policy);
+    assert_eq!(Err(String::from("public tape hash of the last segment does not match the public tape")), result);
+
+    let result = stark::verify_chain(&[1u8; 32], public_inputs, public_tape, &outputs, &segments, &policy);
+    assert_eq!(Err(String::from("program hash of the last segment does not match the program hash")), result);
+
+    // segments which do not form a chain
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &outputs, &segments[1..], &policy);
+    assert!(result.unwrap_err().starts_with("verification of segment 0 failed"));
+
+    let mut reordered = segments.clone();
+    reordered.swap(1, 2);
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &outputs, &reordered, &policy);
+    assert!(result.unwrap_err().starts_with("verification of segment 1 failed"));
+
+    // all segments have the same number of stack registers, and segment states must hold values
+    // of all of them; otherwise, values not bound by the states could change between segments
+    let stack_depth = segments[0].state().stack().len();
+    assert!(segments.iter().all(|segment| segment.state().stack().len() == stack_depth));
+
+    let mut truncated = segments.clone();
+    let state = segments[0].state();
+    let state = SegmentState::new(state.op_acc(), state.tape_acc(), &state.stack()[..(stack_depth - 1)]);
+    truncated[0] = SegmentProof::new(state, segments[0].proof().clone());
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &outputs, &truncated, &policy);
+    let err_msg = format!("verification of segment 0 failed: expected {} stack values in segment states, but received {} and {}",
+        stack_depth, stack_depth, stack_depth - 1);
+    assert_eq!(Err(err_msg), result);
+
+    // a program executed as a single segment has the same hash as the program itself
+    let (outputs, program_hash, segments) = super::execute_segments(&program, &inputs, num_outputs, 64, &options).unwrap();
+    let (_, expected_hash, _) = super::execute(&program, &inputs, num_outputs, &options);
+    assert_eq!(1, segments.len());
+    assert_eq!(expected_hash, program_hash);
+    let result = stark::verify_chain(&program_hash, public_inputs, public_tape, &outputs, &segments, &policy);
+    assert_eq!(Ok(true), result);
+}
+
+// PROOF OPTIONS
+// ================================================================================================
+
+#[test]
+fn execute_verify_hash_functions() {
+    let program = fibonacci_program();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    let hash_functions = [
+        HashFunction::Blake3, HashFunction::Sha3, HashFunction::Poseidon, HashFunction::Rescue, HashFunction::GMiMC,
+        HashFunction::Rescue128
+    ];
+    for &hash_fn in hash_functions.iter() {
+        let options = ProofOptions::new(32, 8, 4, hash_fn);
+        let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+
+        // the proof survives serialization and is accepted by a policy which allows the hash function
+        let proof_bytes = bincode::serialize(&proof).unwrap();
+        let proof = bincode::deserialize::<StarkProof<F128>>(&proof_bytes).unwrap();
+        assert_eq!(hash_fn, proof.options().hash_function());
+
+        let policy = VerifierPolicy::new(0, 16..=256, 1..=128, 0..=32, &[hash_fn]);
+        let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+        assert_eq!(Ok(true), result);
+    }
+}
+
+#[test]
+fn execute_verify_folding_factors() {
+    let program = fibonacci_program();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let policy = VerifierPolicy::with_security_level(0);
+
+    for &(folding_factor, max_remainder_degree) in [(2, 7), (4, 15), (8, 15), (16, 31)].iter() {
+        let options = ProofOptions::new(64, 8, 0, HashFunction::Blake3)
+            .with_folding_factor(folding_factor)
+            .with_max_remainder_degree(max_remainder_degree);
+        let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+        assert_eq!(options.num_fri_layers(proof.domain_size()), proof.degree_proof().layers.len());
+
+        // the proof survives both encodings, and the estimate matches its shape
+        let proof = bincode::deserialize::<StarkProof<F128>>(&bincode::serialize(&proof).unwrap()).unwrap();
+        let proof = StarkProof::<F128>::from_bytes(&proof.to_bytes()).unwrap();
+        let estimate = super::estimate(&program, &inputs, &options).unwrap();
+        assert_eq!(proof.degree_proof().layers.len(), estimate.fri_layers);
+
+        let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+        assert_eq!(Ok(true), result);
+    }
+}
+
+#[test]
+fn execute_verify_merkle_caps() {
+    let program = fibonacci_program();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let policy = VerifierPolicy::with_security_level(0);
+
+    for &(cap_height, zero_knowledge) in [(1, false), (4, false), (8, false), (4, true)].iter() {
+        let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3)
+            .with_merkle_cap_height(cap_height)
+            .with_zero_knowledge(zero_knowledge);
+        let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+        assert_eq!(1 << cap_height, proof.trace_cap().len());
+
+        // trees which are not deep enough are committed to by the parents of their leaves
+        for layer in proof.degree_proof().layers.iter() {
+            assert_eq!(1 << u8::min(cap_height, layer.depth - 1), layer.cap.len());
+        }
+
+        // the proof survives both encodings
+        let proof = bincode::deserialize::<StarkProof<F128>>(&bincode::serialize(&proof).unwrap()).unwrap();
+        let proof = StarkProof::<F128>::from_bytes(&proof.to_bytes()).unwrap();
+
+        let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+        assert_eq!(Ok(true), result);
+    }
+}
+
+#[test]
+fn execute_verify_low_memory() {
+    let program = [
+        opcodes::BEGIN, opcodes::READ,  opcodes::READ2, opcodes::NOOP,
+        opcodes::PUSH,      5,          opcodes::SWAP,  opcodes::DUP2,
+        opcodes::DROP,  opcodes::ADD,   opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+    ];
+    let inputs = ProgramInputs::new(&[1], &[2, 3], &[4]);
+    let policy = VerifierPolicy::with_security_level(0);
+
+    // without zero-knowledge, the proof does not depend on prover settings
+    let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3);
+    let (_, _, proof) = super::execute(&program, &inputs, 2, &options);
+    for &num_threads in [1, 3].iter() {
+        let options = options.clone().with_low_memory(true).with_num_threads(num_threads);
+        let (_, _, lean_proof) = super::execute(&program, &inputs, 2, &options);
+        assert_eq!(proof.to_bytes(), lean_proof.to_bytes());
+    }
+
+    // zero-knowledge proofs are randomized, but must still be valid
+    let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3)
+        .with_zero_knowledge(true)
+        .with_low_memory(true);
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, 2, &options);
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+}
+
+#[test]
+fn execute_verify_zero_knowledge() {
+    let program = [
+        opcodes::BEGIN, opcodes::READ,  opcodes::READ2, opcodes::NOOP,
+        opcodes::PUSH,      5,          opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+    ];
+
+    let options = ProofOptions::new(32, 8, 4, HashFunction::Blake3).with_zero_knowledge(true);
+    let inputs = ProgramInputs::new(&[1], &[2, 3], &[4]);
+    let num_outputs = 5;
+    let policy = VerifierPolicy::with_security_level(0);
+
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    assert_eq!(vec![5, 4, 3, 2, 1], outputs);
+    assert_eq!(16, proof.trace_length());
+    assert_eq!(options.domain_size(16), proof.domain_size());
+
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+
+    // proofs of the same execution are randomized
+    let (_, _, proof2) = super::execute(&program, &inputs, num_outputs, &options);
+    assert!(proof.trace_cap() != proof2.trace_cap());
+    assert!(proof.constraint_cap() != proof2.constraint_cap());
+
+    // the proof can be serialized and encoded
+    let proof_bytes = bincode::serialize(&proof).unwrap();
+    let proof = bincode::deserialize::<StarkProof<F128>>(&proof_bytes).unwrap();
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+
+    let proof = StarkProof::<F128>::from_bytes(&proof.to_bytes()).unwrap();
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof, &policy);
+    assert_eq!(Ok(true), result);
+
+    // proof size should be estimated within 10%
+    let estimate = super::estimate(&program, &inputs, &options).unwrap();
+    assert_eq!(proof.domain_size(), estimate.domain_size);
+    let proof_size = proof.to_bytes().len();
+    assert!(estimate.proof_size * 10 > proof_size * 9 && estimate.proof_size * 10 < proof_size * 11,
+        "estimated proof size {} differs from actual proof size {}", estimate.proof_size, proof_size);
+
+    // zero-knowledge flag (the last byte of proof options) is cleared
+    let mut bad_bytes = proof_bytes.clone();
+    let zk_flag_idx = bad_bytes.len() - 1;
+    bad_bytes[zk_flag_idx] = 0;
+    let result = bincode::deserialize::<StarkProof<F128>>(&bad_bytes);
+    assert_eq!("proof contains blinding values, but zero-knowledge mode is not enabled",
+        result.err().unwrap().to_string());
+
+    // the last constraint salt (right before proof options) is wrong
+    let mut bad_bytes = proof_bytes.clone();
+    let salt_idx = bad_bytes.len() - 9;
+    bad_bytes[salt_idx] ^= 1;
+    let bad_proof = bincode::deserialize::<StarkProof<F128>>(&bad_bytes).unwrap();
+    let result = super::verify_with_policy(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &bad_proof, &policy);
+    assert_eq!(Err(String::from("verification of constraint Merkle proof failed")), result);
+}
+
+// PROOF SERIALIZATION
+// ================================================================================================
+
+#[test]
+fn deserialize_proof() {
+    let program = fibonacci_program();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+
+    // a valid proof can be read back and verified
+    let proof_bytes = bincode::serialize(&proof).unwrap();
+    let proof = bincode::deserialize::<StarkProof<F128>>(&proof_bytes).unwrap();
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // truncated proof
+    let result = bincode::deserialize::<StarkProof<F128>>(&proof_bytes[..(proof_bytes.len() - 1)]);
+    assert!(result.is_err());
+
+    // domain depth (immediately after the trace cap, which is a single root) is too large
+    let mut bad_bytes = proof_bytes.clone();
+    bad_bytes[8 + 32] = 60;
+    let result = bincode::deserialize::<StarkProof<F128>>(&bad_bytes);
+    assert_eq!("domain depth 60 is not within the valid range [9, 32]", result.err().unwrap().to_string());
+
+    // proof options (the last 8 bytes) are invalid
+    let mut bad_bytes = proof_bytes.clone();
+    let num_queries_idx = bad_bytes.len() - 7;
+    bad_bytes[num_queries_idx] = 0;
+    let result = bincode::deserialize::<StarkProof<F128>>(&bad_bytes);
+    assert_eq!("number of queries 0 is not within the valid range [1, 128]", result.err().unwrap().to_string());
+
+    // the last remainder value (right before the nonce, blinding values, and options) is not
+    // a valid field element
+    let mut bad_bytes = proof_bytes.clone();
+    let value_end = bad_bytes.len() - 16;
+    bad_bytes[(value_end - 16)..value_end].copy_from_slice(&[0xFF; 16]);
+    let result = bincode::deserialize::<StarkProof<F128>>(&bad_bytes);
+    assert_eq!("FRI remainder contains invalid field elements", result.err().unwrap().to_string());
+}
+
+#[test]
+fn encode_proof() {
+    let program = fibonacci_program();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, 1, &options);
+
+    // the proof can be decoded and verified, and the encoding is smaller than bincode encoding
+    let proof_bytes = proof.to_bytes();
+    assert!(proof_bytes.len() < bincode::serialize(&proof).unwrap().len());
+    assert_eq!(b"DSTF", &proof_bytes[..4]);
+    assert_eq!(1, proof_bytes[4]);
+
+    let proof = StarkProof::<F128>::from_bytes(&proof_bytes).unwrap();
+    assert_eq!(proof_bytes, proof.to_bytes());
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // invalid magic value
+    let mut bad_bytes = proof_bytes.clone();
+    bad_bytes[0] = b'X';
+    let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+    assert_eq!(Err(String::from("proof does not start with a valid magic value")), result.map(|_| ()));
+
+    // unsupported versions
+    for &version in [0, 2].iter() {
+        let mut bad_bytes = proof_bytes.clone();
+        bad_bytes[4] = version;
+        let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+        let err_msg = format!("proof format version {} is not supported", version);
+        assert_eq!(Err(err_msg), result.map(|_| ()));
+    }
+
+    // invalid options
+    let mut bad_bytes = proof_bytes.clone();
+    bad_bytes[6] = 0;
+    let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+    let err_msg = "proof options are not valid: number of queries 0 is not within the valid range [1, 128]";
+    assert_eq!(Err(String::from(err_msg)), result.map(|_| ()));
+
+    // truncated and extended proofs
+    let result = StarkProof::<F128>::from_bytes(&proof_bytes[..(proof_bytes.len() - 1)]);
+    assert_eq!(Err(String::from("proof ended unexpectedly")), result.map(|_| ()));
+
+    let mut bad_bytes = proof_bytes.clone();
+    bad_bytes.push(0);
+    let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+    assert_eq!(Err(String::from("proof contains 1 unexpected trailing bytes")), result.map(|_| ()));
+
+    // optional extension records (even tags) are skipped, and are not written back
+    let body = &proof_bytes[..(proof_bytes.len() - 1)];
+    let ext_bytes = [body, &[2, 2, 1, 9, 4, 3, 5, 6, 7]].concat();
+    let proof = StarkProof::<F128>::from_bytes(&ext_bytes).unwrap();
+    assert_eq!(proof_bytes, proof.to_bytes());
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // required extension records (odd tags) are rejected, and so are records out of order
+    let bad_bytes = [body, &[1, 1, 0]].concat();
+    let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+    let err_msg = "proof contains an unsupported required extension record 1";
+    assert_eq!(Err(String::from(err_msg)), result.map(|_| ()));
+
+    let bad_bytes = [body, &[2, 4, 0, 2, 0]].concat();
+    let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+    assert_eq!(Err(String::from("proof extension records are not ordered by tag")), result.map(|_| ()));
+
+    let bad_bytes = [body, &[1, 2, 5, 0]].concat();
+    let result = StarkProof::<F128>::from_bytes(&bad_bytes);
+    assert_eq!(Err(String::from("proof ended unexpectedly")), result.map(|_| ()));
+}
+
+// RECURSIVE VERIFICATION
+// ================================================================================================
+
+#[test]
+fn recursion_merkle_path() {
+    // build a tree using the hash function of the VM; each leaf is a pair of field elements
+    let leaves = (0..16u128).map(|i| {
+        let mut leaf = [0u8; 32];
+        leaf.copy_from_slice(as_bytes(&[i, i * i]));
+        leaf
+    }).collect::<Vec<[u8; 32]>>();
+    let tree = MerkleTree::new(leaves, HashFunction::Rescue128);
+
+    // the program resolves authentication paths to the root of the tree
+    let limits = ExecutionLimits::default();
+    for &index in [0, 5, 10, 15].iter() {
+        let path = tree.prove(index);
+        let program = super::recursion::generate_merkle_path_program(path.len());
+        let inputs = super::recursion::get_merkle_path_inputs(&path, index);
+        let (mut outputs, _) = super::run(&program, &inputs, 2, &limits).unwrap();
+        outputs.reverse();
+        assert_eq!(&tree.root()[..], as_bytes(&outputs));
+    }
+
+    // a path checked for a wrong index does not resolve to the root
+    let path = tree.prove(5);
+    let program = super::recursion::generate_merkle_path_program(path.len());
+    let inputs = super::recursion::get_merkle_path_inputs(&path, 4);
+    let (mut outputs, _) = super::run(&program, &inputs, 2, &limits).unwrap();
+    outputs.reverse();
+    assert_ne!(&tree.root()[..], as_bytes(&outputs));
+}
+
+// INPUT OPERATIONS
+// ================================================================================================
+
+#[test]
+fn public_tape_operations() {
+    let program = [
+        opcodes::BEGIN, opcodes::READP, opcodes::READP, opcodes::ADD,
+        opcodes::PUSH,  opcodes::READP, opcodes::READP, opcodes::MUL,
+        opcodes::ADD,   opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+    ];
+    let expected_hash = <F128 as Accumulator>::digest(&program[..(program.len() - 1)]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::with_tape(&[1], &[2, 3, 4], &[], &[]);
+    let num_outputs = 2;
+
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    assert_eq!(outputs, [5 + opcodes::READP * 4, 1]);
+    assert_eq!(program_hash, expected_hash);
+
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+
+    // wrong public tape
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), &[2, 3, 5], &outputs, &proof);
+    let err_msg = format!("seed proof-of-work verification failed");
+    assert_eq!(Err(err_msg), result);
+
+    // public tape with values in different order
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), &[3, 2, 4], &outputs, &proof);
+    let err_msg = format!("seed proof-of-work verification failed");
+    assert_eq!(Err(err_msg), result);
+}
+
+#[test]
+#[should_panic]
+fn public_tape_operations_panic() {
+    let program = [
+        opcodes::BEGIN, opcodes::READP, opcodes::READP, opcodes::NOOP,
+    ];
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::with_tape(&[], &[1], &[], &[]);
+    let num_outputs = 1;
+
+    // ran out of public tape inputs
+    super::execute(&program, &inputs, num_outputs, &options);
+}
+
+#[test]
+fn public_tape_consumption() {
+    let program = [opcodes::BEGIN, opcodes::READP, opcodes::READP, opcodes::ADD];
+
+    let options = ProofOptions::default();
+    let limits = ExecutionLimits::default();
+    let inputs = ProgramInputs::with_tape(&[], &[2, 3], &[], &[]);
+
+    // the prover refuses to execute a program which does not consume the entire tape
+    let partial_inputs = ProgramInputs::with_tape(&[], &[2, 3, 4], &[], &[]);
+    let result = super::execute_with_limits(&program, &partial_inputs, 1, &options, &limits);
+    assert_eq!(Some(String::from("not all public tape inputs have been consumed")), result.err());
+
+    // a proof of execution which consumed only a part of the tape is rejected
+    let (outputs, program_hash, proof, _) = super::execute_with_limits(&program, &inputs, 1, &options, &limits).unwrap();
+    assert_eq!(outputs, [5]);
+    let result = super::verify(&program_hash, &[], &[2, 3], &outputs, &proof);
+    assert_eq!(Ok(true), result);
+    let result = super::verify(&program_hash, &[], &[2, 3, 4], &outputs, &proof);
+    assert!(result.is_err());
+
+    // when verifying against the program, the mismatch is reported explicitly
+    let program = super::Program::new(&program).unwrap();
+    let (outputs, proof) = program.prove(&inputs, 1, &options).unwrap();
+    let result = program.verify(&[], &[2, 3], &outputs, &proof);
+    assert_eq!(Ok(true), result);
+    let result = program.verify(&[], &[2, 3, 4], &outputs, &proof);
+    let err_msg = String::from("expected a public tape of 2 values consumed by the program, but received 3 values");
+    assert_eq!(Err(err_msg), result);
+}
+
+// OTHER OPERATIONS
+// ================================================================================================
+
+#[test]
+fn merkle_path_operations() {
+    // verifies a Merkle path of length 2 for the leaf at index 2
+    let program = [
+        opcodes::BEGIN, opcodes::READ,  opcodes::READ2, opcodes::PAD2,
+        opcodes::PAD2,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::MPATH,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,  opcodes::NOOP,  opcodes::MPATH,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::HASHR, opcodes::HASHR,
+        opcodes::HASHR, opcodes::HASHR, opcodes::DROP4, opcodes::PUSH,
+            0,          opcodes::ROLL4, opcodes::EQ,    opcodes::ASSERT,
+    ];
+
+    let leaf = [1, 2];
+    let node1 = <F128 as Hasher>::digest(&[leaf[0], leaf[1], 3, 4]);
+    let mut expected_root = <F128 as Hasher>::digest(&[5, 6, node1[0], node1[1]]);
+    expected_root.reverse();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[], &[2, leaf[0], 3, 5], &[leaf[1], 4, 6]);
+    let num_outputs = 2;
+
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    assert_eq!(expected_root, outputs);
+
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+}
+
+#[test]
+fn exp_operation() {
+
+    let x: u128 = F128::rand();
+    let e: u128 = F128::rand() >> 64;
+
+    // build inputs; bits of the exponent are consumed in little-endian order
+    let mut inputs_a = Vec::new();
+    for i in 0..64 { inputs_a.push((e >> i) & 1); }
+
+    // build the program
+    let mut program = vec![opcodes::BEGIN];
+    for _ in 0..64 { program.push(opcodes::EXP); }
+    program.extend_from_slice(&[
+        opcodes::DROP, opcodes::ROLL4, opcodes::EQ, opcodes::ASSERT, opcodes::DROP
+    ]);
+    while program.len() < 128 { program.push(opcodes::NOOP); }
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[1, 0, x, 1, e], &inputs_a, &[]);
+    let num_outputs = 1;
+
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    assert_eq!(vec![F128::exp(x, e)], outputs);
+
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
     assert_eq!(Ok(true), result);
 }
 
-// TODO: add more tests
\ No newline at end of file
+#[test]
+fn iszero_operation() {
+    let program = [
+        opcodes::BEGIN, opcodes::ISZERO, opcodes::SWAP,  opcodes::ISZERO,
+        opcodes::ROLL4, opcodes::ISZERO, opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,   opcodes::NOOP,  opcodes::NOOP,
+        opcodes::NOOP,  opcodes::NOOP,   opcodes::NOOP,  opcodes::NOOP,
+    ];
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[0, 7, 5, 0]);
+    let num_outputs = 4;
+
+    let (outputs, program_hash, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    assert_eq!(vec![1, 0, 1, 5], outputs);
+
+    let result = super::verify(&program_hash, inputs.get_public_inputs(), inputs.get_public_tape(), &outputs, &proof);
+    assert_eq!(Ok(true), result);
+}
+
+// TODO: add more tests
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns a program which computes the 4th Fibonacci number from public inputs `[1, 0]`; the
+/// program is padded with NOOPs to 16 operations, and the first 13 operations are the program
+/// before padding.
+fn fibonacci_program() -> [F128; 16] {
+    return [
+        opcodes::BEGIN, opcodes::SWAP, opcodes::DUP2, opcodes::DROP,
+        opcodes::ADD,   opcodes::SWAP, opcodes::DUP2, opcodes::DROP,
+        opcodes::ADD,   opcodes::SWAP, opcodes::DUP2, opcodes::DROP,
+        opcodes::ADD,   opcodes::NOOP, opcodes::NOOP, opcodes::NOOP,
+    ];
+}
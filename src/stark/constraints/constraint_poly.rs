@@ -1,10 +1,11 @@
 use crate::math::{ FiniteField, polynom, parallel };
-use crate::stark::{ MAX_CONSTRAINT_DEGREE, utils::CompositionCoefficients };
+use crate::stark::{ utils::CompositionCoefficients };
 
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct ConstraintPoly<T: FiniteField> {
-    poly: Vec<T>
+    poly        : Vec<T>,
+    trace_length: usize,
 }
 
 // CONSTRAINT POLY IMPLEMENTATION
@@ -12,19 +13,19 @@ pub struct ConstraintPoly<T: FiniteField> {
 impl <T> ConstraintPoly <T>
     where T: FiniteField
 {
-    pub fn new(poly: Vec<T>) -> ConstraintPoly<T> {
+    pub fn new(poly: Vec<T>, trace_length: usize) -> ConstraintPoly<T> {
 
         assert!(poly.len().is_power_of_two(), "poly length must be a power of two");
-        debug_assert!(get_expected_degree(&poly) == polynom::degree_of(&poly),
+        debug_assert!(get_expected_degree(&poly, trace_length) == polynom::degree_of(&poly),
             "expected polynomial of degree {} but received degree {}",
-            get_expected_degree(&poly),
+            get_expected_degree(&poly, trace_length),
             polynom::degree_of(&poly));
 
-        return ConstraintPoly { poly };
+        return ConstraintPoly { poly, trace_length };
     }
 
     pub fn degree(&self) -> usize {
-        return get_expected_degree(&self.poly);
+        return get_expected_degree(&self.poly, self.trace_length);
     }
 
     pub fn eval(&self, twiddles: &[T]) -> Vec<T> {
@@ -33,7 +34,7 @@ impl <T> ConstraintPoly <T>
 
         let mut evaluations = vec![T::ZERO; domain_size];
         evaluations[..self.poly.len()].copy_from_slice(&self.poly);
-        polynom::eval_fft_twiddles(&mut evaluations, twiddles, true);
+        polynom::eval_fft_coset(&mut evaluations, twiddles, T::GENERATOR, true);
 
         return evaluations;
     }
@@ -61,7 +62,8 @@ impl <T> ConstraintPoly <T>
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn get_expected_degree<T: FiniteField>(poly: &[T]) -> usize {
-    let trace_length = poly.len() / MAX_CONSTRAINT_DEGREE;
+/// Constraint polynomial is computed over a domain of MAX_CONSTRAINT_DEGREE * randomized_length
+/// elements, and its degree is smaller than the size of this domain by the length of the trace.
+fn get_expected_degree<T: FiniteField>(poly: &[T], trace_length: usize) -> usize {
     return poly.len() - trace_length;
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
+use crossbeam_utils::thread;
 use crate::math::{ FiniteField, parallel, fft, polynom };
-use crate::stark::{ TraceTable, TraceState, Accumulator, Hasher };
+use crate::crypto::{ HashFunction };
+use crate::stark::{ TraceTable, TraceState, SegmentState, Accumulator, Hasher };
 use crate::utils::{ uninit_vector };
 use super::{ ConstraintEvaluator, ConstraintPoly };
 
@@ -17,8 +19,8 @@ pub struct ConstraintTable<T: FiniteField + Accumulator + Hasher> {
 impl <T> ConstraintTable<T>
     where T: FiniteField + Accumulator + Hasher
 {
-    pub fn new(trace: &TraceTable<T>, trace_root: &[u8; 32], inputs: &[T], outputs: &[T]) -> ConstraintTable<T> {
-        let evaluator = ConstraintEvaluator::from_trace(trace, trace_root, inputs, outputs);
+    pub fn new(trace: &TraceTable<T>, seed: &[u8; 32], hash_fn: HashFunction, init_state: &SegmentState<T>, last_state: &SegmentState<T>) -> ConstraintTable<T> {
+        let evaluator = ConstraintEvaluator::from_trace(trace, seed, hash_fn, init_state, last_state);
         let evaluation_domain_size = evaluator.domain_size();
         return ConstraintTable {
             evaluator       : evaluator,
@@ -33,7 +35,7 @@ impl <T> ConstraintTable<T>
         return self.evaluator.constraint_count();
     }
 
-    /// Returns the size of the evaluation domain = trace_length * MAX_CONSTRAINT_DEGREE
+    /// Returns the size of the evaluation domain = randomized_length * MAX_CONSTRAINT_DEGREE
     pub fn evaluation_domain_size(&self) -> usize {
         return self.evaluator.domain_size();
     }
@@ -43,47 +45,178 @@ impl <T> ConstraintTable<T>
         return self.evaluator.trace_length();
     }
 
-    /// Evaluates transition and boundary constraints at the specified step.
-    pub fn evaluate(&mut self, current: &TraceState<T>, next: &TraceState<T>, x: T, step: usize) {
-        let (init_bound, last_bound) = self.evaluator.evaluate_boundaries(current, x);
-        self.i_evaluations[step] = init_bound;
-        self.f_evaluations[step] = last_bound;
-        self.t_evaluations[step] = self.evaluator.evaluate_transition(current, next, x, step);
+    /// Evaluates transition and boundary constraints over the entire evaluation domain. The
+    /// constraints are evaluated at every state of the extended `trace` which falls into the
+    /// evaluation domain; `lde_domain` must be the domain over which the `trace` is extended.
+    /// The work is distributed across `num_threads` threads; each thread uses its own buffers
+    /// for current and next states.
+    pub fn evaluate(&mut self, trace: &TraceTable<T>, lde_domain: &[T], num_threads: usize) {
+        debug_assert!(lde_domain.len() == trace.domain_size(), "invalid LDE domain size");
+        if trace.is_lazily_extended() {
+            self.evaluate_chunks(trace, lde_domain, num_threads);
+        }
+        else {
+            self.evaluate_steps(trace, lde_domain, num_threads);
+        }
+    }
+
+    /// Evaluates constraints over an extended `trace` which is kept in memory. The evaluation
+    /// domain is split into batches of consecutive steps, and each batch is evaluated in a
+    /// separate thread.
+    fn evaluate_steps(&mut self, trace: &TraceTable<T>, lde_domain: &[T], num_threads: usize) {
+        let domain_size = self.evaluation_domain_size();
+
+        // we don't need to evaluate constraints over the entire extended execution trace; we need
+        // to evaluate them over the domain extended to match max constraint degree - thus, we can
+        // skip most trace states for the purposes of constraint evaluation.
+        let stride = trace.domain_size() / domain_size;
+        let batch_size = domain_size.div_ceil(num_threads);
+
+        // each batch writes into disjoint ranges of evaluation vectors
+        let evaluator = &self.evaluator;
+        let batches = self.i_evaluations.chunks_mut(batch_size)
+            .zip(self.f_evaluations.chunks_mut(batch_size))
+            .zip(self.t_evaluations.chunks_mut(batch_size));
+
+        thread::scope(|s| {
+            for (batch_idx, ((i_batch, f_batch), t_batch)) in batches.enumerate() {
+                s.spawn(move |_| {
+                    // allocate space to hold current and next states for constraint evaluations
+                    let mut current = TraceState::new(trace.max_stack_depth());
+                    let mut next = TraceState::new(trace.max_stack_depth());
+
+                    let first_step = batch_idx * batch_size;
+                    for j in 0..t_batch.len() {
+                        // TODO: avoid copying next state from the trace table twice
+                        let step = first_step + j;
+                        let i = step * stride;
+
+                        // copy current and next states from the trace table; next state may wrap
+                        // around the execution trace (close to the end of the trace)
+                        trace.fill_state(&mut current, i);
+                        trace.fill_state(&mut next, (i + trace.extension_factor()) % trace.domain_size());
+
+                        // evaluate the constraints
+                        let x = lde_domain[i];
+                        let (init_bound, last_bound) = evaluator.evaluate_boundaries(&current, x);
+                        i_batch[j] = init_bound;
+                        f_batch[j] = last_bound;
+                        t_batch[j] = evaluator.evaluate_transition(&current, &next, x, step);
+                    }
+                });
+            }
+        }).unwrap();
+    }
+
+    /// Evaluates constraints over a lazily extended `trace`. The extended trace is computed one
+    /// chunk at a time, and only chunks which contain steps of the evaluation domain are
+    /// computed; steps of each chunk are split into batches which are evaluated in separate
+    /// threads.
+    fn evaluate_chunks(&mut self, trace: &TraceTable<T>, lde_domain: &[T], num_threads: usize) {
+        let domain_size = self.evaluation_domain_size();
+        let stride = trace.domain_size() / domain_size;
+
+        // chunk k contains steps k, k + num_chunks, k + 2 * num_chunks etc.; so, steps of the
+        // evaluation domain are in every chunk with index divisible by the stride, and the next
+        // state of every step of a chunk is in the same chunk
+        let num_chunks = trace.lde_chunk_count();
+        let chunk_size = trace.randomized_length();
+        debug_assert!(num_chunks % stride == 0, "number of chunks must be divisible by stride");
+        let next_offset = trace.extension_factor() / num_chunks;
+
+        let chunk_twiddles = fft::get_twiddles(T::get_root_of_unity(chunk_size), chunk_size);
+        let mut chunk = vec![uninit_vector(chunk_size); trace.register_count()];
+        let batch_size = chunk_size.div_ceil(num_threads);
+
+        let evaluator = &self.evaluator;
+        for k in (0..num_chunks).step_by(stride) {
+            trace.fill_lde_chunk(&mut chunk, k, &chunk_twiddles, num_threads);
+            let batches = thread::scope(|s| {
+                let mut handles = Vec::new();
+                for first_j in (0..chunk_size).step_by(batch_size) {
+                    let chunk = &chunk;
+                    handles.push(s.spawn(move |_| {
+                        // allocate space to hold current and next states for constraint evaluations
+                        let mut current = TraceState::new(trace.max_stack_depth());
+                        let mut next = TraceState::new(trace.max_stack_depth());
+
+                        let mut batch = Vec::with_capacity(batch_size);
+                        for j in first_j..usize::min(first_j + batch_size, chunk_size) {
+                            let i = k + num_chunks * j;
+                            let step = i / stride;
+
+                            // copy current and next states from the chunk; next state may wrap
+                            // around the chunk (close to the end of the trace)
+                            fill_state_from_chunk(&mut current, chunk, j);
+                            fill_state_from_chunk(&mut next, chunk, (j + next_offset) % chunk_size);
+
+                            // evaluate the constraints
+                            let x = lde_domain[i];
+                            let (init_bound, last_bound) = evaluator.evaluate_boundaries(&current, x);
+                            let transition = evaluator.evaluate_transition(&current, &next, x, step);
+                            batch.push((step, init_bound, last_bound, transition));
+                        }
+                        return batch;
+                    }));
+                }
+                return handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>();
+            }).unwrap();
+
+            // steps of a chunk are not consecutive in evaluation vectors; so, evaluations are
+            // copied into their positions once all batches of the chunk are evaluated
+            for (step, init_bound, last_bound, transition) in batches.into_iter().flatten() {
+                self.i_evaluations[step] = init_bound;
+                self.f_evaluations[step] = last_bound;
+                self.t_evaluations[step] = transition;
+            }
+        }
     }
 
     /// Interpolates all constraint evaluations into polynomials and combines all these 
-    /// polynomials into a single polynomial using pseudo-random linear combination.
+    /// polynomials into a single polynomial using pseudo-random linear combination. Constraints
+    /// are assumed to be evaluated over a coset of the evaluation domain offset by the field
+    /// generator.
     pub fn combine_polys(mut self) -> ConstraintPoly<T> {
 
         let combination_root = T::get_root_of_unity(self.evaluation_domain_size());
         let inv_twiddles = fft::get_inv_twiddles(combination_root, self.evaluation_domain_size());
-        
-        let mut combined_poly = uninit_vector(self.evaluation_domain_size());
+        let trace_length = self.trace_length();
         
         // 1 ----- boundary constraints for the initial step --------------------------------------
         // interpolate initial step boundary constraint combination into a polynomial, divide the 
-        // polynomial by Z(x) = (x - 1), and add it to the result
-        polynom::interpolate_fft_twiddles(&mut self.i_evaluations, &inv_twiddles, true);
+        // polynomial by Z(x) = (x - 1), and use it as the result; the result is accumulated in
+        // place to avoid allocating another vector
+        polynom::interpolate_fft_coset(&mut self.i_evaluations, &inv_twiddles, T::GENERATOR);
         polynom::syn_div_in_place(&mut self.i_evaluations, T::ONE);
-        combined_poly.copy_from_slice(&self.i_evaluations);
+        let mut combined_poly = self.i_evaluations;
 
         // 2 ----- boundary constraints for the final step ----------------------------------------
         // interpolate final step boundary constraint combination into a polynomial, divide the 
         // polynomial by Z(x) = (x - x_at_last_step), and add it to the result
-        polynom::interpolate_fft_twiddles(&mut self.f_evaluations, &inv_twiddles, true);
+        polynom::interpolate_fft_coset(&mut self.f_evaluations, &inv_twiddles, T::GENERATOR);
         let x_at_last_step = self.evaluator.get_x_at_last_step();
         polynom::syn_div_in_place(&mut self.f_evaluations, x_at_last_step);
         parallel::add_in_place(&mut combined_poly, &self.f_evaluations, 1);
+        drop(self.f_evaluations);
 
         // 3 ----- transition constraints ---------------------------------------------------------
         // interpolate transition constraint combination into a polynomial, divide the polynomial
         // by Z(x) = (x^steps - 1) / (x - x_at_last_step), and add it to the result
-        let trace_length = self.trace_length();
-        polynom::interpolate_fft_twiddles(&mut self.t_evaluations, &inv_twiddles, true);
+        polynom::interpolate_fft_coset(&mut self.t_evaluations, &inv_twiddles, T::GENERATOR);
         polynom::syn_div_expanded_in_place(&mut self.t_evaluations, trace_length, &[x_at_last_step]);
         parallel::add_in_place(&mut combined_poly, &self.t_evaluations, 1);
 
-        return ConstraintPoly::new(combined_poly);
+        return ConstraintPoly::new(combined_poly, trace_length);
     }
 
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Copies register values at position `index` of the extended trace `chunk` into `state`.
+fn fill_state_from_chunk<T: FiniteField + Accumulator + Hasher>(state: &mut TraceState<T>, chunk: &[Vec<T>], index: usize) {
+    for (i, register) in chunk.iter().enumerate() {
+        state.set_register(i, register[index]);
+    }
 }
\ No newline at end of file
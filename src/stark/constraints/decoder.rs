@@ -6,16 +6,19 @@ use crate::stark::{ ACC_STATE_WIDTH, ACC_CYCLE_LENGTH };
 // CONSTANTS
 // ================================================================================================
 const OP_CODE_CONSTRAINTS: usize = 6;
-const NUM_CONSTRAINTS: usize = OP_CODE_CONSTRAINTS + ACC_STATE_WIDTH;
+const TAPE_ACC_OFFSET: usize = OP_CODE_CONSTRAINTS + ACC_STATE_WIDTH;
+const NUM_CONSTRAINTS: usize = OP_CODE_CONSTRAINTS + 2 * ACC_STATE_WIDTH;
 
 const CONSTRAINT_DEGREES: [usize; NUM_CONSTRAINTS] = [
     2, 2, 2, 2, 2,  // op_bits are binary
     6,              // op_code decomposition constraint
-    4, 6, 3, 3      // op_code hash accumulator constraints
+    4, 6, 3, 3,     // op_code hash accumulator constraints
+    8, 8, 8, 8      // public tape accumulator constraints
 ];
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct Decoder<T: FiniteField> {
     op_accumulator: AccEvaluator<T>,
 }
@@ -56,6 +59,9 @@ impl <T> Decoder <T>
             current.get_op_code(), 
             step,
             &mut result[OP_CODE_CONSTRAINTS..]);
+
+        // evaluate constraints for public tape accumulator
+        self.acc_public_tape(current, next, &mut result[TAPE_ACC_OFFSET..]);
     }
 
     /// Evaluates decoder transition constraints at the specified x coordinate and saves the
@@ -73,6 +79,9 @@ impl <T> Decoder <T>
             current.get_op_code(),
             x,
             &mut result[OP_CODE_CONSTRAINTS..]);
+
+        // evaluate constraints for public tape accumulator
+        self.acc_public_tape(current, next, &mut result[TAPE_ACC_OFFSET..]);
     }
 
     // EVALUATION HELPERS
@@ -92,6 +101,49 @@ impl <T> Decoder <T>
         let op_code = T::mul(next.get_op_code(), binary_not(is_push));
         result[5] = T::sub(op_code, op_bits_value);
     }
+
+    /// Enforces constraints for the public tape accumulator. When the current operation is READP,
+    /// the value pushed onto the stack is absorbed into the accumulator using a single round of
+    /// modified Rescue function (see `Accumulator::absorb()`); otherwise, the accumulator must
+    /// remain unchanged. The constraints are of degree 8. At the last step, the accumulator is
+    /// checked against the hash of the entire public tape by boundary constraints; so, a program
+    /// must consume all values of the public tape for its proof to be accepted.
+    fn acc_public_tape(&self, current: &TraceState<T>, next: &TraceState<T>, result: &mut [T]) {
+
+        let op_flag = current.get_op_flags()[opcodes::READP as usize];
+        let value = next.get_user_stack()[0];
+
+        let mut state_part1 = [T::ZERO; ACC_STATE_WIDTH];
+        state_part1.copy_from_slice(current.get_tape_acc());
+        let mut state_part2 = [T::ZERO; ACC_STATE_WIDTH];
+        state_part2.copy_from_slice(next.get_tape_acc());
+
+        // first half of Rescue round
+        T::add_constants(&mut state_part1, 0, 0);
+        T::apply_sbox(&mut state_part1);
+        T::apply_mds(&mut state_part1);
+
+        // value injection
+        state_part1[0] = T::add(state_part1[0], value);
+
+        // second half of Rescue round
+        let mut ark = [T::ZERO; ACC_STATE_WIDTH];
+        T::add_constants(&mut ark, 0, ACC_STATE_WIDTH);
+        T::apply_inv_mds(&mut state_part2);
+        T::apply_sbox(&mut state_part2);
+        for i in 0..ACC_STATE_WIDTH {
+            state_part2[i] = T::sub(state_part2[i], ark[i]);
+        }
+
+        let current_acc = current.get_tape_acc();
+        let next_acc = next.get_tape_acc();
+        let not_flag = binary_not(op_flag);
+        for i in 0..ACC_STATE_WIDTH {
+            let absorbed = T::mul(op_flag, T::sub(state_part2[i], state_part1[i]));
+            let unchanged = T::mul(not_flag, T::sub(next_acc[i], current_acc[i]));
+            result[i] = T::add(absorbed, unchanged);
+        }
+    }
 }
 
 // HELPER FUNCTIONS
@@ -117,6 +169,7 @@ fn combine_bits<T: FiniteField>(op_bits: &[T]) -> T {
 // ACC EVALUATOR
 // ================================================================================================
 
+#[derive(Clone)]
 struct AccEvaluator<T: FiniteField> {
     trace_length    : usize,
     cycle_length    : usize,
@@ -129,8 +182,11 @@ impl<T> AccEvaluator <T>
 {
     /// Creates a new AccEvaluator based on the provided `trace_length` and `extension_factor`.
     pub fn new(trace_length: usize, extension_factor: usize) -> AccEvaluator<T> {
-        // extend rounds constants by the specified extension factor
-        let (ark_polys, ark_evaluations) = T::get_extended_constants(extension_factor);
+        // extend rounds constants by the specified extension factor; constraints are evaluated
+        // over a coset of the evaluation domain, and round constants are evaluated at x^num_cycles
+        let num_cycles = T::from_usize(trace_length / ACC_CYCLE_LENGTH);
+        let domain_offset = T::exp(T::GENERATOR, num_cycles);
+        let (ark_polys, ark_evaluations) = T::get_extended_constants(extension_factor, domain_offset);
 
         // transpose round constant evaluations so that constants for each round
         // are stored in a single row
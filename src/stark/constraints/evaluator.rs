@@ -1,7 +1,9 @@
-use std::mem;
+use std::{ mem, sync::Mutex };
 use crate::math::{ FiniteField };
+use crate::crypto::{ HashFunction };
 use crate::processor::{ opcodes };
-use crate::stark::{ StarkProof, TraceTable, TraceState, ConstraintCoefficients, Accumulator, Hasher };
+use crate::stark::{ StarkProof, TraceTable, TraceState, SegmentState, ConstraintCoefficients, Accumulator, Hasher };
+use crate::stark::{ ACC_STATE_WIDTH };
 use crate::utils::{ uninit_vector };
 use super::{ decoder::Decoder, stack::Stack, MAX_CONSTRAINT_DEGREE };
 
@@ -19,12 +21,11 @@ pub struct Evaluator<T>
 
     t_constraint_num: usize,
     t_degree_groups : Vec<(T, Vec<usize>)>,
-    t_evaluations   : Vec<Vec<T>>,
+    t_evaluations   : Mutex<Vec<Vec<T>>>,
 
     b_constraint_num: usize,
-    program_hash    : Vec<T>,
-    inputs          : Vec<T>,
-    outputs         : Vec<T>,
+    init_state      : SegmentState<T>,
+    last_state      : SegmentState<T>,
     b_degree_adj    : T,
 }
 
@@ -33,12 +34,17 @@ pub struct Evaluator<T>
 impl <T> Evaluator<T>
     where T: FiniteField + Accumulator + Hasher
 {
-    pub fn from_trace(trace: &TraceTable<T>, trace_root: &[u8; 32], inputs: &[T], outputs: &[T]) -> Evaluator<T> {
+    /// Returns an evaluator for constraints of the specified `trace`; boundary constraints bind
+    /// the first step of the trace to `init_state`, and the last step of the trace to `last_state`.
+    pub fn from_trace(trace: &TraceTable<T>, seed: &[u8; 32], hash_fn: HashFunction, init_state: &SegmentState<T>, last_state: &SegmentState<T>) -> Evaluator<T> {
 
         let stack_depth = trace.max_stack_depth();
-        let program_hash = trace.get_program_hash();
         let trace_length = trace.unextended_length();
-        let extension_factor = MAX_CONSTRAINT_DEGREE;
+        let randomized_length = trace.randomized_length();
+
+        // constraints are evaluated over a domain large enough to hold (randomized) trace
+        // polynomials raised to the max constraint degree
+        let extension_factor = MAX_CONSTRAINT_DEGREE * randomized_length / trace_length;
 
         // instantiate decoder and stack constraint evaluators 
         let decoder = Decoder::new(trace_length, extension_factor);
@@ -62,25 +68,27 @@ impl <T> Evaluator<T>
         return Evaluator {
             decoder         : decoder,
             stack           : stack,
-            coefficients    : ConstraintCoefficients::new(*trace_root),
+            coefficients    : ConstraintCoefficients::new(*seed, hash_fn),
             domain_size     : domain_size,
             extension_factor: extension_factor,
             t_constraint_num: t_constraint_degrees.len(),
-            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length),
-            t_evaluations   : t_evaluations,
-            b_constraint_num: inputs.len() + outputs.len() + program_hash.len(),
-            program_hash    : program_hash,
-            inputs          : inputs.to_vec(),
-            outputs         : outputs.to_vec(),
-            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length),
+            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length, randomized_length),
+            t_evaluations   : Mutex::new(t_evaluations),
+            b_constraint_num: count_boundary_constraints(init_state, last_state),
+            init_state      : init_state.clone(),
+            last_state      : last_state.clone(),
+            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length, randomized_length),
         };
     }
 
-    pub fn from_proof(proof: &StarkProof<T>, program_hash: &[u8; 32], inputs: &[T], outputs: &[T]) -> Evaluator<T> {
+    /// Returns an evaluator for constraints of the trace committed to in the specified `proof`;
+    /// the stacks of `init_state` and `last_state` must fit into the stack of the proof.
+    pub fn from_proof(proof: &StarkProof<T>, seed: &[u8; 32], init_state: &SegmentState<T>, last_state: &SegmentState<T>) -> Evaluator<T> {
         
         let stack_depth = proof.stack_depth();
         let trace_length = proof.trace_length();
-        let extension_factor = proof.options().extension_factor();
+        let randomized_length = proof.options().randomized_trace_length(trace_length);
+        let extension_factor = proof.domain_size() / trace_length;
         
         // instantiate decoder and stack constraint evaluators 
         let decoder = Decoder::new(trace_length, extension_factor);
@@ -94,17 +102,37 @@ impl <T> Evaluator<T>
         return Evaluator {
             decoder         : decoder,
             stack           : stack,
-            coefficients    : ConstraintCoefficients::new(*proof.trace_root()),
+            coefficients    : ConstraintCoefficients::new(*seed, proof.options().hash_function()),
             domain_size     : proof.domain_size(),
             extension_factor: extension_factor,
             t_constraint_num: t_constraint_degrees.len(),
-            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length),
-            t_evaluations   : Vec::new(),
-            b_constraint_num: inputs.len() + outputs.len() + program_hash.len(),
-            program_hash    : parse_program_hash(program_hash),
-            inputs          : inputs.to_vec(),
-            outputs         : outputs.to_vec(),
-            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length),
+            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length, randomized_length),
+            t_evaluations   : Mutex::new(Vec::new()),
+            b_constraint_num: count_boundary_constraints(init_state, last_state),
+            init_state      : init_state.clone(),
+            last_state      : last_state.clone(),
+            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length, randomized_length),
+        };
+    }
+
+    /// Returns a copy of this evaluator with constraint coefficients derived from the `seed` and
+    /// boundary constraints bound to `init_state` and `last_state`. This allows an evaluator set
+    /// up for one proof to be reused for all other proofs with the same trace length, domain size,
+    /// and stack depth.
+    pub fn rebind(&self, seed: &[u8; 32], hash_fn: HashFunction, init_state: &SegmentState<T>, last_state: &SegmentState<T>) -> Evaluator<T> {
+        return Evaluator {
+            decoder         : self.decoder.clone(),
+            stack           : self.stack.clone(),
+            coefficients    : ConstraintCoefficients::new(*seed, hash_fn),
+            domain_size     : self.domain_size,
+            extension_factor: self.extension_factor,
+            t_constraint_num: self.t_constraint_num,
+            t_degree_groups : self.t_degree_groups.clone(),
+            t_evaluations   : Mutex::new(Vec::new()),
+            b_constraint_num: count_boundary_constraints(init_state, last_state),
+            init_state      : init_state.clone(),
+            last_state      : last_state.clone(),
+            b_degree_adj    : self.b_degree_adj,
         };
     }
 
@@ -124,8 +152,8 @@ impl <T> Evaluator<T>
         return self.extension_factor;
     }
 
-    pub fn transition_evaluations(&self) -> &Vec<Vec<T>> {
-        return &self.t_evaluations;
+    pub fn transition_evaluations(&self) -> Vec<Vec<T>> {
+        return self.t_evaluations.lock().unwrap().clone();
     }
 
     pub fn get_x_at_last_step(&self) -> T {
@@ -150,16 +178,6 @@ impl <T> Evaluator<T>
         #[cfg(debug_assertions)]
         self.save_transition_evaluations(&evaluations, step);
 
-        // if the constraints should evaluate to all zeros at this step,
-        // make sure they do, and return
-        if self.should_evaluate_to_zero_at(step) {
-            let step = step / self.extension_factor;
-            for i in 0..evaluations.len() {
-                assert!(evaluations[i] == T::ZERO, "transition constraint at step {} were not satisfied", step);
-            }
-            return T::ZERO;
-        }
-
         // compute a pseudo-random linear combination of all transition constraints
         return self.combine_transition_constraints(&evaluations, x);
     }
@@ -207,19 +225,33 @@ impl <T> Evaluator<T>
             result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
         }
 
-        // make sure operation accumulator registers are set to zeros 
+        // make sure operation accumulator registers are set to the initial accumulator state;
+        // for a complete program, this state is all zeros
         let op_acc = current.get_op_acc();
-        for i in 0..op_acc.len() {
+        let init_op_acc = self.init_state.op_acc();
+        for i in 0..init_op_acc.len() {
             cc_idx += 2;
-            i_result = T::add(i_result, T::mul(op_acc[i], cc[cc_idx]));
-            result_adj = T::add(result_adj, T::mul(op_acc[i], cc[cc_idx + 1]));
+            let val = T::sub(op_acc[i], init_op_acc[i]);
+            i_result = T::add(i_result, T::mul(val, cc[cc_idx]));
+            result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
+        }
+
+        // make sure public tape accumulator registers are set to the initial accumulator state
+        let tape_acc = current.get_tape_acc();
+        let init_tape_acc = self.init_state.tape_acc();
+        for i in 0..init_tape_acc.len() {
+            cc_idx += 2;
+            let val = T::sub(tape_acc[i], init_tape_acc[i]);
+            i_result = T::add(i_result, T::mul(val, cc[cc_idx]));
+            result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
         }
 
         // make sure stack registers are set to inputs
         let user_stack = current.get_user_stack();
-        for i in 0..self.inputs.len() {
+        let inputs = self.init_state.stack();
+        for i in 0..inputs.len() {
             cc_idx += 2;
-            let val = T::sub(user_stack[i], self.inputs[i]);
+            let val = T::sub(user_stack[i], inputs[i]);
             i_result = T::add(i_result, T::mul(val, cc[cc_idx]));
             result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
         }
@@ -246,19 +278,30 @@ impl <T> Evaluator<T>
             result_adj = T::add(result_adj, T::mul(op_bits[i], cc[cc_idx + 1]));
         }
 
-        // make sure operation accumulator contains program hash
-        let program_hash = current.get_program_hash();
-        for i in 0..self.program_hash.len() {
+        // make sure operation accumulator contains program hash; for a segment of a program,
+        // the capacity part of the accumulator state is checked as well
+        let last_op_acc = self.last_state.op_acc();
+        for i in 0..last_op_acc.len() {
             cc_idx += 2;
-            let val = T::sub(program_hash[i], self.program_hash[i]);
+            let val = T::sub(op_acc[i], last_op_acc[i]);
+            f_result = T::add(f_result, T::mul(val, cc[cc_idx]));
+            result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
+        }
+
+        // make sure public tape accumulator contains hash of the public tape
+        let tape_hash = self.last_state.tape_acc();
+        for i in 0..tape_hash.len() {
+            cc_idx += 2;
+            let val = T::sub(tape_acc[i], tape_hash[i]);
             f_result = T::add(f_result, T::mul(val, cc[cc_idx]));
             result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
         }
 
         // make sure stack registers are set to outputs
-        for i in 0..self.outputs.len() {
+        let outputs = self.last_state.stack();
+        for i in 0..outputs.len() {
             cc_idx += 2;
-            let val = T::sub(user_stack[i], self.outputs[i]);
+            let val = T::sub(user_stack[i], outputs[i]);
             f_result = T::add(f_result, T::mul(val, cc[cc_idx]));
             result_adj = T::add(result_adj, T::mul(val, cc[cc_idx + 1]));
         }
@@ -271,11 +314,6 @@ impl <T> Evaluator<T>
 
     // HELPER METHODS
     // -------------------------------------------------------------------------------------------
-    fn should_evaluate_to_zero_at(&self, step: usize) -> bool {
-        return (step & (self.extension_factor - 1) == 0) // same as: step % extension_factor == 0
-            && (step != self.domain_size - self.extension_factor);
-    }
-
     fn combine_transition_constraints(&self, evaluations: &Vec<T>, x: T) -> T {
         let cc = self.coefficients.transition;
         let mut result = T::ZERO;
@@ -303,18 +341,18 @@ impl <T> Evaluator<T>
 
     #[cfg(debug_assertions)]
     fn save_transition_evaluations(&self, evaluations: &[T], step: usize) {
-        unsafe {
-            let mutable_self = &mut *(self as *const _ as *mut Evaluator<T>);
-            for i in 0..evaluations.len() {
-                mutable_self.t_evaluations[i][step] = evaluations[i];
-            }
+        // constraints may be evaluated concurrently from multiple threads; so, writes into
+        // saved evaluations are synchronized
+        let mut t_evaluations = self.t_evaluations.lock().unwrap();
+        for (i, &evaluation) in evaluations.iter().enumerate() {
+            t_evaluations[i][step] = evaluation;
         }
     }
 }
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn group_transition_constraints<T>(degrees: Vec<usize>, trace_length: usize) -> Vec<(T, Vec<usize>)>
+fn group_transition_constraints<T>(degrees: Vec<usize>, trace_length: usize, randomized_length: usize) -> Vec<(T, Vec<usize>)>
     where T: FiniteField
 {
     let mut groups = [
@@ -326,12 +364,12 @@ fn group_transition_constraints<T>(degrees: Vec<usize>, trace_length: usize) ->
         groups[degree].push(i);
     }
 
-    let target_degree = get_transition_constraint_target_degree(trace_length);
+    let target_degree = get_transition_constraint_target_degree(trace_length, randomized_length);
 
     let mut result = Vec::new();
     for (degree, constraints) in groups.iter().enumerate() {
         if constraints.len() == 0 { continue; }
-        let constraint_degree = (trace_length - 1) * degree;    
+        let constraint_degree = (randomized_length - 1) * degree;
         let incremental_degree = T::from_usize(target_degree - constraint_degree);
         result.push((incremental_degree, constraints.clone()));
     }
@@ -339,19 +377,19 @@ fn group_transition_constraints<T>(degrees: Vec<usize>, trace_length: usize) ->
     return result;
 }
 
-fn get_boundary_constraint_adjustment_degree<T>(trace_length: usize) -> T
+fn get_boundary_constraint_adjustment_degree<T>(trace_length: usize, randomized_length: usize) -> T
     where T: FiniteField
 {
-    let target_degree = get_boundary_constraint_target_degree(trace_length);
-    let boundary_constraint_degree = trace_length - 1;
+    let target_degree = get_boundary_constraint_target_degree(trace_length, randomized_length);
+    let boundary_constraint_degree = randomized_length - 1;
     return T::from_usize(target_degree - boundary_constraint_degree);
 }
 
 /// target degree for boundary constraints is set so that when divided by boundary
 /// constraint divisor (degree 1 polynomial), the degree will be equal to
 /// deg(combination domain) - deg(trace)
-fn get_boundary_constraint_target_degree(trace_length: usize) -> usize {
-    let combination_degree = (MAX_CONSTRAINT_DEGREE - 1) * trace_length;
+fn get_boundary_constraint_target_degree(trace_length: usize, randomized_length: usize) -> usize {
+    let combination_degree = MAX_CONSTRAINT_DEGREE * randomized_length - trace_length;
     let divisor_degree = 1;
     return combination_degree + divisor_degree;
 }
@@ -359,13 +397,19 @@ fn get_boundary_constraint_target_degree(trace_length: usize) -> usize {
 /// target degree for transition constraints is set so when divided transition 
 /// constraint divisor (deg(trace) - 1 polynomial), the degree will be equal to
 /// deg(combination domain) - deg(trace)
-fn get_transition_constraint_target_degree(trace_length: usize) -> usize {
-    let combination_degree = (MAX_CONSTRAINT_DEGREE - 1) * trace_length;
+fn get_transition_constraint_target_degree(trace_length: usize, randomized_length: usize) -> usize {
+    let combination_degree = MAX_CONSTRAINT_DEGREE * randomized_length - trace_length;
     let divisor_degree = trace_length - 1;
     return combination_degree + divisor_degree;
 }
 
-fn parse_program_hash<T>(program_hash: &[u8; 32]) -> Vec<T>
+fn count_boundary_constraints<T: FiniteField>(init_state: &SegmentState<T>, last_state: &SegmentState<T>) -> usize {
+    return init_state.op_acc().len() + init_state.tape_acc().len() + init_state.stack().len()
+        + last_state.op_acc().len() + last_state.tape_acc().len() + last_state.stack().len();
+}
+
+/// Converts a 32-byte program hash into the field elements of the program hash accumulator.
+pub fn parse_program_hash<T>(program_hash: &[u8; 32]) -> Vec<T>
     where T: FiniteField
 {
     let element_size = mem::size_of::<T>();
@@ -375,4 +419,16 @@ fn parse_program_hash<T>(program_hash: &[u8; 32]) -> Vec<T>
         result.push(T::from_bytes(&program_hash[i..(i + element_size)]))
     }
     return result;
+}
+
+/// Absorbs all values of the public tape into a single hash value in the same way as the
+/// values are absorbed into the public tape accumulator of the execution trace.
+pub fn hash_public_tape<T>(tape: &[T]) -> Vec<T>
+    where T: FiniteField + Accumulator
+{
+    let mut state = vec![T::ZERO; ACC_STATE_WIDTH];
+    for &value in tape.iter() {
+        T::absorb(&mut state, value);
+    }
+    return state;
 }
\ No newline at end of file
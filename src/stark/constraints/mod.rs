@@ -4,7 +4,7 @@ mod stack;
 mod constraint_table;
 mod constraint_poly;
 
-pub use evaluator::{ Evaluator as ConstraintEvaluator};
+pub use evaluator::{ Evaluator as ConstraintEvaluator, hash_public_tape, parse_program_hash };
 pub use constraint_table::{ ConstraintTable };
 pub use constraint_poly::{ ConstraintPoly };
 
@@ -42,6 +42,28 @@ pub fn enforce_eq<T: FiniteField>(evaluations: &mut [T], current: &[T], next: &[
     return aux_constraint;
 }
 
+/// Evaluates constraints for ISZERO operation. These enforce that when x == 0, top of the stack
+/// at the next step is set to 1, otherwise top of the stack at the next step is set to 0. This
+/// is the same as EQ operation with y = 0, except that the rest of the stack is not shifted.
+pub fn enforce_iszero<T: FiniteField>(evaluations: &mut [T], current: &[T], next: &[T], aux: T, op_flag: T) -> T {
+
+    // aux stack register contains inverse of x, or when x is 0, it will contain value 1
+    let x = current[0];
+    let inv_x = aux;
+
+    // the operation is defined as 1 - x * inv(x)
+    let op_result = T::sub(T::ONE, T::mul(x, inv_x));
+    evaluations[0] = agg_op_constraint(evaluations[0], op_flag, are_equal(next[0], op_result));
+
+    // ensure nothing changed beyond the first item of the stack
+    enforce_no_change(&mut evaluations[1..], &current[1..], &next[1..], op_flag);
+
+    // we also need to make sure that result * x = 0; this ensures that when x != 0
+    // the result must be set to 0
+    let aux_constraint = T::mul(op_flag, T::mul(next[0], x));
+    return aux_constraint;
+}
+
 /// Evaluates constraints for CMP operation.
 pub fn enforce_cmp<T: FiniteField>(evaluations: &mut [T], current: &[T], next: &[T], aux: T, op_flag: T) -> T {
 
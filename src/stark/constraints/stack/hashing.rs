@@ -2,9 +2,11 @@ use std::cmp;
 use crate::math::{ FiniteField, polynom };
 use crate::stark::{ Hasher };
 use crate::stark::{ HASH_STATE_WIDTH, HASH_CYCLE_LENGTH };
+use super::utils::{ agg_op_constraint, is_binary, are_equal, enforce_no_change };
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct HashEvaluator<T: FiniteField> {
     trace_length    : usize,
     cycle_length    : usize,
@@ -19,8 +21,11 @@ impl<T> HashEvaluator <T>
 {
     /// Creates a new HashEvaluator based on the provided `trace_length` and `extension_factor`.
     pub fn new(trace_length: usize, extension_factor: usize) -> HashEvaluator<T> {
-        // extend rounds constants by the specified extension factor
-        let (ark_polys, ark_evaluations) = T::get_extended_constants(extension_factor);
+        // extend rounds constants by the specified extension factor; constraints are evaluated
+        // over a coset of the evaluation domain, and round constants are evaluated at x^num_cycles
+        let num_cycles = T::from_usize(trace_length / HASH_CYCLE_LENGTH);
+        let domain_offset = T::exp(T::GENERATOR, num_cycles);
+        let (ark_polys, ark_evaluations) = T::get_extended_constants(extension_factor, domain_offset);
 
         // transpose round constant evaluations so that constants for each round
         // are stored in a single row
@@ -102,4 +107,40 @@ impl<T> HashEvaluator <T>
             result[i] = T::add(result[i], T::mul(evaluation, op_flag));
         }
     }
-}
\ No newline at end of file
+}
+
+// MERKLE PATH OPERATION
+// ================================================================================================
+
+/// Enforces constraints for MPATH operation. The operation prepares the first 6 registers of the
+/// user stack for hashing the current node of a Merkle authentication path (located in registers
+/// 4 and 5) together with the next node of the path. The order of the nodes is determined by the
+/// least significant bit of the index located in register 6; the index is shifted right by 1 bit.
+/// The next node of the path is read from the input tapes, and thus, is not constrained. Also
+/// evaluates an auxiliary constraint which guarantees that the index bit is binary.
+pub fn enforce_mpath<T: FiniteField>(evaluations: &mut [T], current: &[T], next: &[T], op_flag: T) -> T {
+
+    // the bit is defined as: index - 2 * next_index
+    let bit = T::sub(current[6], T::add(next[6], next[6]));
+    let not_bit = T::sub(T::ONE, bit);
+
+    // the first 2 registers must be set to 0
+    evaluations[0] = agg_op_constraint(evaluations[0], op_flag, next[0]);
+    evaluations[1] = agg_op_constraint(evaluations[1], op_flag, next[1]);
+
+    // when the bit is 1, the current node must be moved into registers 2 and 3; otherwise,
+    // it must remain in registers 4 and 5
+    let op_result = T::mul(bit, are_equal(next[2], current[4]));
+    evaluations[2] = agg_op_constraint(evaluations[2], op_flag, op_result);
+    let op_result = T::mul(bit, are_equal(next[3], current[5]));
+    evaluations[3] = agg_op_constraint(evaluations[3], op_flag, op_result);
+    let op_result = T::mul(not_bit, are_equal(next[4], current[4]));
+    evaluations[4] = agg_op_constraint(evaluations[4], op_flag, op_result);
+    let op_result = T::mul(not_bit, are_equal(next[5], current[5]));
+    evaluations[5] = agg_op_constraint(evaluations[5], op_flag, op_result);
+
+    // ensure nothing changed beyond the index
+    enforce_no_change(&mut evaluations[7..], &current[7..], &next[7..], op_flag);
+
+    return T::mul(op_flag, is_binary(bit));
+}
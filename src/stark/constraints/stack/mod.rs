@@ -7,8 +7,8 @@ mod hashing;
 mod selections;
 mod utils;
 
-use hashing::HashEvaluator;
-use comparisons::{ enforce_eq, enforce_cmp, enforce_binacc };
+use hashing::{ HashEvaluator, enforce_mpath };
+use comparisons::{ enforce_eq, enforce_iszero, enforce_cmp, enforce_binacc };
 use selections::{ enforce_choose, enforce_choose2 };
 use utils::{ agg_op_constraint, enforce_no_change, are_equal, is_binary };
 
@@ -22,6 +22,7 @@ const STACK_REST_DEGREE: usize = 6; // degree for the rest of the stack register
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct Stack<T: FiniteField> {
     hash_evaluator      : HashEvaluator<T>,
     constraint_degrees  : Vec<usize>
@@ -112,6 +113,7 @@ impl <T> Stack<T>
         enforce_push(&mut evaluations,      current, next, next_op, op_flags[opcodes::PUSH as usize]);
         enforce_read(&mut evaluations,      current, next, op_flags[opcodes::READ as usize]);
         enforce_read2(&mut evaluations,     current, next, op_flags[opcodes::READ2 as usize]);
+        enforce_read(&mut evaluations,      current, next, op_flags[opcodes::READP as usize]);
     
         // stack manipulation operations
         enforce_dup(&mut evaluations,       current, next, op_flags[opcodes::DUP as usize]);
@@ -136,10 +138,14 @@ impl <T> Stack<T>
         enforce_neg(&mut evaluations,       current, next, op_flags[opcodes::NEG as usize]);
         result[0] = T::add(result[0],
             enforce_not(&mut evaluations,   current, next, op_flags[opcodes::NOT as usize]));
+        result[0] = T::add(result[0],
+            enforce_exp(&mut evaluations,   current, next, aux, op_flags[opcodes::EXP as usize]));
         
         // comparison operations
         result[0] = T::add(result[0],
             enforce_eq(&mut evaluations,      current, next, aux, op_flags[opcodes::EQ as usize]));
+        result[0] = T::add(result[0],
+            enforce_iszero(&mut evaluations,  current, next, aux, op_flags[opcodes::ISZERO as usize]));
         result[0] = T::add(result[0],
             enforce_cmp(&mut evaluations,     current, next, aux, op_flags[opcodes::CMP as usize]));
         result[0] = T::add(result[0],
@@ -151,6 +157,10 @@ impl <T> Stack<T>
         result[0] = T::add(result[0],
             enforce_choose2(&mut evaluations, current, next, op_flags[opcodes::CHOOSE2 as usize]));
 
+        // cryptographic operations
+        result[0] = T::add(result[0],
+            enforce_mpath(&mut evaluations,   current, next, op_flags[opcodes::MPATH as usize]));
+
         // copy evaluations into the result while skipping the aux constraint because it
         // is already updated in the result vector
         let result = &mut result[1..];  // TODO: use constant
@@ -185,8 +195,9 @@ fn enforce_push<T: FiniteField>(result: &mut [T], current: &[T], next: &[T], op_
     enforce_no_change(&mut result[1..], &current[0..], &next[1..], op_flag);
 }
 
-/// Enforces constraints for READ operation. No constraints are placed on the first element of
-/// the stack; the old stack is shifted right by 1 element.
+/// Enforces constraints for READ and READP operations. No constraints are placed on the first
+/// element of the stack; the old stack is shifted right by 1 element. For READP operation, the
+/// first element of the stack is bound to the public tape by the decoder constraints.
 fn enforce_read<T: FiniteField>(result: &mut [T], current: &[T], next: &[T], op_flag: T) {
     enforce_no_change(&mut result[1..], &current[0..], &next[1..], op_flag);
 }
@@ -374,4 +385,39 @@ fn enforce_not<T: FiniteField>(evaluations: &mut [T], current: &[T], next: &[T],
 
     // we also need to make sure that the operand is binary (i.e. 0 or 1)
     return T::mul(op_flag, is_binary(x));
-}
\ No newline at end of file
+}
+
+/// Enforces constraints for EXP operation. The operation performs a single round of exponentiation
+/// by squaring, where the bit of the exponent is provided via the aux register. The stack is
+/// expected to be arranged as [p, e, b, r], where p is the current power of 2, e is the exponent
+/// accumulator, b is the current power of the base, and r is the result accumulator. Also
+/// evaluates an auxiliary constraint which guarantees that the exponent bit is binary.
+fn enforce_exp<T: FiniteField>(evaluations: &mut [T], current: &[T], next: &[T], aux: T, op_flag: T) -> T {
+
+    let bit = aux;
+    let power_of_two = current[0];
+    let base = current[2];
+
+    // power of 2 register was doubled
+    let op_result = T::mul(power_of_two, T::from_usize(2));
+    evaluations[0] = agg_op_constraint(evaluations[0], op_flag, are_equal(next[0], op_result));
+
+    // exponent accumulator was updated correctly
+    let op_result = T::add(current[1], T::mul(bit, power_of_two));
+    evaluations[1] = agg_op_constraint(evaluations[1], op_flag, are_equal(next[1], op_result));
+
+    // base was squared
+    let op_result = T::mul(base, base);
+    evaluations[2] = agg_op_constraint(evaluations[2], op_flag, are_equal(next[2], op_result));
+
+    // result was multiplied by the base only when the bit is 1: r * (bit * b + 1 - bit)
+    let multiplier = T::add(T::mul(bit, base), T::sub(T::ONE, bit));
+    let op_result = T::mul(current[3], multiplier);
+    evaluations[3] = agg_op_constraint(evaluations[3], op_flag, are_equal(next[3], op_result));
+
+    // registers beyond the 4th register were not affected
+    enforce_no_change(&mut evaluations[4..], &current[4..], &next[4..], op_flag);
+
+    // the bit was a binary value
+    return T::mul(op_flag, is_binary(bit));
+}
@@ -0,0 +1,154 @@
+use std::mem;
+use crate::math::{ FiniteField };
+use crate::crypto::{ MerkleTree };
+use super::{ ProofOptions, MAX_CONSTRAINT_DEGREE, fri, utils::get_composition_degree };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Estimated cost of generating a STARK proof for an execution trace of a given shape.
+/// All sizes are in bytes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ProofEstimate {
+    pub trace_length    : usize,
+    pub register_count  : usize,
+    pub domain_size     : usize,
+    pub fri_layers      : usize,
+    pub proof_size      : usize,
+    pub memory_size     : usize,
+}
+
+// PROOF ESTIMATE IMPLEMENTATION
+// ================================================================================================
+impl ProofEstimate {
+
+    /// Estimates the cost of proving an execution trace with the specified `trace_length` and
+    /// `register_count` using the specified `options`. Trace length, domain size, and the number
+    /// of FRI layers are exact; proof size and memory size are approximations.
+    pub fn new<T: FiniteField>(trace_length: usize, register_count: usize, options: &ProofOptions) -> ProofEstimate {
+
+        assert!(trace_length.is_power_of_two(), "trace length must be a power of 2");
+
+        let element_size = mem::size_of::<T>();
+        let randomized_length = options.randomized_trace_length(trace_length);
+        let domain_size = options.domain_size(trace_length);
+        let num_queries = options.num_queries();
+        let cap_height = options.merkle_cap_height();
+
+        // the number of FRI layers is determined by how many times the domain must be reduced
+        // by the folding factor before it becomes smaller than the max remainder length
+        let folding_factor = options.folding_factor();
+        let fri_layers = options.num_fri_layers(domain_size);
+        let composition_degree = get_composition_degree(trace_length, randomized_length);
+        let remainder_length = fri::get_remainder_length(composition_degree, domain_size, options);
+
+        // 1 ----- proof size ---------------------------------------------------------------------
+
+        // trace and constraint commitments: Merkle caps, queried values and Merkle authentication
+        // paths; constraint evaluations are packed into 32-byte leaves, so several queries may
+        // open the same constraint leaf
+        let constraint_leaves = domain_size * element_size / 32;
+        let opened_constraint_leaves = get_num_opened_leaves(constraint_leaves, num_queries);
+        let mut proof_size = num_queries * register_count * element_size
+            + get_batch_proof_size(domain_size, num_queries, false, cap_height)
+            + get_batch_proof_size(constraint_leaves, num_queries, true, cap_height);
+
+        // deep values: trace states at two out-of-domain points
+        proof_size += 2 * register_count * element_size;
+
+        // FRI layers: Merkle caps, rows of folding_factor values at augmented positions and their
+        // authentication paths
+        let mut layer_domain_size = domain_size;
+        for _ in 0..fri_layers {
+            let num_leaves = layer_domain_size / folding_factor;
+            let num_rows = get_num_opened_leaves(num_leaves, num_queries);
+            proof_size += num_rows * folding_factor * element_size
+                + get_batch_proof_size(num_leaves, num_queries, false, cap_height);
+            layer_domain_size = layer_domain_size / folding_factor;
+        }
+
+        // FRI remainder coefficients and their count, proof header (magic value, version, proof
+        // options, and domain depth), proof-of-work nonce (at most 10 bytes when encoded as
+        // a varint), and the number of extension records
+        proof_size += 2 + remainder_length * element_size;
+        proof_size += 14 + 10 + 1;
+
+        // in zero-knowledge mode: salts of opened trace and constraint leaves, randomizer values
+        // at queried positions and at the DEEP point, and trace depth
+        if options.zero_knowledge() {
+            proof_size += (num_queries + opened_constraint_leaves) * 32 + (num_queries + 1) * element_size + 1;
+        }
+
+        // 2 ----- memory size --------------------------------------------------------------------
+
+        // extended trace registers, trace polynomials, and trace Merkle tree
+        let mut memory_size = register_count * (domain_size + randomized_length) * element_size
+            + 2 * domain_size * 32;
+
+        // in zero-knowledge mode, randomizer polynomial and its evaluations over LDE domain
+        if options.zero_knowledge() {
+            memory_size += 2 * domain_size * element_size;
+        }
+
+        // constraint evaluations (initial, final, transition, and combined) over the evaluation
+        // domain, constraint evaluations over LDE domain, and constraint Merkle tree
+        let evaluation_domain_size = randomized_length * MAX_CONSTRAINT_DEGREE;
+        memory_size += 4 * evaluation_domain_size * element_size
+            + domain_size * element_size
+            + 2 * constraint_leaves * 32;
+
+        // composition polynomial evaluations and FRI layers; each FRI layer holds evaluations
+        // and a Merkle tree with a leaf for every folding_factor evaluations
+        memory_size += domain_size * element_size;
+        let mut layer_domain_size = domain_size;
+        for _ in 0..(fri_layers + 1) {
+            memory_size += layer_domain_size * element_size + 2 * (layer_domain_size / folding_factor) * 32;
+            layer_domain_size = layer_domain_size / folding_factor;
+        }
+
+        return ProofEstimate {
+            trace_length,
+            register_count,
+            domain_size,
+            fri_layers,
+            proof_size,
+            memory_size,
+        };
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns expected size of a batch Merkle proof for `num_queries` random queries against a tree
+/// with `num_leaves` leaves, together with the Merkle cap of the tree. Paths of different leaves
+/// share nodes close to the cap: a node is included in the proof only if its sibling is on the
+/// path of some queried leaf, but the node itself is not.
+fn get_batch_proof_size(num_leaves: usize, num_queries: usize, include_leaves: bool, cap_height: u8) -> usize {
+    let tree_depth = num_leaves.trailing_zeros();
+    let cap_size = MerkleTree::cap_size(tree_depth as u8, cap_height);
+    let mut num_nodes = cap_size as f64;
+    for level in (cap_size.trailing_zeros() + 1)..=tree_depth {
+        let parents = get_expected_touched_nodes(1 << (level - 1), num_queries);
+        let children = get_expected_touched_nodes(1 << level, num_queries);
+        num_nodes += 2.0 * parents - children;
+    }
+    let mut result = (num_nodes * 32.0) as usize;
+    if include_leaves {
+        result += get_num_opened_leaves(num_leaves, num_queries) * 32;
+    }
+    return result;
+}
+
+/// Returns expected number of distinct leaves opened by `num_queries` random queries against
+/// a tree with `num_leaves` leaves.
+fn get_num_opened_leaves(num_leaves: usize, num_queries: usize) -> usize {
+    return get_expected_touched_nodes(num_leaves, num_queries).round() as usize;
+}
+
+/// Returns expected number of distinct nodes hit by `num_queries` random queries at a level of
+/// a tree with `num_nodes` nodes.
+fn get_expected_touched_nodes(num_nodes: usize, num_queries: usize) -> f64 {
+    let n = num_nodes as f64;
+    return n * (1.0 - (1.0 - 1.0 / n).powi(num_queries as i32));
+}
@@ -1,5 +1,6 @@
 use serde::{ Serialize, Deserialize };
 use crate::math::{ FiniteField };
+use crate::stark::{ ProofOptions };
 
 // RE-EXPORTS
 // ================================================================================================
@@ -9,52 +10,61 @@ mod prover;
 pub use prover::{ reduce, build_proof };
 
 mod verifier;
-pub use verifier::{ verify };
-
-const MAX_REMAINDER_LENGTH: usize = 256;
+pub use verifier::{ verify, draw_coordinates };
 
 // TYPES AND INTERFACES
 // ================================================================================================
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FriProof<T: FiniteField> {
     pub layers      : Vec<FriLayer<T>>,
-    pub rem_root    : [u8; 32],
-    pub rem_values  : Vec<T>,
+    pub remainder   : Vec<T>,   // coefficients of the remainder polynomial
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FriLayer<T: FiniteField> {
-    pub root    : [u8; 32],
-    pub values  : Vec<[T; 4]>,
+    pub cap     : Vec<[u8; 32]>,
+    pub values  : Vec<Vec<T>>,
     pub nodes   : Vec<Vec<[u8; 32]>>,
     pub depth   : u8,
 }
 
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Returns the number of coefficients in the remainder polynomial for a polynomial of degree at
+/// most `max_degree` evaluated over a domain of `domain_size` elements. Each FRI layer reduces
+/// the number of coefficients by the folding factor (rounding up).
+pub fn get_remainder_length(max_degree: usize, domain_size: usize, options: &ProofOptions) -> usize {
+    let folding_factor = options.folding_factor();
+    let mut result = max_degree + 1;
+    for _ in 0..options.num_fri_layers(domain_size) {
+        result = result.div_ceil(folding_factor);
+    }
+    return result;
+}
+
 // TESTS
 // ================================================================================================
 #[cfg(test)]
 mod tests {
-    use crate::{ F64, FiniteField, polynom, ProofOptions };
+    use crate::{ F64, FiniteField, fft, polynom, ProofOptions, Transcript, crypto::HashFunction };
     use crate::stark::utils::compute_query_positions;
+    use super::FriProof;
 
     #[test]
     fn prove_verify() {
         let degree: usize = 63;
         let domain_size: usize = 512;
-        let root = F64::get_root_of_unity(domain_size);
-        let domain = F64::get_power_series(root, domain_size);
         let options = ProofOptions::default();
 
         let evaluations = build_random_poly_evaluations(domain_size, degree);
 
         // generate proof
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
-        let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
-        let proof = super::build_proof(fri_trees, fri_values, &positions);
+        let (proof, positions) = build_proof(&evaluations, degree, &options);
 
         // verify proof
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u64>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
+        let result = verify_proof(&proof, &sampled_evaluations, domain_size, degree, &options);
         assert_eq!(Ok(true), result);
     }
 
@@ -62,37 +72,122 @@ mod tests {
     fn verify_fail() {
         let degree: usize = 63;
         let domain_size: usize = 512;
-        let root = F64::get_root_of_unity(domain_size);
-        let domain = F64::get_power_series(root, domain_size);
         let options = ProofOptions::default();
 
         // degree too low 1
         let evaluations = build_random_poly_evaluations(domain_size, degree);
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
-        let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
-        let proof = super::build_proof(fri_trees, fri_values, &positions);
+        let (proof, positions) = build_proof(&evaluations, degree, &options);
 
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u64>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree - 1, &options);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", 14);
+        let result = verify_proof(&proof, &sampled_evaluations, domain_size, degree - 4, &options);
+        let err_msg = format!("expected {} remainder coefficients, but received {}", 15, 16);
         assert_eq!(Err(err_msg), result);
 
         // degree too low 2
         let evaluations = build_random_poly_evaluations(domain_size, degree + 1);
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
-        let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
-        let proof = super::build_proof(fri_trees, fri_values, &positions);
+        let (proof, positions) = build_proof(&evaluations, degree + 1, &options);
 
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u64>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", 15);
+        let result = verify_proof(&proof, &sampled_evaluations, domain_size, degree, &options);
+        let err_msg = format!("expected {} remainder coefficients, but received {}", 16, 17);
         assert_eq!(Err(err_msg), result);
 
         // invalid evaluations
-        let sampled_evaluations = sampled_evaluations[1..].to_vec();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
+        let mut bad_evaluations = sampled_evaluations.clone();
+        bad_evaluations[0] = F64::add(bad_evaluations[0], 1);
+        let result = verify_proof(&proof, &bad_evaluations, domain_size, degree + 1, &options);
         let err_msg = format!("evaluations did not match column value at depth 0");
         assert_eq!(Err(err_msg), result);
+
+        // missing evaluations
+        let bad_evaluations = sampled_evaluations[1..].to_vec();
+        let result = verify_proof(&proof, &bad_evaluations, domain_size, degree + 1, &options);
+        let err_msg = format!("expected {} evaluations, but received {}", positions.len(), positions.len() - 1);
+        assert_eq!(Err(err_msg), result);
+    }
+
+    #[test]
+    fn verify_malformed() {
+        let degree: usize = 63;
+        let domain_size: usize = 4096;
+        let options = ProofOptions::default();
+
+        let evaluations = build_random_poly_evaluations(domain_size, degree);
+        let (proof, positions) = build_proof(&evaluations, degree, &options);
+        let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u64>>();
+
+        // missing layer values
+        let mut bad_proof = proof.clone();
+        bad_proof.layers[1].values.pop();
+        let result = verify_proof(&bad_proof, &sampled_evaluations, domain_size, degree, &options);
+        assert!(result.is_err());
+
+        // inconsistent layer depth
+        let mut bad_proof = proof.clone();
+        bad_proof.layers[1].depth = 7;
+        let result = verify_proof(&bad_proof, &sampled_evaluations, domain_size, degree, &options);
+        let err_msg = format!("depth of layer {} is inconsistent with the domain size", 1);
+        assert_eq!(Err(err_msg), result);
+
+        // layer depth is too large
+        let mut bad_proof = proof.clone();
+        bad_proof.layers[0].depth = 255;
+        let result = verify_proof(&bad_proof, &sampled_evaluations, domain_size, degree, &options);
+        let err_msg = format!("depth of layer {} is inconsistent with the domain size", 0);
+        assert_eq!(Err(err_msg), result);
+
+        // missing layer
+        let mut bad_proof = proof.clone();
+        bad_proof.layers.pop();
+        let result = verify_proof(&bad_proof, &sampled_evaluations, domain_size, degree, &options);
+        let err_msg = format!("expected {} FRI layers, but received {}", 2, 1);
+        assert_eq!(Err(err_msg), result);
+
+        // missing remainder coefficients
+        let mut bad_proof = proof.clone();
+        bad_proof.remainder.pop();
+        let result = verify_proof(&bad_proof, &sampled_evaluations, domain_size, degree, &options);
+        let err_msg = format!("expected {} remainder coefficients, but received {}", 4, 3);
+        assert_eq!(Err(err_msg), result);
+    }
+
+    #[test]
+    fn prove_verify_no_layers() {
+        let degree: usize = 15;
+        let domain_size: usize = 256;
+        let options = ProofOptions::default();
+
+        let evaluations = build_random_poly_evaluations(domain_size, degree);
+        let (proof, positions) = build_proof(&evaluations, degree, &options);
+        assert_eq!(0, proof.layers.len());
+
+        let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u64>>();
+        let result = verify_proof(&proof, &sampled_evaluations, domain_size, degree, &options);
+        assert_eq!(Ok(true), result);
+    }
+
+    #[test]
+    fn prove_verify_folding_factors() {
+        let degree: usize = 2047;
+        let domain_size: usize = 8192;
+
+        for &folding_factor in [2, 4, 8, 16].iter() {
+            let options = ProofOptions::new(32, 50, 0, HashFunction::Blake3)
+                .with_folding_factor(folding_factor)
+                .with_max_remainder_degree(31);
+            let evaluations = build_random_poly_evaluations(domain_size, degree);
+            let (proof, positions) = build_proof(&evaluations, degree, &options);
+            assert_eq!(options.num_fri_layers(domain_size), proof.layers.len());
+            assert!(proof.remainder.len() <= options.max_remainder_degree() + 1);
+
+            let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u64>>();
+            let result = verify_proof(&proof, &sampled_evaluations, domain_size, degree, &options);
+            assert_eq!(Ok(true), result);
+
+            // degree too low
+            let result = verify_proof(&proof, &sampled_evaluations, domain_size, degree / 2, &options);
+            assert!(result.is_err());
+        }
     }
 
     // TODO: add more tests
@@ -100,7 +195,30 @@ mod tests {
     fn build_random_poly_evaluations(domain_size: usize, degree: usize) -> Vec<u64> {
         let mut evaluations = F64::rand_vector(degree + 1);
         evaluations.resize(domain_size, 0);
-        polynom::eval_fft(&mut evaluations, true);
+        let twiddles = fft::get_twiddles(F64::get_root_of_unity(domain_size), domain_size);
+        polynom::eval_fft_coset(&mut evaluations, &twiddles, F64::GENERATOR, true);
         return evaluations;
     }
-}
\ No newline at end of file
+
+    fn build_transcript(options: &ProofOptions) -> Transcript {
+        return Transcript::new::<u64>(&[0u8; 32], &[], &[], &[], 32, options);
+    }
+
+    fn build_proof(evaluations: &[u64], degree: usize, options: &ProofOptions) -> (FriProof<u64>, Vec<usize>) {
+        let root = F64::get_root_of_unity(evaluations.len());
+        let domain = F64::get_power_series_with_offset(root, F64::GENERATOR, evaluations.len());
+
+        let mut transcript = build_transcript(options);
+        let (fri_trees, fri_values, remainder) = super::reduce(evaluations, &domain, degree, &mut transcript, options);
+        let positions = compute_query_positions(&transcript.draw(), evaluations.len(), options);
+        let proof = super::build_proof(fri_trees, fri_values, remainder, &positions, options);
+        return (proof, positions);
+    }
+
+    fn verify_proof(proof: &FriProof<u64>, evaluations: &[u64], domain_size: usize, degree: usize, options: &ProofOptions) -> Result<bool, String> {
+        let mut transcript = build_transcript(options);
+        let coordinates = super::draw_coordinates(proof, &mut transcript);
+        let positions = compute_query_positions(&transcript.draw(), domain_size, options);
+        return super::verify(proof, evaluations, &positions, &coordinates, domain_size, degree, options);
+    }
+}
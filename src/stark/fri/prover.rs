@@ -1,100 +1,107 @@
-use std::mem;
-use crate::math::{ FiniteField, quartic };
+use crate::math::{ FiniteField, polynom, fft };
 use crate::crypto::{ MerkleTree };
-use crate::stark::{ ProofOptions };
+use crate::stark::{ ProofOptions, Transcript };
+use crate::utils::{ as_bytes };
 
-use super::{ FriProof, FriLayer, utils, MAX_REMAINDER_LENGTH};
+use super::{ FriProof, FriLayer, utils };
 
 // PROVER FUNCTIONS
 // ================================================================================================
 
-pub fn reduce<T>(evaluations: &[T], domain: &[T], options: &ProofOptions) -> (Vec<MerkleTree>, Vec<Vec<[T; 4]>>)
+/// Reduces `evaluations` of a polynomial of degree at most `max_degree` over `domain` to FRI
+/// layers. Returns Merkle trees and transposed evaluations for all layers, and coefficients of
+/// the remainder polynomial.
+pub fn reduce<T>(evaluations: &[T], domain: &[T], max_degree: usize, transcript: &mut Transcript, options: &ProofOptions) -> (Vec<MerkleTree>, Vec<Vec<Vec<T>>>, Vec<T>)
     where T: FiniteField
 {
     let mut tree_results: Vec<MerkleTree> = Vec::new();
-    let mut value_results: Vec<Vec<[T; 4]>> = Vec::new();
+    let mut value_results: Vec<Vec<Vec<T>>> = Vec::new();
 
-    // transpose evaluations into a matrix with 4 columns and put its rows into a Merkle tree
-    let mut p_values = quartic::transpose(evaluations, 1);
-    let hashed_values = utils::hash_values(&p_values, options.hash_function());
-    let mut p_tree = MerkleTree::new(hashed_values, options.hash_function());
+    let folding_factor = options.folding_factor();
+    let inv_twiddles = fft::get_inv_twiddles(T::get_root_of_unity(folding_factor), folding_factor);
 
-    // reduce the degree by 4 at each iteration until the remaining polynomial is small enough
-    while p_tree.leaves().len() * 4 > MAX_REMAINDER_LENGTH {
+    // reduce the degree by folding_factor at each iteration until the remaining polynomial is small enough
+    let mut column = evaluations.to_vec();
+    while column.len() > options.max_remainder_length() {
 
-        // build polynomials from each row of the polynomial value matrix
-        let depth = tree_results.len() as u32;
-        let xs = quartic::transpose(domain, usize::pow(4, depth));
-        let polys = quartic::interpolate_batch(&xs, &p_values);
-
-        // select a pseudo-random x coordinate and evaluate each row polynomial at that x
-        let special_x = T::prng(*p_tree.root());
-        let column = quartic::evaluate_batch(&polys, special_x);
+        // transpose evaluations into a matrix with folding_factor columns and put its rows into a Merkle tree
+        let p_values = utils::transpose(&column, folding_factor);
+        let hashed_values = utils::hash_values(&p_values, options.hash_function());
+        let p_tree = MerkleTree::new_concurrent(hashed_values, options.hash_function(), options.num_threads());
 
-        // break the column in a polynomial value matrix for the next layer
-        let mut c_values = quartic::transpose(&column, 1);
-
-        // put the resulting matrix into a Merkle tree
-        let hashed_values = utils::hash_values(&c_values, options.hash_function());
-        let mut c_tree = MerkleTree::new(hashed_values, options.hash_function());
+        // the domain of layer d is the original coset raised to the power folding_factor^d; so,
+        // row i of the current layer corresponds to x_i = domain[i * stride] * offset^(stride - 1)
+        let depth = tree_results.len() as u32;
+        let stride = usize::pow(folding_factor, depth);
+        let scale = T::exp(domain[0], T::from_usize(stride - 1));
+        let xs = (0..p_values.len()).map(|i| T::mul(domain[i * stride], scale)).collect::<Vec<T>>();
+        let inv_xs = T::inv_many(&xs);
+
+        // absorb the cap of the current layer into the transcript, draw a pseudo-random
+        // x coordinate from it, and fold each row of the evaluation matrix at that x; the
+        // result becomes the column for the next layer
+        transcript.absorb(as_bytes(p_tree.cap(options.merkle_cap_height())));
+        let special_x = transcript.draw_element();
+        column = p_values.iter().zip(inv_xs)
+            .map(|(row, inv_x)| utils::fold_row(row, &inv_twiddles, T::mul(special_x, inv_x)))
+            .collect::<Vec<T>>();
+
+        tree_results.push(p_tree);
+        value_results.push(p_values);
+    }
 
-        // set p_tree = c_tree and p_values = c_values for the next iteration of the loop
-        mem::swap(&mut c_tree, &mut p_tree);
-        mem::swap(&mut c_values, &mut p_values);
+    // interpolate the last column into the remainder polynomial; the domain of the last column
+    // is the original coset raised to the power folding_factor^num_layers
+    let stride = usize::pow(folding_factor, tree_results.len() as u32);
+    let offset = T::exp(domain[0], T::from_usize(stride));
+    let inv_twiddles = fft::get_inv_twiddles(T::get_root_of_unity(column.len()), column.len());
+    polynom::interpolate_fft_coset(&mut column, &inv_twiddles, offset);
 
-        // add p_tree and p_values from this loop (which is now under c_tree and c_values) to the result
-        tree_results.push(c_tree);
-        value_results.push(c_values);
-    }
+    // coefficients beyond the degree bound must be zeros for valid inputs, and are not sent
+    let remainder_length = super::get_remainder_length(max_degree, evaluations.len(), options);
+    debug_assert!(column[remainder_length..].iter().all(|&c| c == T::ZERO),
+        "remainder polynomial has more than {} coefficients", remainder_length);
+    column.truncate(remainder_length);
 
-    // add the tree at the last layer (the remainder)
-    tree_results.push(p_tree);
-    value_results.push(p_values);
+    // add the remainder to the transcript
+    transcript.absorb(as_bytes(&column));
 
-    return (tree_results, value_results);
+    return (tree_results, value_results, column);
 }
 
-pub fn build_proof<T>(trees: Vec<MerkleTree>, values: Vec<Vec<[T; 4]>>, positions: &[usize]) -> FriProof<T>
+pub fn build_proof<T>(trees: Vec<MerkleTree>, values: Vec<Vec<Vec<T>>>, remainder: Vec<T>, positions: &[usize], options: &ProofOptions) -> FriProof<T>
     where T: FiniteField
 {
     let mut positions = positions.to_vec();
-    let mut domain_size = trees[0].leaves().len() * 4;
-
-    // for all trees, except the last one, record tree root, authentication paths
-    // to row evaluations, and values for row evaluations
     let mut layers = Vec::with_capacity(trees.len());
-    for i in 0..(trees.len() - 1) {
+    if trees.is_empty() {
+        return FriProof { layers, remainder };
+    }
+
+    let folding_factor = values[0][0].len();
+    let mut domain_size = trees[0].leaves().len() * folding_factor;
+
+    // for all layers, record tree cap, authentication paths to row evaluations, and values
+    // for row evaluations
+    for (tree, values) in trees.iter().zip(values.iter()) {
         
-        positions = utils::get_augmented_positions(&positions, domain_size);
+        positions = utils::get_augmented_positions(&positions, domain_size, folding_factor);
 
-        let tree = &trees[i];
-        let proof = tree.prove_batch(&positions);
+        let proof = tree.prove_batch(&positions, options.merkle_cap_height());
         
-        let mut queried_values: Vec<[T; 4]> = Vec::with_capacity(positions.len());
+        let mut queried_values: Vec<Vec<T>> = Vec::with_capacity(positions.len());
         for &position in positions.iter() {
-            queried_values.push(values[i][position]);
+            queried_values.push(values[position].clone());
         }
 
         layers.push(FriLayer {
-            root    : *tree.root(),
+            cap     : tree.cap(options.merkle_cap_height()).to_vec(),
             values  : queried_values,
             nodes   : proof.nodes,
             depth   : proof.depth
         });
-        domain_size = domain_size / 4;
+        domain_size = domain_size / folding_factor;
     }
 
-    // use the remaining polynomial values directly as proof
-    let last_tree = &trees[trees.len() - 1];
-    let last_values = &values[values.len() - 1];
-    let n = last_values.len();
-    let mut remainder = vec![T::ZERO; n * 4];
-    for i in 0..last_values.len() {
-        remainder[i] = last_values[i][0];
-        remainder[i + n] = last_values[i][1];
-        remainder[i + n * 2] = last_values[i][2];
-        remainder[i + n * 3] = last_values[i][3];
-    }
-
-    return FriProof { layers, rem_root: *last_tree.root(), rem_values: remainder };
-}
\ No newline at end of file
+    return FriProof { layers, remainder };
+}
@@ -1,8 +1,21 @@
+use crate::math::{ FiniteField, polynom };
 use crate::crypto::{ HashFunction };
 use crate::utils::{ uninit_vector, as_bytes };
 
-pub fn get_augmented_positions(positions: &[usize], column_length: usize) -> Vec<usize> {
-    let row_length = column_length / 4;
+/// Transposes `values` into a matrix with `folding_factor` columns such that row i contains
+/// values at positions i, i + n / folding_factor, i + 2 * n / folding_factor etc.
+pub fn transpose<T: Copy>(values: &[T], folding_factor: usize) -> Vec<Vec<T>> {
+    assert!(values.len() % folding_factor == 0, "number of values must be divisible by {}", folding_factor);
+    let row_count = values.len() / folding_factor;
+    let mut result = Vec::with_capacity(row_count);
+    for i in 0..row_count {
+        result.push((0..folding_factor).map(|j| values[i + j * row_count]).collect());
+    }
+    return result;
+}
+
+pub fn get_augmented_positions(positions: &[usize], column_length: usize, folding_factor: usize) -> Vec<usize> {
+    let row_length = column_length / folding_factor;
     let mut result = Vec::new();
     for i in 0..positions.len() {
         let ap = positions[i] % row_length;
@@ -13,10 +26,23 @@ pub fn get_augmented_positions(positions: &[usize], column_length: usize) -> Vec
     return result;
 }
 
-pub fn hash_values<T>(values: &Vec<[T; 4]>, hash: HashFunction) -> Vec<[u8; 32]> {
+pub fn hash_values<T>(values: &[Vec<T>], hash: HashFunction) -> Vec<[u8; 32]> {
     let mut result: Vec<[u8; 32]> = uninit_vector(values.len());
     for i in 0..values.len() {
-        hash(as_bytes(&values[i]), &mut result[i]);
+        hash.digest(as_bytes(&values[i]), &mut result[i]);
     }
     return result;
-}
\ No newline at end of file
+}
+
+/// Folds a row of the evaluation matrix into a single value. Values in the row are evaluations
+/// of some polynomial at x_0 * e^j, where e is a root of unity of order equal to the row length;
+/// the row is interpolated into a polynomial R(x_0 * y) of degree smaller than the row length
+/// using inverse FFT, and the result is the evaluation of this polynomial at `x`. `x` must be
+/// the pseudo-random x coordinate of the layer divided by x_0.
+pub fn fold_row<T>(row: &[T], inv_twiddles: &[T], x: T) -> T
+    where T: FiniteField
+{
+    let mut coefficients = row.to_vec();
+    polynom::interpolate_fft_twiddles(&mut coefficients, inv_twiddles, true);
+    return polynom::eval(&coefficients, x);
+}
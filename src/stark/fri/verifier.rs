@@ -1,124 +1,139 @@
 use std::mem;
-use crate::math::{ FiniteField, polynom, quartic };
+use crate::math::{ FiniteField, polynom, fft };
 use crate::crypto::{ MerkleTree, BatchMerkleProof };
-use crate::stark::{ ProofOptions };
+use crate::stark::{ ProofOptions, Transcript, MAX_DOMAIN_DEPTH };
+use crate::utils::{ as_bytes };
 
 use super::{ FriProof, FriLayer, utils };
 
 // VERIFIER
 // ================================================================================================
 
+/// Absorbs caps of all FRI layers and the remainder into the `transcript` and returns
+/// pseudo-random x coordinates for all layers drawn in the same way as during FRI reduction.
+pub fn draw_coordinates<T: FiniteField>(proof: &FriProof<T>, transcript: &mut Transcript) -> Vec<T> {
+    let mut result = Vec::with_capacity(proof.layers.len());
+    for layer in proof.layers.iter() {
+        transcript.absorb(as_bytes(&layer.cap));
+        result.push(transcript.draw_element());
+    }
+    transcript.absorb(as_bytes(&proof.remainder));
+    return result;
+}
+
 pub fn verify<T: FiniteField>(
     proof       : &FriProof<T>,
     evaluations : &[T],
     positions   : &[usize],
+    coordinates : &[T],
+    domain_size : usize,
     max_degree  : usize,
     options     : &ProofOptions) -> Result<bool, String>
 {
+    if coordinates.len() != proof.layers.len() {
+        return Err(format!("expected {} pseudo-random coordinates, but received {}",
+            proof.layers.len(), coordinates.len()));
+    }
+
+    if evaluations.len() != positions.len() {
+        return Err(format!("expected {} evaluations, but received {}", positions.len(), evaluations.len()));
+    }
+
+    if !domain_size.is_power_of_two() || !(4..=(1 << MAX_DOMAIN_DEPTH)).contains(&domain_size) {
+        return Err(format!("domain size {} is not valid", domain_size));
+    }
+
+    // each layer must reduce the domain by the folding factor until the polynomial is small
+    // enough, and the remainder must have as many coefficients as its degree bound allows
+    let folding_factor = options.folding_factor();
+    let num_layers = options.num_fri_layers(domain_size);
+    if proof.layers.len() != num_layers {
+        return Err(format!("expected {} FRI layers, but received {}", num_layers, proof.layers.len()));
+    }
+
+    let mut layer_domain_size = domain_size;
+    for (depth, layer) in proof.layers.iter().enumerate() {
+        if layer.depth as u32 != (layer_domain_size / folding_factor).trailing_zeros() {
+            return Err(format!("depth of layer {} is inconsistent with the domain size", depth));
+        }
+        let cap_size = MerkleTree::cap_size(layer.depth, options.merkle_cap_height());
+        if layer.cap.len() != cap_size {
+            return Err(format!("cap of layer {} must contain {} nodes", depth, cap_size));
+        }
+        if layer.values.iter().any(|row| row.len() != folding_factor) {
+            return Err(format!("rows of layer {} must contain {} values", depth, folding_factor));
+        }
+        layer_domain_size = layer_domain_size / folding_factor;
+    }
+
+    let remainder_length = super::get_remainder_length(max_degree, domain_size, options);
+    if proof.remainder.len() != remainder_length {
+        return Err(format!("expected {} remainder coefficients, but received {}",
+            remainder_length, proof.remainder.len()));
+    }
+
+    if positions.iter().any(|&p| p >= domain_size) {
+        return Err(String::from("query positions must be smaller than the domain size"));
+    }
 
-    let domain_size = usize::pow(2, proof.layers[0].depth as u32) * 4;
     let domain_root = T::get_root_of_unity(domain_size);
 
-    // powers of the given root of unity 1, p, p^2, p^3 such that p^4 = 1
-    let quartic_roots = [
-        T::from_usize(1),
-        T::exp(domain_root, T::from_usize(domain_size / 4)),
-        T::exp(domain_root, T::from_usize(domain_size / 2)),
-        T::exp(domain_root, T::from_usize(domain_size * 3 / 4)),
-    ];
+    // inverse twiddles for interpolating rows of folding_factor values
+    let inv_twiddles = fft::get_inv_twiddles(T::get_root_of_unity(folding_factor), folding_factor);
 
     // 1 ----- verify the recursive components of the FRI proof -----------------------------------
+    // the domain is a coset of the subgroup generated by domain root, offset by the field generator
     let mut domain_root = domain_root;
+    let mut domain_offset = T::GENERATOR;
     let mut domain_size = domain_size;
-    let mut max_degree_plus_1 = max_degree + 1;
     let mut positions = positions.to_vec();
     let mut evaluations = evaluations.to_vec();
 
     for (depth, layer) in proof.layers.iter().enumerate() {
 
-        let mut augmented_positions = utils::get_augmented_positions(&positions, domain_size);
-        let column_values = get_column_values(&layer.values, &positions, &augmented_positions, domain_size);
+        let mut augmented_positions = utils::get_augmented_positions(&positions, domain_size, folding_factor);
+        if layer.values.len() != augmented_positions.len() {
+            return Err(format!("expected {} values at depth {}, but received {}",
+                augmented_positions.len(), depth, layer.values.len()));
+        }
+
+        let column_values = get_column_values(&layer.values, &positions, &augmented_positions, domain_size, folding_factor);
         if evaluations != column_values {
             return Err(format!("evaluations did not match column value at depth {}", depth));
         }
 
         // verify Merkle proof for the layer
         let merkle_proof = build_layer_merkle_proof(&layer, options);
-        if !MerkleTree::verify_batch(&layer.root, &augmented_positions, &merkle_proof, options.hash_function()) {
+        if !MerkleTree::verify_batch(&layer.cap, &augmented_positions, &merkle_proof, options.hash_function()) {
             return Err(format!("verification of Merkle proof failed at layer {}", depth));
         }
 
-        // build a set of x for each row polynomial
-        let mut xs = Vec::with_capacity(augmented_positions.len());
-        for &i in augmented_positions.iter() {
-            let xe = T::exp(domain_root, T::from_usize(i));
-            xs.push([
-                T::mul(quartic_roots[0], xe),
-                T::mul(quartic_roots[1], xe),
-                T::mul(quartic_roots[2], xe),
-                T::mul(quartic_roots[3], xe)
-            ]);
-        }
-
-        // interpolate x and y values into row polynomials
-        let row_polys = quartic::interpolate_batch(&xs, &layer.values);
-
-        // calculate the pseudo-random x coordinate
-        let special_x = T::prng(layer.root);
+        // get the pseudo-random x coordinate for the layer
+        let special_x = coordinates[depth];
 
-        // check that when the polynomials are evaluated at x, the result is equal to the corresponding column value
-        evaluations = quartic::evaluate_batch(&row_polys, special_x);
+        // fold each row at x; row i contains evaluations at x_i * e^j, where x_i = offset * root^i
+        // and e is a root of unity of order folding_factor; the result must be equal to the
+        // corresponding column value in the next layer
+        evaluations = Vec::with_capacity(augmented_positions.len());
+        for (&i, row) in augmented_positions.iter().zip(layer.values.iter()) {
+            let x_i = T::mul(domain_offset, T::exp(domain_root, T::from_usize(i)));
+            evaluations.push(utils::fold_row(row, &inv_twiddles, T::div(special_x, x_i)));
+        }
 
         // update variables for the next iteration of the loop
-        domain_root = T::exp(domain_root, T::from_usize(4));
-        max_degree_plus_1 = max_degree_plus_1 / 4;
-        domain_size = domain_size / 4;
+        domain_root = T::exp(domain_root, T::from_usize(folding_factor));
+        domain_offset = T::exp(domain_offset, T::from_usize(folding_factor));
+        domain_size = domain_size / folding_factor;
         mem::swap(&mut positions, &mut augmented_positions);
     }
 
     // 2 ----- verify the remainder of the FRI proof ----------------------------------------------
-    
+    // the remainder has at most remainder_length coefficients, and thus, its degree is within
+    // the bound; so, it is enough to check that it agrees with the values of the last column
     for (&position, evaluation) in positions.iter().zip(evaluations) {
-        if proof.rem_values[position] != evaluation {
-            return Err(String::from("remainder values are inconsistent with values of the last column"));
-        }
-    }
-
-    // make sure the remainder values satisfy the degree
-    return verify_remainder(&proof.rem_values, max_degree_plus_1, domain_root, options.extension_factor());
-}
-
-fn verify_remainder<T>(remainder: &[T], max_degree_plus_1: usize, domain_root: T, extension_factor: usize) -> Result<bool, String>
-    where T: FiniteField
-{
-    if max_degree_plus_1 > remainder.len() {
-        return Err(String::from("remainder degree is greater than number of remainder values"));
-    }
-
-    // exclude points which should be skipped during evaluation
-    let mut positions = Vec::new();
-    for i in 0..remainder.len() {
-        if i % extension_factor != 0 {
-            positions.push(i);
-        }
-    }
-
-    // pick a subset of points from the remainder and interpolate them into a polynomial
-    let domain = T::get_power_series(domain_root, remainder.len());
-    let mut xs = Vec::with_capacity(max_degree_plus_1);
-    let mut ys = Vec::with_capacity(max_degree_plus_1);
-    for i in 0..max_degree_plus_1 {
-        let p = positions[i];
-        xs.push(domain[p]);
-        ys.push(remainder[p]);
-    }
-    let poly = polynom::interpolate(&xs, &ys);
-
-    // check that polynomial evaluates correctly for all other points in the remainder
-    for i in max_degree_plus_1..positions.len() {
-        let p = positions[i];
-        if polynom::eval(&poly, domain[p]) != remainder[p] {
-            return Err(format!("remainder is not a valid degree {} polynomial", max_degree_plus_1 - 1));
+        let x = T::mul(domain_offset, T::exp(domain_root, T::from_usize(position)));
+        if polynom::eval(&proof.remainder, x) != evaluation {
+            return Err(String::from("remainder polynomial is inconsistent with values of the last column"));
         }
     }
 
@@ -127,11 +142,12 @@ fn verify_remainder<T>(remainder: &[T], max_degree_plus_1: usize, domain_root: T
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn get_column_values<T>(values: &Vec<[T; 4]>, positions: &[usize], augmented_positions: &[usize], column_length: usize) -> Vec<T>
+fn get_column_values<T>(values: &[Vec<T>], positions: &[usize], augmented_positions: &[usize], column_length: usize, folding_factor: usize) -> Vec<T>
     where T: FiniteField
 {
-    let row_length = column_length / 4;
+    let row_length = column_length / folding_factor;
 
+    // augmented positions are derived from positions, so a matching augmented position always exists
     let mut result = Vec::new();
     for position in positions {
         let idx = augmented_positions.iter().position(|&v| v == position % row_length).unwrap();
@@ -150,38 +166,4 @@ fn build_layer_merkle_proof<T>(layer: &FriLayer<T>, options: &ProofOptions) -> B
         nodes   : layer.nodes.clone(),
         depth   : layer.depth
     };
-}
-
-// TESTS
-// ================================================================================================
-#[cfg(test)]
-mod tests {
-    
-    use crate::{ F64, FiniteField, polynom };
-
-    #[test]
-    fn verify_remainder() {
-        let degree_plus_1: usize = 32;
-        let root = F64::get_root_of_unity(degree_plus_1 * 2);
-        let extension_factor = 16;
-
-        let mut remainder = F64::rand_vector(degree_plus_1);
-        remainder.resize(degree_plus_1 * 2, 0);
-        polynom::eval_fft(&mut remainder, true);
-
-        // check against exact degree
-        let result = super::verify_remainder(&remainder, degree_plus_1, root, extension_factor);
-        assert_eq!(Ok(true), result);
-
-        // check against higher degree
-        let result = super::verify_remainder(&remainder, degree_plus_1 + 1, root, extension_factor);
-        assert_eq!(Ok(true), result);
-
-        // check against lower degree
-        let degree_plus_1 = degree_plus_1 - 1;
-        let result = super::verify_remainder(&remainder, degree_plus_1, root, extension_factor);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", degree_plus_1 - 1);
-        assert_eq!(Err(err_msg), result);
-    }
-
 }
\ No newline at end of file
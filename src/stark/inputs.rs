@@ -4,6 +4,7 @@ use crate::stark::{ MAX_PUBLIC_INPUTS };
 #[derive(Clone, Debug)]
 pub struct ProgramInputs<T: FiniteField> {
     public: Vec<T>,
+    tape  : Vec<T>,
     secret: [Vec<T>; 2],
 }
 
@@ -22,23 +23,34 @@ impl <T> ProgramInputs<T>
 
         return ProgramInputs {
             public  : public.to_vec(),
+            tape    : Vec::new(),
             secret  : [secret_a.to_vec(), secret_b.to_vec()]
         };
     }
 
-    /// Returns `ProgramInputs` with public and secret input tapes set to empty vectors.
+    /// Returns `ProgramInputs` initialized with the provided public inputs, public input tape,
+    /// and secret inputs. Values from the public input tape are read by READP operation.
+    pub fn with_tape(public: &[T], tape: &[T], secret_a: &[T], secret_b: &[T]) -> ProgramInputs<T> {
+        let mut inputs = ProgramInputs::new(public, secret_a, secret_b);
+        inputs.tape = tape.to_vec();
+        return inputs;
+    }
+
+    /// Returns `ProgramInputs` with public inputs and all input tapes set to empty vectors.
     pub fn none() -> ProgramInputs<T> {
         return ProgramInputs {
             public  : Vec::new(),
+            tape    : Vec::new(),
             secret  : [Vec::new(), Vec::new()],
         };
     }
 
-    /// Returns `ProgramInputs` initialized with the provided public inputs and secret
+    /// Returns `ProgramInputs` initialized with the provided public inputs and public and secret
     /// input tapes set to empty vectors.
     pub fn from_public(public: &[T]) -> ProgramInputs<T> {
         return ProgramInputs {
             public: public.to_vec(),
+            tape  : Vec::new(),
             secret: [vec![], vec![]]
         };
     }
@@ -47,6 +59,10 @@ impl <T> ProgramInputs<T>
         return &self.public;
     }
 
+    pub fn get_public_tape(&self) -> &[T] {
+        return &self.tape;
+    }
+
     pub fn get_secret_inputs(&self) -> &[Vec<T>; 2] {
         return &self.secret;
     }
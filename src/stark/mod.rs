@@ -7,6 +7,10 @@ mod options;
 mod prover;
 mod verifier;
 mod proof;
+mod estimate;
+mod segment;
+mod transcript;
+mod policy;
 mod fri;
 mod utils;
 
@@ -24,24 +28,30 @@ pub use utils::{
     Hasher,
     Accumulator,
     ConstraintCoefficients,
-    CompositionCoefficients };
+    CompositionCoefficients,
+    Prng };
 
 pub use options::ProofOptions;
-pub use proof::{ StarkProof, DeepValues };
-pub use prover::{ prove };
-pub use verifier::{ verify };
+pub use proof::{ StarkProof, DeepValues, BlindingValues };
+pub use estimate::{ ProofEstimate };
+pub use segment::{ SegmentState, SegmentProof };
+pub use transcript::{ Transcript };
+pub use policy::{ VerifierPolicy };
+pub use prover::{ prove, prove_segment };
+pub use verifier::{ verify, verify_chain, verify_many, BatchItem };
 
 // GENERAL CONSTANTS
 // ------------------------------------------------------------------------------------------------
 pub const MIN_TRACE_LENGTH  : usize = 16;
 pub const MAX_REGISTER_COUNT: usize = 128;
+const MAX_DOMAIN_DEPTH      : usize = 32;
 
 // HASH OPERATION
 // ------------------------------------------------------------------------------------------------
 const HASH_STATE_RATE       : usize = 4;
 const HASH_STATE_CAPACITY   : usize = 2;
 const HASH_STATE_WIDTH      : usize = HASH_STATE_RATE + HASH_STATE_CAPACITY;
-const HASH_CYCLE_LENGTH     : usize = 16;
+pub const HASH_CYCLE_LENGTH : usize = 16;
 
 // HASH ACCUMULATOR
 // ------------------------------------------------------------------------------------------------
@@ -53,19 +63,20 @@ const ACC_CYCLE_LENGTH      : usize = 16;
 // DECODER TRACE
 // ------------------------------------------------------------------------------------------------
 //
-//   op  ╒═════════ op_bits ═══════════╕╒══════ op_acc ════════╕
-//    0      1    2     3     4     5     6     7     8     9
-// ├─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┤
+//   op  ╒═════════ op_bits ═══════════╕╒══════ op_acc ════════╕╒═════ tape_acc ═══════╕
+//    0      1    2     3     4     5     6     7     8     9     10    11    12    13
+// ├─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┤
 
 const NUM_OP_BITS           : usize = 5;
 const NUM_LD_OPS            : usize = 32;
 
-const DECODER_WIDTH         : usize = 1 + NUM_OP_BITS + ACC_STATE_WIDTH;
+const DECODER_WIDTH         : usize = 1 + NUM_OP_BITS + 2 * ACC_STATE_WIDTH;
 
 const OP_CODE_INDEX         : usize = 0;
 const OP_BITS_RANGE         : Range<usize> = Range { start: 1, end: 6 };
 const OP_ACC_RANGE          : Range<usize> = Range { start: 6, end: 6 + ACC_STATE_WIDTH };
 const PROG_HASH_RANGE       : Range<usize> = Range { start: 6, end: 6 + ACC_STATE_RATE  };
+const TAPE_ACC_RANGE        : Range<usize> = Range { start: 10, end: 10 + ACC_STATE_WIDTH };
 
 // STACK TRACE
 // ------------------------------------------------------------------------------------------------
@@ -1,5 +1,6 @@
+use std::{ convert::TryFrom, thread::available_parallelism };
 use serde::{ Serialize, Deserialize };
-use crate::crypto::{ HashFunction, hash };
+use crate::crypto::{ HashFunction };
 use super::MAX_CONSTRAINT_DEGREE;
 
 // CONSTANTS
@@ -7,19 +8,51 @@ use super::MAX_CONSTRAINT_DEGREE;
 const DEFAULT_EXTENSION_FACTOR: u8 = (MAX_CONSTRAINT_DEGREE * 4) as u8;
 const DEFAULT_NUM_QUERIES     : u8 = 50;
 const DEFAULT_GRINDING_FACTOR : u8 = 20;
+const DEFAULT_FOLDING_FACTOR  : u8 = 4;
+const DEFAULT_REMAINDER_DEGREE: u8 = 63;
+const DEFAULT_CAP_HEIGHT      : u8 = 0;
+
+const MIN_EXTENSION_FACTOR    : usize = 16;
+const MAX_EXTENSION_FACTOR    : usize = 256;
+const MAX_NUM_QUERIES         : usize = 128;
+const MAX_GRINDING_FACTOR     : u32 = 32;
+const MIN_FOLDING_FACTOR      : usize = 2;
+const MAX_FOLDING_FACTOR      : usize = 16;
+const MAX_REMAINDER_DEGREE    : usize = 1023;
+const MAX_CAP_HEIGHT          : u8 = 8;
 
 // TYPES AND INTERFACES
 // ================================================================================================
 
-// TODO: validate field values on de-serialization
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawProofOptions")]
 pub struct ProofOptions {
     extension_factor    : u8,   // stored as power of 2
     num_queries         : u8,
     grinding_factor     : u8,
+    folding_factor      : u8,   // stored as power of 2
+    remainder_degree    : u8,   // max degree + 1, stored as power of 2
+    merkle_cap_height   : u8,
+    hash_function       : HashFunction,
+    zero_knowledge      : bool,
+    #[serde(skip)]
+    num_threads         : usize,  // 0 means all available cores; not a part of the proof
+    #[serde(skip)]
+    low_memory          : bool,   // not a part of the proof
+}
 
-    #[serde(with = "hash_fn_serialization")]
-    hash_function: HashFunction,
+/// Proof options as read from serialized data; these are converted into `ProofOptions` only
+/// if all field values are valid.
+#[derive(Deserialize)]
+struct RawProofOptions {
+    extension_factor    : u8,
+    num_queries         : u8,
+    grinding_factor     : u8,
+    folding_factor      : u8,
+    remainder_degree    : u8,
+    merkle_cap_height   : u8,
+    hash_function       : HashFunction,
+    zero_knowledge      : bool,
 }
 
 // PROOF OPTIONS IMPLEMENTATION
@@ -33,19 +66,23 @@ impl ProofOptions {
         hash_function    : HashFunction) -> ProofOptions
     {
         assert!(extension_factor.is_power_of_two(), "extension_factor must be a power of 2");
-        assert!(extension_factor >= 16, "extension_factor cannot be smaller than 16");
-        assert!(extension_factor <= 256, "extension_factor cannot be greater than 256");
+        assert!(extension_factor >= MIN_EXTENSION_FACTOR,
+            "extension_factor cannot be smaller than {}", MIN_EXTENSION_FACTOR);
+        assert!(extension_factor <= MAX_EXTENSION_FACTOR,
+            "extension_factor cannot be greater than {}", MAX_EXTENSION_FACTOR);
 
         assert!(num_queries > 0, "num_queries must be greater than 0");
-        assert!(num_queries <= 128, "num_queries cannot be greater than 128");
+        assert!(num_queries <= MAX_NUM_QUERIES, "num_queries cannot be greater than {}", MAX_NUM_QUERIES);
 
-        assert!(grinding_factor <= 32, "grinding factor cannot be greater than 32");
+        assert!(grinding_factor <= MAX_GRINDING_FACTOR,
+            "grinding factor cannot be greater than {}", MAX_GRINDING_FACTOR);
 
         return ProofOptions {
             extension_factor    : extension_factor.trailing_zeros() as u8,
             num_queries         : num_queries as u8,
             grinding_factor     : grinding_factor as u8,
-            hash_function
+            hash_function,
+            ..ProofOptions::default()
         };
     }
 
@@ -61,10 +98,146 @@ impl ProofOptions {
         return self.grinding_factor as u32;
     }
 
+    /// Returns the factor by which each FRI layer reduces the size of the domain and the degree
+    /// of the polynomial.
+    pub fn folding_factor(&self) -> usize {
+        return 1 << (self.folding_factor as usize);
+    }
+
+    /// Returns these options with the FRI folding factor set to `folding_factor`; the folding
+    /// factor must be 2, 4, 8, or 16.
+    pub fn with_folding_factor(mut self, folding_factor: usize) -> ProofOptions {
+        assert!(folding_factor.is_power_of_two(), "folding_factor must be a power of 2");
+        assert!(folding_factor >= MIN_FOLDING_FACTOR,
+            "folding_factor cannot be smaller than {}", MIN_FOLDING_FACTOR);
+        assert!(folding_factor <= MAX_FOLDING_FACTOR,
+            "folding_factor cannot be greater than {}", MAX_FOLDING_FACTOR);
+        assert!(is_remainder_large_enough(self.max_remainder_degree(), self.extension_factor(), folding_factor),
+            "folding_factor is too large for the max_remainder_degree and extension_factor");
+
+        self.folding_factor = folding_factor.trailing_zeros() as u8;
+        return self;
+    }
+
+    /// Returns the max degree of the polynomial sent in the clear as the last FRI layer.
+    pub fn max_remainder_degree(&self) -> usize {
+        return (1 << (self.remainder_degree as usize)) - 1;
+    }
+
+    /// Returns these options with the max degree of the FRI remainder set to
+    /// `max_remainder_degree`; the degree must be one less than a power of 2.
+    pub fn with_max_remainder_degree(mut self, max_remainder_degree: usize) -> ProofOptions {
+        assert!((max_remainder_degree + 1).is_power_of_two(),
+            "max_remainder_degree must be one less than a power of 2");
+        assert!(max_remainder_degree <= MAX_REMAINDER_DEGREE,
+            "max_remainder_degree cannot be greater than {}", MAX_REMAINDER_DEGREE);
+        assert!(is_remainder_large_enough(max_remainder_degree, self.extension_factor(), self.folding_factor()),
+            "max_remainder_degree is too small for the extension_factor and folding_factor");
+
+        self.remainder_degree = (max_remainder_degree + 1).trailing_zeros() as u8;
+        return self;
+    }
+
+    /// Returns the max number of values in the last FRI layer. The composition polynomial is
+    /// evaluated over a domain which is extension_factor / MAX_CONSTRAINT_DEGREE times larger
+    /// than its degree, and FRI layers preserve this ratio.
+    pub fn max_remainder_length(&self) -> usize {
+        return (self.max_remainder_degree() + 1) * self.extension_factor() / MAX_CONSTRAINT_DEGREE;
+    }
+
+    /// Returns the number of FRI layers (not counting the remainder) for the LDE domain of the
+    /// specified size.
+    pub fn num_fri_layers(&self, domain_size: usize) -> usize {
+        let mut result = 0;
+        let mut layer_domain_size = domain_size;
+        while layer_domain_size > self.max_remainder_length() {
+            result += 1;
+            layer_domain_size = layer_domain_size / self.folding_factor();
+        }
+        return result;
+    }
+
+    /// Returns the height of Merkle caps used to commit to the trace, constraint evaluations, and
+    /// FRI layers. Instead of a single root, a commitment contains all 2^height nodes at this
+    /// level of the tree, and authentication paths end at these nodes.
+    pub fn merkle_cap_height(&self) -> u8 {
+        return self.merkle_cap_height;
+    }
+
+    /// Returns these options with the height of Merkle caps set to `merkle_cap_height`.
+    pub fn with_merkle_cap_height(mut self, merkle_cap_height: u8) -> ProofOptions {
+        assert!(merkle_cap_height <= MAX_CAP_HEIGHT,
+            "merkle_cap_height cannot be greater than {}", MAX_CAP_HEIGHT);
+        self.merkle_cap_height = merkle_cap_height;
+        return self;
+    }
+
     pub fn hash_function(&self) -> HashFunction {
         return self.hash_function;
     }
 
+    pub fn zero_knowledge(&self) -> bool {
+        return self.zero_knowledge;
+    }
+
+    /// Returns these options with the zero-knowledge mode set to `zero_knowledge`.
+    pub fn with_zero_knowledge(mut self, zero_knowledge: bool) -> ProofOptions {
+        self.zero_knowledge = zero_knowledge;
+        return self;
+    }
+
+    /// Returns the number of threads the prover distributes work across. This setting affects
+    /// only how fast a proof is generated: it is not included in the proof, and the proof does
+    /// not depend on it. By default, all available CPU cores are used.
+    pub fn num_threads(&self) -> usize {
+        if self.num_threads == 0 {
+            return available_parallelism().map(|n| n.get()).unwrap_or(1);
+        }
+        return self.num_threads;
+    }
+
+    /// Returns these options with the number of prover threads set to `num_threads`; 0 means
+    /// that all available CPU cores are used.
+    pub fn with_num_threads(mut self, num_threads: usize) -> ProofOptions {
+        self.num_threads = num_threads;
+        return self;
+    }
+
+    /// Returns `true` if the prover should keep only trace polynomials in memory and compute the
+    /// extended execution trace chunk by chunk whenever it is needed. This reduces memory used
+    /// by the prover several times at the expense of somewhat slower proof generation. Same as
+    /// the number of threads, this setting is not included in the proof, and the proof does not
+    /// depend on it.
+    pub fn low_memory(&self) -> bool {
+        return self.low_memory;
+    }
+
+    /// Returns these options with the low-memory prover mode set to `low_memory`.
+    pub fn with_low_memory(mut self, low_memory: bool) -> ProofOptions {
+        self.low_memory = low_memory;
+        return self;
+    }
+
+    /// Returns the number of coefficients in trace polynomials for an execution trace with the
+    /// specified number of steps. In zero-knowledge mode, trace polynomials are randomized so that
+    /// their evaluations revealed in a proof are independent of the execution trace. A trace value
+    /// is revealed at every queried position and at each of its neighbors in the constraint tree,
+    /// at the next step for all of these positions, and at both DEEP points; so, at least this
+    /// many random coefficients are needed.
+    pub fn randomized_trace_length(&self, trace_length: usize) -> usize {
+        if !self.zero_knowledge {
+            return trace_length;
+        }
+        let num_revealed_values = 4 * self.num_queries() + 2;
+        return (trace_length + num_revealed_values).next_power_of_two();
+    }
+
+    /// Returns the size of the LDE domain for an execution trace with the specified number
+    /// of steps.
+    pub fn domain_size(&self, trace_length: usize) -> usize {
+        return self.randomized_trace_length(trace_length) * self.extension_factor();
+    }
+
     pub fn security_level(&self, optimistic: bool) -> u32 {
         let one_over_rho = (self.extension_factor() / MAX_CONSTRAINT_DEGREE) as u32;
         let security_factor = 31 - one_over_rho.leading_zeros(); // same as log2(one_over_rho)
@@ -86,36 +259,206 @@ impl Default for ProofOptions {
             extension_factor    : DEFAULT_EXTENSION_FACTOR.trailing_zeros() as u8,
             num_queries         : DEFAULT_NUM_QUERIES,
             grinding_factor     : DEFAULT_GRINDING_FACTOR,
-            hash_function       : hash::blake3,
+            folding_factor      : DEFAULT_FOLDING_FACTOR.trailing_zeros() as u8,
+            remainder_degree    : (DEFAULT_REMAINDER_DEGREE + 1).trailing_zeros() as u8,
+            merkle_cap_height   : DEFAULT_CAP_HEIGHT,
+            hash_function       : HashFunction::Blake3,
+            zero_knowledge      : false,
+            num_threads         : 0,
+            low_memory          : false,
         };
     }
 
 }
 
-// HASH FUNCTION SERIALIZATION / DE-SERIALIZATION
-// ================================================================================================
-mod hash_fn_serialization {
+impl TryFrom<RawProofOptions> for ProofOptions {
+    type Error = String;
+
+    fn try_from(raw: RawProofOptions) -> Result<ProofOptions, String> {
+        // extension factor is stored as a power of 2, so anything above 63 would overflow
+        if raw.extension_factor > 63 {
+            return Err(format!("extension factor 2^{} is not valid", raw.extension_factor));
+        }
+        let extension_factor = 1usize << raw.extension_factor;
+        if !(MIN_EXTENSION_FACTOR..=MAX_EXTENSION_FACTOR).contains(&extension_factor) {
+            return Err(format!("extension factor {} is not within the valid range [{}, {}]",
+                extension_factor, MIN_EXTENSION_FACTOR, MAX_EXTENSION_FACTOR));
+        }
 
-    use serde::{ Serializer, Deserializer, Deserialize, ser, de };
-    use crate::crypto::{ HashFunction, hash };
+        let num_queries = raw.num_queries as usize;
+        if num_queries == 0 || num_queries > MAX_NUM_QUERIES {
+            return Err(format!("number of queries {} is not within the valid range [1, {}]",
+                num_queries, MAX_NUM_QUERIES));
+        }
 
-    pub fn serialize<S>(hf: &HashFunction, s: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer
-    {
-        match *hf as usize {
-            f if f == hash::blake3 as usize => s.serialize_u8(0),
-            _ => Err(ser::Error::custom("unsupported hash function"))?
+        let grinding_factor = raw.grinding_factor as u32;
+        if grinding_factor > MAX_GRINDING_FACTOR {
+            return Err(format!("grinding factor {} is not within the valid range [0, {}]",
+                grinding_factor, MAX_GRINDING_FACTOR));
         }
-    }
-    
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashFunction, D::Error>
-    where
-        D: Deserializer<'de>
-    {
-        match Deserialize::deserialize(deserializer)? {
-            0u8 => Ok(hash::blake3),
-            _ => Err(de::Error::custom("unsupported hash function"))
+
+        // folding factor and remainder degree are stored as powers of 2 as well
+        if raw.folding_factor > 63 {
+            return Err(format!("folding factor 2^{} is not valid", raw.folding_factor));
+        }
+        let folding_factor = 1usize << raw.folding_factor;
+        if !(MIN_FOLDING_FACTOR..=MAX_FOLDING_FACTOR).contains(&folding_factor) {
+            return Err(format!("folding factor {} is not within the valid range [{}, {}]",
+                folding_factor, MIN_FOLDING_FACTOR, MAX_FOLDING_FACTOR));
+        }
+
+        if raw.remainder_degree as u32 > (MAX_REMAINDER_DEGREE + 1).trailing_zeros() {
+            return Err(format!("max remainder degree 2^{} - 1 is not within the valid range [0, {}]",
+                raw.remainder_degree, MAX_REMAINDER_DEGREE));
+        }
+        let max_remainder_degree = (1usize << raw.remainder_degree) - 1;
+        if !is_remainder_large_enough(max_remainder_degree, extension_factor, folding_factor) {
+            return Err(format!("max remainder degree {} is too small for extension factor {} and folding factor {}",
+                max_remainder_degree, extension_factor, folding_factor));
         }
+
+        if raw.merkle_cap_height > MAX_CAP_HEIGHT {
+            return Err(format!("merkle cap height {} is not within the valid range [0, {}]",
+                raw.merkle_cap_height, MAX_CAP_HEIGHT));
+        }
+
+        return Ok(ProofOptions {
+            extension_factor    : raw.extension_factor,
+            num_queries         : raw.num_queries,
+            grinding_factor     : raw.grinding_factor,
+            folding_factor      : raw.folding_factor,
+            remainder_degree    : raw.remainder_degree,
+            merkle_cap_height   : raw.merkle_cap_height,
+            hash_function       : raw.hash_function,
+            zero_knowledge      : raw.zero_knowledge,
+            num_threads         : 0,
+            low_memory          : false,
+        });
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// The last FRI layer is committed to as rows of `folding_factor` values. The last fold leaves
+/// more than max_remainder_length / folding_factor values; so, to fill at least one row, the max
+/// remainder length must be at least folding_factor^2 / 2.
+fn is_remainder_large_enough(max_remainder_degree: usize, extension_factor: usize, folding_factor: usize) -> bool {
+    let max_remainder_length = (max_remainder_degree + 1) * extension_factor / MAX_CONSTRAINT_DEGREE;
+    return max_remainder_length * 2 >= folding_factor * folding_factor;
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::crypto::HashFunction;
+    use super::ProofOptions;
+
+    #[test]
+    fn deserialize() {
+        let options = ProofOptions::default();
+        let bytes = bincode::serialize(&options).unwrap();
+        let result = bincode::deserialize::<ProofOptions>(&bytes).unwrap();
+        assert_eq!(options.extension_factor(), result.extension_factor());
+        assert_eq!(options.num_queries(), result.num_queries());
+        assert_eq!(options.grinding_factor(), result.grinding_factor());
+        assert_eq!(options.folding_factor(), result.folding_factor());
+        assert_eq!(options.max_remainder_degree(), result.max_remainder_degree());
+        assert_eq!(options.merkle_cap_height(), result.merkle_cap_height());
+        assert_eq!(options.zero_knowledge(), result.zero_knowledge());
+
+        // extension factor too small
+        let result = bincode::deserialize::<ProofOptions>(&[3, 50, 20, 2, 6, 0, 0, 0]);
+        assert_eq!("extension factor 8 is not within the valid range [16, 256]",
+            result.err().unwrap().to_string());
+
+        // extension factor overflows
+        let result = bincode::deserialize::<ProofOptions>(&[200, 50, 20, 2, 6, 0, 0, 0]);
+        assert_eq!("extension factor 2^200 is not valid", result.err().unwrap().to_string());
+
+        // zero queries
+        let result = bincode::deserialize::<ProofOptions>(&[5, 0, 20, 2, 6, 0, 0, 0]);
+        assert_eq!("number of queries 0 is not within the valid range [1, 128]",
+            result.err().unwrap().to_string());
+
+        // grinding factor too large
+        let result = bincode::deserialize::<ProofOptions>(&[5, 50, 33, 2, 6, 0, 0, 0]);
+        assert_eq!("grinding factor 33 is not within the valid range [0, 32]",
+            result.err().unwrap().to_string());
+
+        // folding factor too large
+        let result = bincode::deserialize::<ProofOptions>(&[5, 50, 20, 5, 6, 0, 0, 0]);
+        assert_eq!("folding factor 32 is not within the valid range [2, 16]",
+            result.err().unwrap().to_string());
+
+        // remainder degree too large
+        let result = bincode::deserialize::<ProofOptions>(&[5, 50, 20, 2, 11, 0, 0, 0]);
+        assert_eq!("max remainder degree 2^11 - 1 is not within the valid range [0, 1023]",
+            result.err().unwrap().to_string());
+
+        // remainder too small for the folding factor
+        let result = bincode::deserialize::<ProofOptions>(&[4, 50, 20, 4, 5, 0, 0, 0]);
+        assert_eq!("max remainder degree 31 is too small for extension factor 16 and folding factor 16",
+            result.err().unwrap().to_string());
+
+        // merkle cap height too large
+        let result = bincode::deserialize::<ProofOptions>(&[5, 50, 20, 2, 6, 9, 0, 0]);
+        assert_eq!("merkle cap height 9 is not within the valid range [0, 8]",
+            result.err().unwrap().to_string());
+
+        // unsupported hash function
+        let result = bincode::deserialize::<ProofOptions>(&[5, 50, 20, 2, 6, 0, 6, 0]);
+        assert_eq!("unsupported hash function", result.err().unwrap().to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn randomized_trace_length() {
+        let options = ProofOptions::default();
+        assert_eq!(64, options.randomized_trace_length(64));
+        assert_eq!(64 * 32, options.domain_size(64));
+
+        // 4 values for each of 8 queries + 2 DEEP values need to be hidden
+        let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3).with_zero_knowledge(true);
+        assert_eq!(64, options.randomized_trace_length(16));
+        assert_eq!(64, options.randomized_trace_length(29));
+        assert_eq!(128, options.randomized_trace_length(64));
+        assert_eq!(128 * 32, options.domain_size(64));
+    }
+
+    #[test]
+    fn num_fri_layers() {
+        // default options: fold by 4 until there are at most 256 values left
+        let options = ProofOptions::default();
+        assert_eq!(4, options.folding_factor());
+        assert_eq!(63, options.max_remainder_degree());
+        assert_eq!(256, options.max_remainder_length());
+        assert_eq!(0, options.num_fri_layers(256));
+        assert_eq!(1, options.num_fri_layers(1024));
+        assert_eq!(2, options.num_fri_layers(2048));
+
+        let options = ProofOptions::new(16, 8, 0, HashFunction::Blake3)
+            .with_folding_factor(8)
+            .with_max_remainder_degree(15);
+        assert_eq!(32, options.max_remainder_length());
+        assert_eq!(2, options.num_fri_layers(2048));
+        assert_eq!(3, options.num_fri_layers(4096));
+    }
+
+    #[test]
+    fn prover_settings() {
+        let options = ProofOptions::default();
+        assert!(options.num_threads() >= 1);
+
+        let options = ProofOptions::default().with_num_threads(3);
+        assert_eq!(3, options.num_threads());
+
+        // the number of threads and the low-memory mode are not serialized, and thus, do not
+        // affect the proof
+        let options = options.with_low_memory(true);
+        assert!(options.low_memory());
+        let bytes = bincode::serialize(&options).unwrap();
+        assert_eq!(bincode::serialize(&ProofOptions::default()).unwrap(), bytes);
+    }
+}
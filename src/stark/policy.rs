@@ -0,0 +1,145 @@
+use std::ops::RangeInclusive;
+use crate::crypto::{ HashFunction };
+use super::{ ProofOptions };
+
+// CONSTANTS
+// ================================================================================================
+const DEFAULT_MIN_SECURITY_LEVEL: u32 = 120;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Specifies which proof options a verifier is willing to accept. Proofs generated with options
+/// which do not satisfy the policy are rejected before any other verification work is done.
+#[derive(Clone)]
+pub struct VerifierPolicy {
+    min_security_level  : u32,
+    extension_factor    : RangeInclusive<usize>,
+    num_queries         : RangeInclusive<usize>,
+    grinding_factor     : RangeInclusive<u32>,
+    hash_functions      : Vec<HashFunction>,
+}
+
+// VERIFIER POLICY IMPLEMENTATION
+// ================================================================================================
+impl VerifierPolicy {
+
+    pub fn new(
+        min_security_level  : u32,
+        extension_factor    : RangeInclusive<usize>,
+        num_queries         : RangeInclusive<usize>,
+        grinding_factor     : RangeInclusive<u32>,
+        hash_functions      : &[HashFunction]) -> VerifierPolicy
+    {
+        assert!(!extension_factor.is_empty(), "extension_factor range cannot be empty");
+        assert!(!num_queries.is_empty(), "num_queries range cannot be empty");
+        assert!(!grinding_factor.is_empty(), "grinding_factor range cannot be empty");
+        assert!(hash_functions.len() > 0, "at least one hash function must be allowed");
+
+        return VerifierPolicy {
+            min_security_level,
+            extension_factor,
+            num_queries,
+            grinding_factor,
+            hash_functions: hash_functions.to_vec(),
+        };
+    }
+
+    /// Returns a policy which accepts any valid proof options resulting in at least the specified
+    /// security level.
+    pub fn with_security_level(min_security_level: u32) -> VerifierPolicy {
+        return VerifierPolicy {
+            min_security_level,
+            ..VerifierPolicy::default()
+        };
+    }
+
+    pub fn min_security_level(&self) -> u32 {
+        return self.min_security_level;
+    }
+
+    /// Returns an error if the specified `options` do not satisfy this policy. Security level is
+    /// checked against the optimistic estimate (see `ProofOptions::security_level()`).
+    pub fn check(&self, options: &ProofOptions) -> Result<(), String> {
+
+        let extension_factor = options.extension_factor();
+        if !self.extension_factor.contains(&extension_factor) {
+            return Err(format!("extension factor {} is not within the allowed range [{}, {}]",
+                extension_factor, self.extension_factor.start(), self.extension_factor.end()));
+        }
+
+        let num_queries = options.num_queries();
+        if !self.num_queries.contains(&num_queries) {
+            return Err(format!("number of queries {} is not within the allowed range [{}, {}]",
+                num_queries, self.num_queries.start(), self.num_queries.end()));
+        }
+
+        let grinding_factor = options.grinding_factor();
+        if !self.grinding_factor.contains(&grinding_factor) {
+            return Err(format!("grinding factor {} is not within the allowed range [{}, {}]",
+                grinding_factor, self.grinding_factor.start(), self.grinding_factor.end()));
+        }
+
+        let hash_fn = options.hash_function();
+        if !self.hash_functions.contains(&hash_fn) {
+            return Err(format!("hash function {:?} is not allowed", hash_fn));
+        }
+
+        let security_level = options.security_level(true);
+        if security_level < self.min_security_level {
+            return Err(format!("proof security level of {} bits is below the required {} bits",
+                security_level, self.min_security_level));
+        }
+
+        return Ok(());
+    }
+}
+
+impl Default for VerifierPolicy {
+
+    fn default() -> VerifierPolicy {
+        return VerifierPolicy {
+            min_security_level  : DEFAULT_MIN_SECURITY_LEVEL,
+            extension_factor    : 16..=256,
+            num_queries         : 1..=128,
+            grinding_factor     : 0..=32,
+            hash_functions      : vec![HashFunction::Blake3],
+        };
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ ProofOptions, crypto::HashFunction };
+    use super::VerifierPolicy;
+
+    #[test]
+    fn check() {
+        let policy = VerifierPolicy::default();
+        assert_eq!(Ok(()), policy.check(&ProofOptions::default()));
+
+        // security level too low
+        let options = ProofOptions::new(32, 1, 0, HashFunction::Blake3);
+        let err_msg = format!("proof security level of {} bits is below the required {} bits", 2, 120);
+        assert_eq!(Err(err_msg), policy.check(&options));
+
+        let policy = VerifierPolicy::with_security_level(2);
+        assert_eq!(Ok(()), policy.check(&options));
+
+        // option outside of the allowed range
+        let policy = VerifierPolicy::new(0, 16..=256, 20..=128, 0..=32, &[HashFunction::Blake3]);
+        let err_msg = format!("number of queries {} is not within the allowed range [{}, {}]", 1, 20, 128);
+        assert_eq!(Err(err_msg), policy.check(&options));
+
+        // hash function not allowed
+        let options = ProofOptions::new(32, 50, 20, HashFunction::Sha3);
+        let err_msg = String::from("hash function Sha3 is not allowed");
+        assert_eq!(Err(err_msg), policy.check(&options));
+
+        let policy = VerifierPolicy::new(0, 16..=256, 1..=128, 0..=32, &[HashFunction::Blake3, HashFunction::Sha3]);
+        assert_eq!(Ok(()), policy.check(&options));
+    }
+}
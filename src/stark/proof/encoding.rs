@@ -0,0 +1,391 @@
+use std::{ mem, convert::TryFrom };
+use crate::math::{ FiniteField };
+use crate::crypto::{ MerkleTree, BatchMerkleProof };
+use crate::stark::{ Accumulator, ProofOptions, MAX_REGISTER_COUNT, MAX_DOMAIN_DEPTH };
+use crate::stark::fri::{ FriProof, FriLayer };
+use crate::utils::{ as_bytes };
+use super::{ StarkProof, RawStarkProof, DeepValues, BlindingValues };
+
+// CONSTANTS
+// ================================================================================================
+const MAGIC: [u8; 4] = *b"DSTF";
+const CURRENT_VERSION: u8 = 1;
+const OPTIONS_SIZE: usize = 8;
+
+// PROOF ENCODING
+// ================================================================================================
+impl <T> StarkProof<T>
+    where T: FiniteField + Accumulator
+{
+    /// Encodes the proof using a compact binary format; the format is described in the README
+    /// file of the `stark` module.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ProofWriter::default();
+
+        // header and proof options
+        writer.write_bytes(&MAGIC);
+        writer.write_u8(CURRENT_VERSION);
+        writer.write_bytes(&bincode::serialize(&self.options).unwrap());
+        writer.write_u8(self.domain_depth);
+
+        // trace commitment and queried trace states
+        writer.write_cap(&self.trace_cap);
+        let register_count = self.trace_evaluations.first().map_or(0, |e| e.len());
+        writer.write_varint(register_count as u64);
+        writer.write_varint(self.trace_evaluations.len() as u64);
+        for evaluations in self.trace_evaluations.iter() {
+            writer.write_elements(evaluations);
+        }
+        writer.write_paths(&self.trace_nodes);
+
+        // constraint commitment and queried constraint evaluations
+        writer.write_cap(&self.constraint_cap);
+        writer.write_varint(self.constraint_proof.values.len() as u64);
+        for leaf in self.constraint_proof.values.iter() {
+            writer.write_bytes(leaf);
+        }
+        writer.write_paths(&self.constraint_proof.nodes);
+
+        // out-of-domain trace evaluations
+        writer.write_elements(&self.deep_values.trace_at_z1);
+        writer.write_elements(&self.deep_values.trace_at_z2);
+
+        // FRI layers and remainder
+        for layer in self.degree_proof.layers.iter() {
+            writer.write_cap(&layer.cap);
+            writer.write_varint(layer.values.len() as u64);
+            for row in layer.values.iter() {
+                writer.write_elements(row);
+            }
+            writer.write_paths(&layer.nodes);
+        }
+        writer.write_varint(self.degree_proof.remainder.len() as u64);
+        writer.write_elements(&self.degree_proof.remainder);
+
+        // blinding values are present only in zero-knowledge mode; the number of salts and
+        // randomizer values is implied by the number of opened leaves
+        if let Some(blinding) = &self.blinding {
+            writer.write_u8(blinding.trace_depth);
+            for salt in blinding.trace_salts.iter() {
+                writer.write_bytes(salt);
+            }
+            writer.write_elements(&blinding.randomizer_values);
+            writer.write_elements(&[blinding.randomizer_at_z]);
+            for salt in blinding.constraint_salts.iter() {
+                writer.write_bytes(salt);
+            }
+        }
+
+        // proof-of-work nonce
+        writer.write_varint(self.pow_nonce);
+
+        // extension records; none are defined in the current version of the format
+        writer.write_varint(0);
+
+        return writer.bytes;
+    }
+
+    /// Decodes a proof encoded with `to_bytes()` function. The decoder for the layout is chosen
+    /// based on the format version of the proof. The decoded proof goes through the same
+    /// validation as a proof de-serialized with serde.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StarkProof<T>, String> {
+        let mut reader = ProofReader { bytes, pos: 0 };
+
+        // header
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(String::from("proof does not start with a valid magic value"));
+        }
+        let version = reader.read_u8()?;
+        let proof = match version {
+            1 => decode_v1(&mut reader)?,
+            _ => return Err(format!("proof format version {} is not supported", version))
+        };
+
+        if reader.pos != bytes.len() {
+            return Err(format!("proof contains {} unexpected trailing bytes", bytes.len() - reader.pos));
+        }
+
+        return StarkProof::try_from(proof);
+    }
+}
+
+// PROOF DECODERS
+// ================================================================================================
+
+/// Decodes the body of a proof encoded with version 1 of the format; `reader` must be positioned
+/// right after the format version.
+fn decode_v1<T: FiniteField + Accumulator>(reader: &mut ProofReader) -> Result<RawStarkProof<T>, String> {
+    // proof options
+    let options = match bincode::deserialize::<ProofOptions>(reader.read_bytes(OPTIONS_SIZE)?) {
+        Ok(options) => options,
+        Err(err) => return Err(format!("proof options are not valid: {}", err))
+    };
+    let domain_depth = reader.read_u8()?;
+    if domain_depth as usize > MAX_DOMAIN_DEPTH {
+        return Err(format!("domain depth {} is not valid", domain_depth));
+    }
+
+    // no list in the proof can contain more items than the number of queries
+    let num_queries = options.num_queries();
+
+    // sizes of Merkle caps are implied by the depths of the trees and the cap height
+    let cap_height = options.merkle_cap_height();
+
+    // trace commitment and queried trace states
+    let trace_cap = reader.read_hashes(MerkleTree::cap_size(domain_depth, cap_height))?;
+    let register_count = reader.read_count(MAX_REGISTER_COUNT)?;
+    let num_states = reader.read_count(num_queries)?;
+    let mut trace_evaluations = Vec::new();
+    for _ in 0..num_states {
+        trace_evaluations.push(reader.read_elements(register_count)?);
+    }
+    let trace_nodes = reader.read_paths(num_queries)?;
+
+    // constraint commitment and queried constraint evaluations; depth of the constraint
+    // tree is implied by the domain depth and the number of elements packed into each leaf
+    let elements_per_leaf = 32 / mem::size_of::<T>();
+    let constraint_depth = domain_depth.saturating_sub(elements_per_leaf.trailing_zeros() as u8);
+    let constraint_cap = reader.read_hashes(MerkleTree::cap_size(constraint_depth, cap_height))?;
+    let num_leaves = reader.read_count(num_queries)?;
+    let leaves = reader.read_hashes(num_leaves)?;
+    let constraint_proof = BatchMerkleProof {
+        values  : leaves,
+        nodes   : reader.read_paths(num_queries)?,
+        depth   : constraint_depth,
+    };
+
+    // out-of-domain trace evaluations
+    let deep_values = DeepValues {
+        trace_at_z1 : reader.read_elements(register_count)?,
+        trace_at_z2 : reader.read_elements(register_count)?,
+    };
+
+    // FRI layers and remainder; the number of layers, their depths, and the length of each
+    // row are implied by the domain depth and proof options
+    let folding_factor = options.folding_factor();
+    let mut layers = Vec::new();
+    let mut layer_domain_size = 1usize << domain_depth;
+    for _ in 0..options.num_fri_layers(layer_domain_size) {
+        let layer_depth = (layer_domain_size / folding_factor).trailing_zeros() as u8;
+        let cap = reader.read_hashes(MerkleTree::cap_size(layer_depth, cap_height))?;
+        let num_rows = reader.read_count(num_queries)?;
+        let mut values = Vec::new();
+        for _ in 0..num_rows {
+            values.push(reader.read_elements(folding_factor)?);
+        }
+        let nodes = reader.read_paths(num_queries)?;
+        layer_domain_size = layer_domain_size / folding_factor;
+        layers.push(FriLayer { cap, values, nodes, depth: layer_depth });
+    }
+    let remainder_length = reader.read_count(options.max_remainder_length())?;
+    let remainder = reader.read_elements(remainder_length)?;
+    let degree_proof = FriProof { layers, remainder };
+
+    // blinding values are present only in zero-knowledge mode
+    let blinding = if options.zero_knowledge() {
+        let trace_depth = reader.read_u8()?;
+        let mut trace_salts = Vec::new();
+        for _ in 0..num_states {
+            trace_salts.push(reader.read_hash()?);
+        }
+        let randomizer_values = reader.read_elements(num_states)?;
+        let randomizer_at_z = reader.read_elements(1)?[0];
+        let mut constraint_salts = Vec::new();
+        for _ in 0..num_leaves {
+            constraint_salts.push(reader.read_hash()?);
+        }
+        Some(BlindingValues { trace_depth, trace_salts, randomizer_values, randomizer_at_z, constraint_salts })
+    }
+    else {
+        None
+    };
+
+    // proof-of-work nonce
+    let pow_nonce = reader.read_varint()?;
+
+    // extension records; version 1 defines none, so all optional records are skipped
+    reader.skip_extensions()?;
+
+    return Ok(RawStarkProof {
+        trace_cap,
+        domain_depth,
+        trace_nodes,
+        trace_evaluations,
+        constraint_cap,
+        constraint_proof,
+        deep_values,
+        degree_proof,
+        pow_nonce,
+        blinding,
+        options,
+    });
+}
+
+// PROOF WRITER
+// ================================================================================================
+#[derive(Default)]
+struct ProofWriter {
+    bytes   : Vec<u8>,
+}
+
+impl ProofWriter {
+
+    fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Writes `value` using LEB128 encoding: 7 bits per byte, with the high bit of each byte
+    /// set if more bytes follow.
+    fn write_varint(&mut self, value: u64) {
+        let mut value = value;
+        while value >= 0x80 {
+            self.bytes.push((value as u8) | 0x80);
+            value = value >> 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    /// Writes nodes of a Merkle cap; the number of nodes is implied by the depth of the tree and
+    /// the cap height, and thus, is not written.
+    fn write_cap(&mut self, cap: &[[u8; 32]]) {
+        for node in cap.iter() {
+            self.write_bytes(node);
+        }
+    }
+
+    fn write_elements<T: FiniteField>(&mut self, elements: &[T]) {
+        self.bytes.extend_from_slice(as_bytes(elements));
+    }
+
+    /// Writes authentication paths of a batch Merkle proof; since a path can be at most as long
+    /// as the depth of the tree, the length of each path is written as a single byte.
+    fn write_paths(&mut self, paths: &[Vec<[u8; 32]>]) {
+        self.write_varint(paths.len() as u64);
+        for path in paths.iter() {
+            self.write_u8(path.len() as u8);
+            for node in path.iter() {
+                self.write_bytes(node);
+            }
+        }
+    }
+}
+
+// PROOF READER
+// ================================================================================================
+struct ProofReader<'a> {
+    bytes   : &'a [u8],
+    pos     : usize,
+}
+
+impl <'a> ProofReader<'a> {
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    fn read_bytes(&mut self, num_bytes: usize) -> Result<&'a [u8], String> {
+        if num_bytes > self.bytes.len() - self.pos {
+            return Err(String::from("proof ended unexpectedly"));
+        }
+        let result = &self.bytes[self.pos..(self.pos + num_bytes)];
+        self.pos += num_bytes;
+        return Ok(result);
+    }
+
+    fn read_hash(&mut self) -> Result<[u8; 32], String> {
+        let mut result = [0u8; 32];
+        result.copy_from_slice(self.read_bytes(32)?);
+        return Ok(result);
+    }
+
+    fn read_hashes(&mut self, num_hashes: usize) -> Result<Vec<[u8; 32]>, String> {
+        let mut result = Vec::new();
+        for _ in 0..num_hashes {
+            result.push(self.read_hash()?);
+        }
+        return Ok(result);
+    }
+
+    /// Reads a LEB128-encoded integer; only the shortest encoding of an integer is accepted so
+    /// that every integer has exactly one valid encoding.
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut result = 0u64;
+        for i in 0..10 {
+            let byte = self.read_u8()?;
+            if i == 9 && byte > 1 {
+                return Err(String::from("proof contains an integer which is too large"));
+            }
+            result |= ((byte & 0x7F) as u64) << (i * 7);
+            if byte & 0x80 == 0 {
+                if byte == 0 && i > 0 {
+                    return Err(String::from("proof contains an integer which is not encoded canonically"));
+                }
+                return Ok(result);
+            }
+        }
+        return Err(String::from("proof contains an integer which is too large"));
+    }
+
+    fn read_count(&mut self, max_count: usize) -> Result<usize, String> {
+        let count = self.read_varint()?;
+        if count > max_count as u64 {
+            return Err(format!("proof contains a list of {} items, but at most {} items are allowed",
+                count, max_count));
+        }
+        return Ok(count as usize);
+    }
+
+    fn read_elements<T: FiniteField>(&mut self, num_elements: usize) -> Result<Vec<T>, String> {
+        let element_size = mem::size_of::<T>();
+        let num_bytes = match num_elements.checked_mul(element_size) {
+            Some(num_bytes) => num_bytes,
+            None => return Err(String::from("proof ended unexpectedly"))
+        };
+        let bytes = self.read_bytes(num_bytes)?;
+        return Ok(bytes.chunks(element_size).map(|chunk| T::from_bytes(chunk)).collect());
+    }
+
+    /// Reads extension records which follow the last field of the proof and skips them. Each
+    /// record is written as a tag, the length of the payload, and the payload, and records must be
+    /// ordered by tag. Records with even tags are optional and are skipped by decoders which do
+    /// not know them; records with odd tags are required for verification, and thus, a proof with
+    /// such a record is rejected by decoders which do not know the tag.
+    fn skip_extensions(&mut self) -> Result<(), String> {
+        let num_records = self.read_varint()?;
+        let mut prev_tag: Option<u64> = None;
+        for _ in 0..num_records {
+            let tag = self.read_varint()?;
+            if prev_tag.map_or(false, |prev_tag| tag <= prev_tag) {
+                return Err(String::from("proof extension records are not ordered by tag"));
+            }
+            if tag & 1 == 1 {
+                return Err(format!("proof contains an unsupported required extension record {}", tag));
+            }
+            let length = self.read_varint()?;
+            if length > (self.bytes.len() - self.pos) as u64 {
+                return Err(String::from("proof ended unexpectedly"));
+            }
+            self.read_bytes(length as usize)?;
+            prev_tag = Some(tag);
+        }
+        return Ok(());
+    }
+
+    fn read_paths(&mut self, max_paths: usize) -> Result<Vec<Vec<[u8; 32]>>, String> {
+        let num_paths = self.read_count(max_paths)?;
+        let mut paths = Vec::new();
+        for _ in 0..num_paths {
+            let path_length = self.read_u8()?;
+            let mut path = Vec::with_capacity(path_length as usize);
+            for _ in 0..path_length {
+                path.push(self.read_hash()?);
+            }
+            paths.push(path);
+        }
+        return Ok(paths);
+    }
+}
@@ -0,0 +1,393 @@
+use std::{ mem, convert::TryFrom };
+use serde::{ Serialize, Deserialize };
+use crate::math::{ FiniteField };
+use crate::crypto::{ MerkleTree, BatchMerkleProof };
+use crate::stark::{ Accumulator, fri::{ self, FriProof }, TraceState, ProofOptions };
+use crate::stark::{ MIN_TRACE_LENGTH, MAX_DOMAIN_DEPTH, DECODER_WIDTH, MAX_STACK_DEPTH, utils };
+use crate::utils::{ uninit_vector, as_bytes };
+
+mod encoding;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawStarkProof<T>")]
+pub struct StarkProof<T: FiniteField + Accumulator> {
+    trace_cap           : Vec<[u8; 32]>,
+    domain_depth        : u8,
+    trace_nodes         : Vec<Vec<[u8; 32]>>,
+    trace_evaluations   : Vec<Vec<T>>,
+    constraint_cap      : Vec<[u8; 32]>,
+    constraint_proof    : BatchMerkleProof,
+    deep_values         : DeepValues<T>,
+    degree_proof        : FriProof<T>,
+    pow_nonce           : u64,
+    blinding            : Option<BlindingValues<T>>,
+    options             : ProofOptions
+}
+
+/// STARK proof as read from serialized data; this is converted into a `StarkProof` only if the
+/// shape of the proof is consistent with its options and all field elements are valid.
+#[derive(Deserialize)]
+struct RawStarkProof<T: FiniteField + Accumulator> {
+    trace_cap           : Vec<[u8; 32]>,
+    domain_depth        : u8,
+    trace_nodes         : Vec<Vec<[u8; 32]>>,
+    trace_evaluations   : Vec<Vec<T>>,
+    constraint_cap      : Vec<[u8; 32]>,
+    constraint_proof    : BatchMerkleProof,
+    deep_values         : DeepValues<T>,
+    degree_proof        : FriProof<T>,
+    pow_nonce           : u64,
+    blinding            : Option<BlindingValues<T>>,
+    options             : ProofOptions
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeepValues<T: FiniteField + Accumulator> {
+    pub trace_at_z1     : Vec<T>,
+    pub trace_at_z2     : Vec<T>,
+}
+
+/// Values which a proof generated in zero-knowledge mode carries in addition to the values of
+/// a regular proof: salts of all opened Merkle tree leaves, and evaluations of the randomizer
+/// polynomial at the queried positions and at the DEEP point z. Length of the execution trace
+/// cannot be inferred from the domain size of such a proof, and thus, it is included as well.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindingValues<T: FiniteField + Accumulator> {
+    pub trace_depth         : u8,
+    pub trace_salts         : Vec<[u8; 32]>,
+    pub randomizer_values   : Vec<T>,
+    pub randomizer_at_z     : T,
+    pub constraint_salts    : Vec<[u8; 32]>,
+}
+
+// STARK PROOF IMPLEMENTATION
+// ================================================================================================
+impl <T> StarkProof<T>
+    where T: FiniteField + Accumulator
+{
+    pub fn new(
+        trace_cap           : &[[u8; 32]],
+        trace_proof         : BatchMerkleProof,
+        trace_evaluations   : Vec<Vec<T>>,
+        constraint_cap      : &[[u8; 32]],
+        constraint_proof    : BatchMerkleProof,
+        deep_values         : DeepValues<T>,
+        degree_proof        : FriProof<T>,
+        pow_nonce           : u64,
+        blinding            : Option<BlindingValues<T>>,
+        options             : &ProofOptions ) -> StarkProof<T>
+    {
+        return StarkProof {
+            trace_cap           : trace_cap.to_vec(),
+            domain_depth        : trace_proof.depth,
+            trace_nodes         : trace_proof.nodes,
+            trace_evaluations   : trace_evaluations,
+            constraint_cap      : constraint_cap.to_vec(),
+            constraint_proof    : constraint_proof,
+            deep_values         : deep_values,
+            degree_proof        : degree_proof,
+            pow_nonce           : pow_nonce,
+            blinding            : blinding,
+            options             : options.clone()
+        };
+    }
+
+    pub fn trace_cap(&self) -> &[[u8; 32]] {
+        return &self.trace_cap;
+    }
+
+    pub fn options(&self) -> &ProofOptions {
+        return &self.options;
+    }
+
+    pub fn domain_size(&self) -> usize {
+        return usize::pow(2, self.domain_depth as u32);
+    }
+
+    pub fn trace_proof(&self) -> BatchMerkleProof {
+
+        let hash = self.options.hash_function();
+        let mut hashed_states = uninit_vector::<[u8; 32]>(self.trace_evaluations.len());
+        match &self.blinding {
+            None => {
+                for i in 0..self.trace_evaluations.len() {
+                    hash.digest(as_bytes(&self.trace_evaluations[i]), &mut hashed_states[i]);
+                }
+            },
+            Some(blinding) => {
+                // in zero-knowledge mode, value of the randomizer is appended to each trace
+                // state, and the result is hashed together with a salt
+                for i in 0..self.trace_evaluations.len() {
+                    let mut trace_state = self.trace_evaluations[i].clone();
+                    trace_state.push(blinding.randomizer_values[i]);
+                    utils::hash_salted(hash, as_bytes(&trace_state), &blinding.trace_salts[i], &mut hashed_states[i]);
+                }
+            }
+        }
+
+        return BatchMerkleProof {
+            nodes   : self.trace_nodes.clone(),
+            values  : hashed_states,
+            depth   : self.domain_depth,
+         };
+    }
+
+    pub fn constraint_cap(&self) -> &[[u8; 32]] {
+        return &self.constraint_cap;
+    }
+
+    pub fn constraint_proof(&self) -> BatchMerkleProof {
+        let mut proof = self.constraint_proof.clone();
+        // in zero-knowledge mode, the proof contains constraint evaluations, but the leaves of
+        // constraint tree are hashes of these evaluations together with salts
+        if let Some(blinding) = &self.blinding {
+            let hash = self.options.hash_function();
+            for (leaf, salt) in proof.values.iter_mut().zip(blinding.constraint_salts.iter()) {
+                let evaluations = *leaf;
+                utils::hash_salted(hash, &evaluations, salt, leaf);
+            }
+        }
+        return proof;
+    }
+
+    /// Returns constraint evaluations at the queried positions packed into 32-byte chunks.
+    pub fn constraint_evaluations(&self) -> &[[u8; 32]] {
+        return &self.constraint_proof.values;
+    }
+
+    pub fn degree_proof(&self) -> &FriProof<T> {
+        return &self.degree_proof;
+    }
+
+    pub fn trace_evaluations(&self) -> &[Vec<T>] {
+        return &self.trace_evaluations;
+    }
+
+    pub fn trace_length(&self) -> usize {
+        return match &self.blinding {
+            None => self.domain_size() / self.options.extension_factor(),
+            Some(blinding) => 1 << (blinding.trace_depth as usize)
+        };
+    }
+
+    pub fn stack_depth(&self) -> usize {
+        return TraceState::<T>::compute_stack_depth(self.trace_evaluations[0].len());
+    }
+
+    pub fn deep_values(&self) -> &DeepValues<T> {
+        return &self.deep_values;
+    }
+
+    pub fn pow_nonce(&self) -> u64 {
+        return self.pow_nonce;
+    }
+
+    pub fn blinding(&self) -> Option<&BlindingValues<T>> {
+        return self.blinding.as_ref();
+    }
+
+    // DEEP VALUES
+    // -------------------------------------------------------------------------------------------
+    pub fn get_state_at_z1(&self) -> TraceState<T> {
+        return TraceState::from_raw_state(self.deep_values.trace_at_z1.clone());
+    }
+
+    pub fn get_state_at_z2(&self) -> TraceState<T> {
+        return TraceState::from_raw_state(self.deep_values.trace_at_z2.clone());
+    }
+}
+
+// DE-SERIALIZATION
+// ================================================================================================
+impl <T> TryFrom<RawStarkProof<T>> for StarkProof<T>
+    where T: FiniteField + Accumulator
+{
+    type Error = String;
+
+    fn try_from(raw: RawStarkProof<T>) -> Result<StarkProof<T>, String> {
+
+        let options = &raw.options;
+        let num_queries = options.num_queries();
+
+        // domain must be large enough to hold a trace of minimum length, and small enough
+        // for roots of unity of the domain size to exist in the field
+        let min_domain_depth = (MIN_TRACE_LENGTH * options.extension_factor()).trailing_zeros() as u8;
+        if raw.domain_depth < min_domain_depth || raw.domain_depth as usize > MAX_DOMAIN_DEPTH {
+            return Err(format!("domain depth {} is not within the valid range [{}, {}]",
+                raw.domain_depth, min_domain_depth, MAX_DOMAIN_DEPTH));
+        }
+
+        // trace evaluations must have the same number of registers, and this number must be
+        // valid for an execution trace
+        if raw.trace_evaluations.len() == 0 || raw.trace_evaluations.len() > num_queries {
+            return Err(format!("number of trace evaluations {} is not within the valid range [1, {}]",
+                raw.trace_evaluations.len(), num_queries));
+        }
+
+        let register_count = raw.trace_evaluations[0].len();
+        // stack trace always contains at least the aux register
+        let min_register_count = DECODER_WIDTH + 1;
+        let max_register_count = DECODER_WIDTH + MAX_STACK_DEPTH;
+        if register_count < min_register_count || register_count > max_register_count {
+            return Err(format!("number of trace registers {} is not within the valid range [{}, {}]",
+                register_count, min_register_count, max_register_count));
+        }
+
+        for evaluations in raw.trace_evaluations.iter() {
+            if evaluations.len() != register_count {
+                return Err(String::from("trace evaluations have inconsistent number of registers"));
+            }
+            validate_elements(evaluations, "trace evaluation row")?;
+        }
+        validate_merkle_proof(&raw.trace_cap, &raw.trace_nodes, raw.trace_evaluations.len(), raw.domain_depth, options, "trace")?;
+
+        // constraint evaluations are packed into 32-byte leaves, so constraint tree has fewer
+        // levels than the trace tree
+        let element_size = mem::size_of::<T>();
+        let elements_per_leaf = 32 / element_size;
+        let constraint_depth = raw.domain_depth - elements_per_leaf.trailing_zeros() as u8;
+        let constraint_proof = &raw.constraint_proof;
+        if constraint_proof.depth != constraint_depth {
+            return Err(format!("constraint proof depth {} is inconsistent with domain depth {}",
+                constraint_proof.depth, raw.domain_depth));
+        }
+        if constraint_proof.values.len() == 0 || constraint_proof.values.len() > num_queries {
+            return Err(format!("number of constraint evaluations {} is not within the valid range [1, {}]",
+                constraint_proof.values.len(), num_queries));
+        }
+        for leaf in constraint_proof.values.iter() {
+            for element_bytes in leaf.chunks(element_size) {
+                if T::from_bytes(element_bytes) >= T::MODULUS {
+                    return Err(String::from("constraint evaluations contain invalid field elements"));
+                }
+            }
+        }
+        validate_merkle_proof(&raw.constraint_cap, &constraint_proof.nodes, constraint_proof.values.len(), constraint_depth, options, "constraint")?;
+
+        // deep values must contain values for all trace registers
+        let deep_values = &raw.deep_values;
+        if deep_values.trace_at_z1.len() != register_count || deep_values.trace_at_z2.len() != register_count {
+            return Err(format!("deep values must contain exactly {} trace registers", register_count));
+        }
+        validate_elements(&deep_values.trace_at_z1, "list of deep values")?;
+        validate_elements(&deep_values.trace_at_z2, "list of deep values")?;
+
+        // each FRI layer reduces the domain by the folding factor until it is small enough
+        let degree_proof = &raw.degree_proof;
+        let folding_factor = options.folding_factor();
+        let folding_depth = folding_factor.trailing_zeros() as u8;
+        let num_layers = options.num_fri_layers(1 << raw.domain_depth);
+        if degree_proof.layers.len() != num_layers {
+            return Err(format!("expected {} FRI layers, but received {}", num_layers, degree_proof.layers.len()));
+        }
+
+        for (i, layer) in degree_proof.layers.iter().enumerate() {
+            let layer_depth = raw.domain_depth - folding_depth * (i as u8 + 1);
+            if layer.depth != layer_depth {
+                return Err(format!("FRI layer {} depth {} is inconsistent with domain depth {}",
+                    i, layer.depth, raw.domain_depth));
+            }
+            if layer.values.len() == 0 || layer.values.len() > num_queries {
+                return Err(format!("number of values {} in FRI layer {} is not within the valid range [1, {}]",
+                    layer.values.len(), i, num_queries));
+            }
+            for row in layer.values.iter() {
+                if row.len() != folding_factor {
+                    return Err(format!("rows of FRI layer {} must contain exactly {} values", i, folding_factor));
+                }
+                validate_elements(row, "FRI layer row")?;
+            }
+            validate_merkle_proof(&layer.cap, &layer.nodes, layer.values.len(), layer_depth, options, "FRI layer")?;
+        }
+
+        // proofs generated in zero-knowledge mode must contain salts for all opened leaves and
+        // randomizer values for all queried positions; other proofs must not contain these
+        match &raw.blinding {
+            None => if options.zero_knowledge() {
+                return Err(String::from("zero-knowledge proof does not contain blinding values"));
+            },
+            Some(blinding) => {
+                if !options.zero_knowledge() {
+                    return Err(String::from("proof contains blinding values, but zero-knowledge mode is not enabled"));
+                }
+                let min_trace_depth = MIN_TRACE_LENGTH.trailing_zeros() as u8;
+                if blinding.trace_depth < min_trace_depth || blinding.trace_depth >= raw.domain_depth {
+                    return Err(format!("trace depth {} is not within the valid range [{}, {}]",
+                        blinding.trace_depth, min_trace_depth, raw.domain_depth - 1));
+                }
+                let domain_size = options.domain_size(1 << blinding.trace_depth);
+                if domain_size != 1usize << raw.domain_depth {
+                    return Err(format!("domain depth {} is inconsistent with trace depth {}",
+                        raw.domain_depth, blinding.trace_depth));
+                }
+                if blinding.trace_salts.len() != raw.trace_evaluations.len()
+                    || blinding.randomizer_values.len() != raw.trace_evaluations.len() {
+                    return Err(String::from("number of blinding values is inconsistent with the number of trace evaluations"));
+                }
+                if blinding.constraint_salts.len() != constraint_proof.values.len() {
+                    return Err(String::from("number of constraint salts is inconsistent with the number of constraint evaluations"));
+                }
+                validate_elements(&blinding.randomizer_values, "list of randomizer values")?;
+                validate_elements(&[blinding.randomizer_at_z], "list of randomizer values")?;
+            }
+        }
+
+        // the remainder must have exactly as many coefficients as the degree of the composition
+        // polynomial allows; the degree depends on the trace length, which is known only after
+        // blinding values have been validated
+        let trace_length = match &raw.blinding {
+            None => (1usize << raw.domain_depth) / options.extension_factor(),
+            Some(blinding) => 1 << (blinding.trace_depth as usize)
+        };
+        let composition_degree = utils::get_composition_degree(trace_length, options.randomized_trace_length(trace_length));
+        let remainder_length = fri::get_remainder_length(composition_degree, 1 << raw.domain_depth, options);
+        if degree_proof.remainder.len() != remainder_length {
+            return Err(format!("expected {} FRI remainder coefficients, but received {}",
+                remainder_length, degree_proof.remainder.len()));
+        }
+        validate_elements(&degree_proof.remainder, "FRI remainder")?;
+
+        return Ok(StarkProof {
+            trace_cap           : raw.trace_cap,
+            domain_depth        : raw.domain_depth,
+            trace_nodes         : raw.trace_nodes,
+            trace_evaluations   : raw.trace_evaluations,
+            constraint_cap      : raw.constraint_cap,
+            constraint_proof    : raw.constraint_proof,
+            deep_values         : raw.deep_values,
+            degree_proof        : raw.degree_proof,
+            pow_nonce           : raw.pow_nonce,
+            blinding            : raw.blinding,
+            options             : raw.options
+        });
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn validate_elements<T: FiniteField>(elements: &[T], name: &str) -> Result<(), String> {
+    if elements.iter().any(|&e| e >= T::MODULUS) {
+        return Err(format!("{} contains invalid field elements", name));
+    }
+    return Ok(());
+}
+
+/// Makes sure the Merkle cap of a tree has as many nodes as the cap height in `options` implies,
+/// and that a batch Merkle proof for `num_values` leaves has no more node paths than there are
+/// leaves, and that no path is longer than the depth of the tree.
+fn validate_merkle_proof(cap: &[[u8; 32]], nodes: &[Vec<[u8; 32]>], num_values: usize, depth: u8, options: &ProofOptions, name: &str) -> Result<(), String> {
+    let cap_size = MerkleTree::cap_size(depth, options.merkle_cap_height());
+    if cap.len() != cap_size {
+        return Err(format!("{} Merkle cap must contain exactly {} nodes", name, cap_size));
+    }
+    if nodes.len() > num_values {
+        return Err(format!("{} Merkle proof contains more paths than leaves", name));
+    }
+    if nodes.iter().any(|path| path.len() > depth as usize) {
+        return Err(format!("{} Merkle proof contains paths longer than tree depth {}", name, depth));
+    }
+    return Ok(());
+}
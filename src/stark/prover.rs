@@ -3,26 +3,68 @@ use log::debug;
 use crate::math::{ FiniteField, polynom, fft };
 use crate::crypto::{ MerkleTree };
 
-use super::trace::{ TraceTable, TraceState };
-use super::constraints::{ ConstraintTable, ConstraintPoly, MAX_CONSTRAINT_DEGREE };
-use super::{ ProofOptions, StarkProof, Accumulator, Hasher, CompositionCoefficients, DeepValues, fri, utils };
+use super::trace::{ TraceTable };
+use super::constraints::{ ConstraintTable, ConstraintPoly };
+use super::{ ProofOptions, StarkProof, SegmentState, SegmentProof, Transcript, Accumulator, Hasher, CompositionCoefficients, DeepValues, BlindingValues, Prng, fri, utils };
+use crate::utils::{ uninit_vector, as_bytes };
 
 // PROVER FUNCTION
 // ================================================================================================
 
 pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options: &ProofOptions) -> StarkProof<T>
     where T: FiniteField + Accumulator + Hasher
+{
+    // initialize the transcript with public data of the proof; all pseudo-random challenges
+    // are drawn from the transcript after the prover commits to the data they depend on
+    let mut program_hash = [0u8; 32];
+    program_hash.copy_from_slice(as_bytes(&trace.get_program_hash()));
+    let tape_hash = trace.get_public_tape_hash();
+    let transcript = Transcript::new(&program_hash, inputs, &tape_hash, outputs, trace.unextended_length(), options);
+
+    // boundary constraints bind the first step of the trace to the inputs, and the last step
+    // of the trace to the program hash, hash of the public tape, and the outputs
+    let init_state = SegmentState::from_inputs(inputs);
+    let last_state = SegmentState::from_outputs(&trace.get_program_hash(), &tape_hash, outputs);
+    return prove_with(trace, transcript, &init_state, &last_state, options);
+}
+
+/// Generates a proof of execution of a single segment of a program. The `trace` of the segment
+/// must start in `init_state`; the final state of the segment is read from the last step of the
+/// trace, and is returned together with the proof.
+pub fn prove_segment<T>(trace: &mut TraceTable<T>, init_state: &SegmentState<T>, options: &ProofOptions) -> SegmentProof<T>
+    where T: FiniteField + Accumulator + Hasher
+{
+    let last_state = trace.get_segment_state(trace.unextended_length() - 1);
+    let transcript = Transcript::for_segment(init_state, &last_state, trace.unextended_length(), options);
+    let proof = prove_with(trace, transcript, init_state, &last_state, options);
+    return SegmentProof::new(last_state, proof);
+}
+
+/// Generates a proof for the `trace` using the `transcript` initialized with public data of the
+/// proof; boundary constraints bind the first and the last steps of the trace to the specified
+/// states.
+fn prove_with<T>(trace: &mut TraceTable<T>, mut transcript: Transcript, init_state: &SegmentState<T>, last_state: &SegmentState<T>, options: &ProofOptions) -> StarkProof<T>
+    where T: FiniteField + Accumulator + Hasher
 {
     // 1 ----- extend execution trace -------------------------------------------------------------
     let now = Instant::now();
 
-    // build LDE domain and LDE twiddles (for FFT evaluation over LDE domain)
+    // build LDE domain and LDE twiddles (for FFT evaluation over LDE domain); LDE domain is
+    // a coset of the subgroup generated by the LDE root of unity, offset by the field generator
     let lde_root = T::get_root_of_unity(trace.domain_size());
-    let lde_domain = T::get_power_series(lde_root, trace.domain_size());
-    let lde_twiddles = twiddles_from_domain(&lde_domain);
-
-    // extend the execution trace registers to LDE domain
-    trace.extend(&lde_twiddles);
+    let lde_domain = T::get_power_series_with_offset(lde_root, T::GENERATOR, trace.domain_size());
+    let lde_twiddles = fft::get_twiddles(lde_root, trace.domain_size());
+
+    // extend the execution trace registers to LDE domain; in zero-knowledge mode, the trace
+    // is also blinded. In low-memory mode, only trace polynomials are kept, and the extended
+    // trace is computed chunk by chunk whenever it is needed
+    let randomized_length = options.randomized_trace_length(trace.unextended_length());
+    if options.low_memory() {
+        trace.extend_lazily(&lde_twiddles, randomized_length, options.num_threads());
+    }
+    else {
+        trace.extend(&lde_twiddles, randomized_length, options.num_threads());
+    }
     debug!("Extended execution trace from {} to {} steps in {} ms",
         trace.unextended_length(),
         trace.domain_size(), 
@@ -30,36 +72,23 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
 
     // 2 ----- build Merkle tree from the extended execution trace ------------------------------------
     let now = Instant::now();
-    let trace_tree = trace.build_merkle_tree(options.hash_function());
+    let trace_tree = trace.build_merkle_tree(options.hash_function(), options.num_threads());
     debug!("Built trace Merkle tree in {} ms", 
         now.elapsed().as_millis());
 
     // 3 ----- evaluate constraints ---------------------------------------------------------------
     let now = Instant::now();
     
-    // initialize constraint evaluation table
-    let mut constraints = ConstraintTable::new(&trace, trace_tree.root(), inputs, outputs);
-    
-    // allocate space to hold current and next states for constraint evaluations
-    let mut current = TraceState::new(trace.max_stack_depth());
-    let mut next = TraceState::new(trace.max_stack_depth());
-
-    // we don't need to evaluate constraints over the entire extended execution trace; we need
-    // to evaluate them over the domain extended to match max constraint degree - thus, we can
-    // skip most trace states for the purposes of constraint evaluation.
-    let stride = trace.extension_factor() / MAX_CONSTRAINT_DEGREE;
-    for i in (0..trace.domain_size()).step_by(stride) {
-        // TODO: this loop should be parallelized and also potentially optimized to avoid copying
-        // next state from the trace table twice
-
-        // copy current and next states from the trace table; next state may wrap around the
-        // execution trace (close to the end of the trace)
-        trace.fill_state(&mut current, i);
-        trace.fill_state(&mut next, (i + trace.extension_factor()) % trace.domain_size());
-
-        // evaluate the constraints
-        constraints.evaluate(&current, &next, lde_domain[i], i / stride);
-    }
+    // initialize constraint evaluation table; coefficients for constraint combination are
+    // derived from the transcript after the commitment to the trace (the cap of the trace
+    // Merkle tree) is absorbed into it
+    let cap_height = options.merkle_cap_height();
+    transcript.absorb(as_bytes(trace_tree.cap(cap_height)));
+    let mut constraints = ConstraintTable::new(&trace, &transcript.draw(), options.hash_function(), init_state, last_state);
+
+    // evaluate the constraints over the constraint evaluation domain; the evaluation is
+    // distributed across multiple threads
+    constraints.evaluate(&trace, &lde_domain, options.num_threads());
 
     debug!("Evaluated {} constraints over domain of {} elements in {} ms",
         constraints.constraint_count(),
@@ -79,9 +108,22 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
     // evaluate constraint polynomial over the evaluation domain
     let constraint_evaluations = constraint_poly.eval(&lde_twiddles);
 
-    // put evaluations into a Merkle tree; 4 evaluations per leaf
+    // put evaluations into a Merkle tree; 4 evaluations per leaf. In zero-knowledge mode,
+    // each leaf is hashed together with a salt before it is put into the tree, and unsalted
+    // leaves are kept separately; otherwise, the tree takes ownership of the leaves
     let constraint_evaluations = evaluations_to_leaves(constraint_evaluations);
-    let constraint_tree = MerkleTree::new(constraint_evaluations, options.hash_function());
+    let constraint_salt_seed: Option<[u8; 32]> = if options.zero_knowledge() { Some(rand::random()) } else { None };
+    let (constraint_tree, unsalted_leaves) = match constraint_salt_seed {
+        None => (MerkleTree::new_concurrent(constraint_evaluations, options.hash_function(), options.num_threads()), None),
+        Some(salt_seed) => {
+            let mut salted_leaves = uninit_vector::<[u8; 32]>(constraint_evaluations.len());
+            for (i, leaf) in constraint_evaluations.iter().enumerate() {
+                let salt = utils::get_salt(&salt_seed, i);
+                utils::hash_salted(options.hash_function(), leaf, &salt, &mut salted_leaves[i]);
+            }
+            (MerkleTree::new_concurrent(salted_leaves, options.hash_function(), options.num_threads()), Some(constraint_evaluations))
+        }
+    };
     debug!("Evaluated constraint polynomial and built constraint Merkle tree in {} ms",
         now.elapsed().as_millis());
 
@@ -89,14 +131,22 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
     let now = Instant::now();
 
     // combine trace and constraint polynomials into the final deep composition polynomial
-    let seed = constraint_tree.root();
-    let (composition_poly, deep_values) = build_composition_poly(&trace, constraint_poly, seed);
+    transcript.absorb(as_bytes(constraint_tree.cap(cap_height)));
+    let seed = transcript.draw();
+    let (composition_poly, deep_values, randomizer_at_z) = build_composition_poly(&trace, constraint_poly, &seed, options);
+
+    // absorb trace states at the DEEP points into the transcript
+    transcript.absorb(as_bytes(&deep_values.trace_at_z1));
+    transcript.absorb(as_bytes(&deep_values.trace_at_z2));
+    if let Some(randomizer_at_z) = randomizer_at_z {
+        transcript.absorb(as_bytes(&[randomizer_at_z]));
+    }
 
     // evaluate the composition polynomial over LDE domain
     let mut composed_evaluations = composition_poly;
     debug_assert!(composed_evaluations.capacity() == lde_domain.len(), "invalid composition polynomial capacity");
     unsafe { composed_evaluations.set_len(composed_evaluations.capacity()); }
-    polynom::eval_fft_twiddles(&mut composed_evaluations, &lde_twiddles, true);
+    polynom::eval_fft_coset(&mut composed_evaluations, &lde_twiddles, T::GENERATOR, true);
 
     debug!("Built composition polynomial and evaluated it over domain of {} elements in {} ms",
         composed_evaluations.len(),
@@ -104,9 +154,13 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
 
     // 7 ----- compute FRI layers for the composition polynomial ----------------------------------
     let now = Instant::now();
-    let composition_degree = utils::get_composition_degree(trace.unextended_length());
+    let composition_degree = utils::get_composition_degree(trace.unextended_length(), randomized_length);
     debug_assert!(composition_degree == polynom::infer_degree(&composed_evaluations));
-    let (fri_trees, fri_values) = fri::reduce(&composed_evaluations, &lde_domain, options);
+    let (fri_trees, fri_values, fri_remainder) = fri::reduce(&composed_evaluations, &lde_domain, composition_degree, &mut transcript, options);
+
+    // composition polynomial evaluations are no longer needed: the first FRI layer contains
+    // the same values
+    drop(composed_evaluations);
     debug!("Computed {} FRI layers from composition polynomial evaluations in {} ms",
     fri_trees.len(),
         now.elapsed().as_millis());
@@ -114,15 +168,9 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
     // 8 ----- determine query positions -----------------------------------------------------------
     let now = Instant::now();
 
-    // combine all FRI layer roots into a single vector
-    let mut fri_roots: Vec<u8> = Vec::new();
-    for tree in fri_trees.iter() {
-        tree.root().iter().for_each(|&v| fri_roots.push(v));
-    }
-
-    // derive a seed from the combined roots
-    let mut seed = [0u8; 32];
-    options.hash_function()(&fri_roots, &mut seed);
+    // derive a seed from the transcript; by now, roots of all FRI layers and the remainder have
+    // been absorbed into the transcript
+    let seed = transcript.draw();
 
     // apply proof-of-work to get a new seed
     let (seed, pow_nonce) = utils::find_pow_nonce(seed, &options);
@@ -138,24 +186,43 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
     let now = Instant::now();
 
     // generate FRI proof
-    let fri_proof = fri::build_proof(fri_trees, fri_values, &positions);
+    let fri_proof = fri::build_proof(fri_trees, fri_values, fri_remainder, &positions, options);
 
     // built a list of trace evaluations at queried positions
-    let trace_evaluations = trace.get_register_values_at(&positions);
+    let trace_evaluations = trace.get_register_values_at(&positions, options.num_threads());
 
     // build a list of constraint positions
     let constraint_positions = utils::map_trace_to_constraint_positions::<T>(&positions);
 
+    // in zero-knowledge mode, the proof must also contain salts of all opened leaves; since
+    // constraint leaves are salted, the proof needs to carry the leaves themselves
+    let mut constraint_proof = constraint_tree.prove_batch(&constraint_positions, cap_height);
+    let blinding = match (randomizer_at_z, constraint_salt_seed, unsalted_leaves) {
+        (Some(randomizer_at_z), Some(salt_seed), Some(leaves)) => {
+            let (randomizer_values, trace_salts) = trace.get_blinding_values_at(&positions);
+            constraint_proof.values = constraint_positions.iter().map(|&i| leaves[i]).collect();
+            Some(BlindingValues {
+                trace_depth         : trace.unextended_length().trailing_zeros() as u8,
+                trace_salts         : trace_salts,
+                randomizer_values   : randomizer_values,
+                randomizer_at_z     : randomizer_at_z,
+                constraint_salts    : constraint_positions.iter().map(|&i| utils::get_salt(&salt_seed, i)).collect(),
+            })
+        },
+        _ => None
+    };
+
     // build the proof object
     let proof = StarkProof::new(
-        trace_tree.root(),
-        trace_tree.prove_batch(&positions),
+        trace_tree.cap(cap_height),
+        trace_tree.prove_batch(&positions, cap_height),
         trace_evaluations,
-        constraint_tree.root(),
-        constraint_tree.prove_batch(&constraint_positions),
+        constraint_tree.cap(cap_height),
+        constraint_proof,
         deep_values,
         fri_proof,
         pow_nonce,
+        blinding,
         &options);
 
     debug!("Built proof object in {} ms", now.elapsed().as_millis());
@@ -164,12 +231,6 @@ pub fn prove<T>(trace: &mut TraceTable<T>, inputs: &[T], outputs: &[T], options:
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn twiddles_from_domain<T: FiniteField>(domain: &[T]) -> Vec<T> {
-    let mut twiddles = domain[..(domain.len() / 2)].to_vec();
-    fft::permute(&mut twiddles);
-    return twiddles;
-}
-
 fn evaluations_to_leaves<T: FiniteField>(evaluations: Vec<T>) -> Vec<[u8; 32]> {
     let element_size = mem::size_of::<T>();
     let elements_per_leaf = 32 / element_size;
@@ -183,12 +244,13 @@ fn evaluations_to_leaves<T: FiniteField>(evaluations: Vec<T>) -> Vec<[u8; 32]> {
     return unsafe { Vec::from_raw_parts(p as *mut [u8; 32], len, cap) };
 }
 
-fn build_composition_poly<T>(trace: &TraceTable<T>, constraint_poly: ConstraintPoly<T>, seed: &[u8; 32]) -> (Vec<T>, DeepValues<T>)
+fn build_composition_poly<T>(trace: &TraceTable<T>, constraint_poly: ConstraintPoly<T>, seed: &[u8; 32], options: &ProofOptions) -> (Vec<T>, DeepValues<T>, Option<T>)
     where T: FiniteField + Accumulator + Hasher
 {
-    // pseudo-randomly selection deep point z and coefficients for the composition
-    let z = T::prng(*seed);
-    let coefficients = CompositionCoefficients::new(*seed);
+    // pseudo-randomly selection deep point z and coefficients for the composition; z is the
+    // first element drawn from the seed, and the coefficients skip it
+    let z = Prng::new(*seed, options.hash_function()).draw_element();
+    let coefficients = CompositionCoefficients::new(*seed, options.hash_function());
 
     // divide out deep point from trace polynomials and merge them into a single polynomial
     let (mut result, s1, s2) = trace.get_composition_poly(z, &coefficients);
@@ -196,5 +258,14 @@ fn build_composition_poly<T>(trace: &TraceTable<T>, constraint_poly: ConstraintP
     // divide out deep point from constraint polynomial and merge it into the result
     constraint_poly.merge_into(&mut result, z, &coefficients);
 
-    return (result, DeepValues { trace_at_z1: s1, trace_at_z2: s2 });
+    // if the trace is blinded, divide out deep point from the randomizer and merge it into
+    // the result as well
+    let randomizer_at_z = if trace.is_blinded() {
+        Some(trace.merge_randomizer_into(&mut result, z, &coefficients))
+    }
+    else {
+        None
+    };
+
+    return (result, DeepValues { trace_at_z1: s1, trace_at_z2: s2 }, randomizer_at_z);
 }
\ No newline at end of file
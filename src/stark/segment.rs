@@ -0,0 +1,132 @@
+use std::convert::TryFrom;
+use serde::{ Serialize, Deserialize };
+use crate::math::{ FiniteField };
+use super::{ StarkProof, Accumulator, ACC_STATE_WIDTH, ACC_STATE_RATE, MAX_STACK_DEPTH };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Public state of the VM at a boundary between two execution segments. The state consists of
+/// the full states of program hash and public tape accumulators, and values of all user stack
+/// registers. Boundary constraints of a segment proof bind the first step of the segment to its
+/// initial state, and the last step of the segment to its final state; the final state of one
+/// segment is the initial state of the next segment.
+///
+/// Since segment states are public, values on the stack at segment boundaries are revealed to
+/// the verifier even if segment proofs are generated in zero-knowledge mode.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawSegmentState<T>")]
+pub struct SegmentState<T: FiniteField> {
+    op_acc      : Vec<T>,
+    tape_acc    : Vec<T>,
+    stack       : Vec<T>,
+}
+
+/// Segment state as read from serialized data; this is converted into a `SegmentState` only if
+/// the state has a valid shape and all field elements are valid.
+#[derive(Deserialize)]
+struct RawSegmentState<T: FiniteField> {
+    op_acc      : Vec<T>,
+    tape_acc    : Vec<T>,
+    stack       : Vec<T>,
+}
+
+/// Proof of execution of a single segment of a program together with the state of the VM
+/// after the last step of the segment.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SegmentProof<T: FiniteField + Accumulator> {
+    state       : SegmentState<T>,
+    proof       : StarkProof<T>,
+}
+
+// SEGMENT STATE IMPLEMENTATION
+// ================================================================================================
+impl <T> SegmentState<T>
+    where T: FiniteField
+{
+    pub fn new(op_acc: &[T], tape_acc: &[T], stack: &[T]) -> SegmentState<T> {
+        assert!(op_acc.len() == ACC_STATE_WIDTH,
+            "expected {} program hash accumulator values, but received {}", ACC_STATE_WIDTH, op_acc.len());
+        assert!(tape_acc.len() == ACC_STATE_WIDTH,
+            "expected {} public tape accumulator values, but received {}", ACC_STATE_WIDTH, tape_acc.len());
+        assert!(stack.len() < MAX_STACK_DEPTH,
+            "expected no more than {} stack values, but received {}", MAX_STACK_DEPTH - 1, stack.len());
+        return SegmentState { op_acc: op_acc.to_vec(), tape_acc: tape_acc.to_vec(), stack: stack.to_vec() };
+    }
+
+    /// Returns the state of the VM before the first step of a program: both accumulators are
+    /// set to zeros, and the stack contains the public `inputs`.
+    pub fn from_inputs(inputs: &[T]) -> SegmentState<T> {
+        return SegmentState::new(&[T::ZERO; ACC_STATE_WIDTH], &[T::ZERO; ACC_STATE_WIDTH], inputs);
+    }
+
+    /// Returns the state of the VM after the last step of a program executed as a single
+    /// segment; only the rate part of the program hash accumulator (i.e. the program hash) is
+    /// a part of such state, and the stack contains only the program `outputs`.
+    pub(crate) fn from_outputs(program_hash: &[T], tape_hash: &[T], outputs: &[T]) -> SegmentState<T> {
+        debug_assert!(program_hash.len() == ACC_STATE_RATE, "invalid program hash length");
+        return SegmentState { op_acc: program_hash.to_vec(), tape_acc: tape_hash.to_vec(), stack: outputs.to_vec() };
+    }
+
+    pub fn op_acc(&self) -> &[T] {
+        return &self.op_acc;
+    }
+
+    /// Returns the program hash, i.e. the rate part of the program hash accumulator.
+    pub fn program_hash(&self) -> &[T] {
+        return &self.op_acc[..ACC_STATE_RATE];
+    }
+
+    pub fn tape_acc(&self) -> &[T] {
+        return &self.tape_acc;
+    }
+
+    pub fn stack(&self) -> &[T] {
+        return &self.stack;
+    }
+}
+
+impl <T> TryFrom<RawSegmentState<T>> for SegmentState<T>
+    where T: FiniteField
+{
+    type Error = String;
+
+    fn try_from(raw: RawSegmentState<T>) -> Result<SegmentState<T>, String> {
+        if raw.op_acc.len() != ACC_STATE_WIDTH {
+            return Err(format!("expected {} program hash accumulator values, but received {}",
+                ACC_STATE_WIDTH, raw.op_acc.len()));
+        }
+        if raw.tape_acc.len() != ACC_STATE_WIDTH {
+            return Err(format!("expected {} public tape accumulator values, but received {}",
+                ACC_STATE_WIDTH, raw.tape_acc.len()));
+        }
+        if raw.stack.len() >= MAX_STACK_DEPTH {
+            return Err(format!("expected no more than {} stack values, but received {}",
+                MAX_STACK_DEPTH - 1, raw.stack.len()));
+        }
+        let elements = [&raw.op_acc[..], &raw.tape_acc[..], &raw.stack[..]].concat();
+        if elements.iter().any(|&e| e >= T::MODULUS) {
+            return Err(String::from("segment state contains invalid field elements"));
+        }
+        return Ok(SegmentState { op_acc: raw.op_acc, tape_acc: raw.tape_acc, stack: raw.stack });
+    }
+}
+
+// SEGMENT PROOF IMPLEMENTATION
+// ================================================================================================
+impl <T> SegmentProof<T>
+    where T: FiniteField + Accumulator
+{
+    pub fn new(state: SegmentState<T>, proof: StarkProof<T>) -> SegmentProof<T> {
+        return SegmentProof { state, proof };
+    }
+
+    /// Returns the state of the VM after the last step of the segment.
+    pub fn state(&self) -> &SegmentState<T> {
+        return &self.state;
+    }
+
+    pub fn proof(&self) -> &StarkProof<T> {
+        return &self.proof;
+    }
+}
@@ -6,8 +6,9 @@ use crate::utils::filled_vector;
 // TRACE BUILDER
 // ================================================================================================
 
-/// Builds decoder execution trace
-pub fn process<T>(program: &[T], extension_factor: usize) -> Vec<Vec<T>>
+/// Builds decoder execution trace; program hash and public tape accumulators start in the
+/// specified `op_acc` and `tape_acc` states (these are all zeros for a complete program).
+pub fn process<T>(program: &[T], op_acc: &[T], tape_acc: &[T], public_tape: &[T], extension_factor: usize) -> Vec<Vec<T>>
     where T: FiniteField + Accumulator
 {
     let trace_length = program.len();
@@ -45,12 +46,16 @@ pub fn process<T>(program: &[T], extension_factor: usize) -> Vec<Vec<T>>
     }
 
     // create op_acc register traces
-    let op_acc = hash_program(&op_code, domain_size);
+    let op_acc = hash_program(&op_code, op_acc, domain_size);
+
+    // create tape_acc register traces
+    let tape_acc = hash_public_tape(&op_code, tape_acc, public_tape, domain_size);
 
     // move all registers into a single vector
     let mut registers = vec![op_code];
     for register in op_bits.into_iter() { registers.push(register); }
     for register in op_acc.into_iter() { registers.push(register); }
+    for register in tape_acc.into_iter() { registers.push(register); }
 
     assert!(registers.len() == DECODER_WIDTH,
         "invalid decoder width: expected: {}, actual: {}", DECODER_WIDTH, registers.len());
@@ -62,7 +67,7 @@ pub fn process<T>(program: &[T], extension_factor: usize) -> Vec<Vec<T>>
 // ================================================================================================
 
 /// Uses a modified version of Rescue hash function to reduce all op_codes into a single hash value
-fn hash_program<T>(op_codes: &[T], domain_size: usize) -> Vec<Vec<T>>
+fn hash_program<T>(op_codes: &[T], init_state: &[T], domain_size: usize) -> Vec<Vec<T>>
     where T: FiniteField + Accumulator
 {
     let trace_length = op_codes.len();
@@ -73,7 +78,11 @@ fn hash_program<T>(op_codes: &[T], domain_size: usize) -> Vec<Vec<T>>
         registers.push(filled_vector(trace_length, domain_size, T::ZERO));
     }
 
-    let mut state = vec![T::ZERO; ACC_STATE_WIDTH];
+    let mut state = init_state.to_vec();
+    for j in 0..ACC_STATE_WIDTH {
+        registers[j][0] = state[j];
+    }
+
     for i in 0..(op_codes.len() - 1) {
 
         // add op_code into the accumulator
@@ -88,6 +97,46 @@ fn hash_program<T>(op_codes: &[T], domain_size: usize) -> Vec<Vec<T>>
     return registers;
 }
 
+/// Absorbs values of the public input tape into a single hash value; a value is absorbed at each
+/// step when READP operation is executed, and at all other steps the state remains unchanged.
+fn hash_public_tape<T>(op_codes: &[T], init_state: &[T], public_tape: &[T], domain_size: usize) -> Vec<Vec<T>>
+    where T: FiniteField + Accumulator
+{
+    let trace_length = op_codes.len();
+
+    // allocate space for the registers
+    let mut registers = Vec::with_capacity(ACC_STATE_WIDTH);
+    for _ in 0..ACC_STATE_WIDTH {
+        registers.push(filled_vector(trace_length, domain_size, T::ZERO));
+    }
+
+    let mut state = init_state.to_vec();
+    for j in 0..ACC_STATE_WIDTH {
+        registers[j][0] = state[j];
+    }
+
+    let mut tape_idx = 0;
+    let mut is_push_value = false;
+    for i in 0..(op_codes.len() - 1) {
+
+        // values pushed onto the stack by PUSH operation are not operations, and thus,
+        // should not be interpreted as READP
+        if !is_push_value && op_codes[i] == T::from(opcodes::READP) {
+            assert!(tape_idx < public_tape.len(), "ran out of public tape inputs at step {}", i);
+            T::absorb(&mut state, public_tape[tape_idx]);
+            tape_idx += 1;
+        }
+        is_push_value = !is_push_value && op_codes[i] == T::from(opcodes::PUSH);
+
+        // copy updated state into registers for the next step
+        for j in 0..ACC_STATE_WIDTH {
+            registers[j][i + 1] = state[j];
+        }
+    }
+
+    return registers;
+}
+
 /// Sets the op_bits registers at the specified `step` to the binary decomposition
 /// of the `op_code` parameter.
 fn set_op_bits<T: FiniteField>(op_bits: &mut Vec<Vec<T>>, op_code: u8, step: usize) {
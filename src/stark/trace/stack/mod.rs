@@ -18,7 +18,12 @@ const MAX_USER_STACK_DEPTH: usize = MAX_STACK_DEPTH - 1;
 
 // TRACE BUILDER
 // ================================================================================================
-pub fn execute<T>(program: &[T], inputs: &ProgramInputs<T>, extension_factor: usize) -> Vec<Vec<T>>
+/// Executes the program starting with the stack populated with `init_stack` values, out of which
+/// the top `init_depth` values are considered to be on the stack; public inputs of `inputs` are
+/// ignored. Returns stack registers together with the depth of the stack after the last step,
+/// or an error if an operation cannot be executed (e.g. because of stack underflow or because
+/// the inputs have run out) or if not all inputs have been consumed by the program.
+pub fn execute<T>(program: &[T], init_stack: &[T], init_depth: usize, inputs: &ProgramInputs<T>, extension_factor: usize) -> Result<(Vec<Vec<T>>, usize), String>
     where T: FiniteField + Hasher
 {
     let trace_length = program.len();
@@ -30,21 +35,25 @@ pub fn execute<T>(program: &[T], inputs: &ProgramInputs<T>, extension_factor: us
     assert!(program[program.len() - 1] == T::from(opcodes::NOOP), "last operation of a program must be NOOP");
     assert!(extension_factor.is_power_of_two(), "trace extension factor must be a power of 2");
 
-    // allocate space for stack registers and populate the first state with public inputs
-    let public_inputs = inputs.get_public_inputs();
-    let init_stack_depth = cmp::max(public_inputs.len(), MIN_USER_STACK_DEPTH);
+    assert!(init_stack.len() <= MAX_USER_STACK_DEPTH, "stack cannot hold more than {} values", MAX_USER_STACK_DEPTH);
+    assert!(init_depth <= init_stack.len(), "initial stack depth cannot exceed the number of initial stack values");
+
+    // allocate space for stack registers and populate the first state with initial stack values
+    let init_stack_depth = cmp::max(init_stack.len(), MIN_USER_STACK_DEPTH);
     let mut user_registers: Vec<Vec<T>> = Vec::with_capacity(init_stack_depth);
     for i in 0..init_stack_depth {
         let mut register = filled_vector(trace_length, domain_size, T::ZERO);
-        if i < public_inputs.len() { 
-            register[0] = public_inputs[i];
+        if i < init_stack.len() { 
+            register[0] = init_stack[i];
         }
         user_registers.push(register);
     }
 
     let aux_register = filled_vector(trace_length, domain_size, T::ZERO);
 
-    // reverse secret inputs so that they are consumed in FIFO order
+    // reverse public tape and secret inputs so that they are consumed in FIFO order
+    let mut public_tape = inputs.get_public_tape().to_vec();
+    public_tape.reverse();
     let [secret_inputs_a, secret_inputs_b] = inputs.get_secret_inputs();
     let mut secret_inputs_a = secret_inputs_a.clone();
     secret_inputs_a.reverse();
@@ -54,10 +63,11 @@ pub fn execute<T>(program: &[T], inputs: &ProgramInputs<T>, extension_factor: us
     let mut stack = StackTrace {
         aux_register,
         user_registers,
+        public_tape,
         secret_inputs_a,
         secret_inputs_b,
-        max_depth: public_inputs.len(),
-        depth: public_inputs.len()
+        max_depth: init_stack.len(),
+        depth: init_depth
     };
 
     // execute the program capturing each successive stack state in the trace
@@ -67,59 +77,67 @@ pub fn execute<T>(program: &[T], inputs: &ProgramInputs<T>, extension_factor: us
         // TODO: make sure operation can be safely cast to u8
         match program[i].as_u8() {
 
-            opcodes::BEGIN   => stack.noop(i),
-            opcodes::NOOP    => stack.noop(i),
-            opcodes::ASSERT  => stack.assert(i),
+            opcodes::BEGIN   => stack.noop(i)?,
+            opcodes::NOOP    => stack.noop(i)?,
+            opcodes::ASSERT  => stack.assert(i)?,
 
             opcodes::PUSH  => {
                 // push the value of the next instruction onto the stack and skip a step
                 // since next instruction is not an operation
-                stack.push(i, program[i + 1]);
+                stack.push(i, program[i + 1])?;
                 i += 1;
-                stack.noop(i);
+                stack.noop(i)?;
             },
 
-            opcodes::READ    => stack.read(i),
-            opcodes::READ2   => stack.read2(i),
+            opcodes::READ    => stack.read(i)?,
+            opcodes::READ2   => stack.read2(i)?,
+            opcodes::READP   => stack.readp(i)?,
 
-            opcodes::DUP     => stack.dup(i),
-            opcodes::DUP2    => stack.dup2(i),
-            opcodes::DUP4    => stack.dup4(i),
-            opcodes::PAD2    => stack.pad2(i),
+            opcodes::DUP     => stack.dup(i)?,
+            opcodes::DUP2    => stack.dup2(i)?,
+            opcodes::DUP4    => stack.dup4(i)?,
+            opcodes::PAD2    => stack.pad2(i)?,
 
-            opcodes::DROP    => stack.drop(i),
-            opcodes::DROP4   => stack.drop4(i),
+            opcodes::DROP    => stack.drop(i)?,
+            opcodes::DROP4   => stack.drop4(i)?,
 
-            opcodes::SWAP    => stack.swap(i),
-            opcodes::SWAP2   => stack.swap2(i),
-            opcodes::SWAP4   => stack.swap4(i),
+            opcodes::SWAP    => stack.swap(i)?,
+            opcodes::SWAP2   => stack.swap2(i)?,
+            opcodes::SWAP4   => stack.swap4(i)?,
 
-            opcodes::ROLL4   => stack.roll4(i),
-            opcodes::ROLL8   => stack.roll8(i),
+            opcodes::ROLL4   => stack.roll4(i)?,
+            opcodes::ROLL8   => stack.roll8(i)?,
 
-            opcodes::CHOOSE  => stack.choose(i),
-            opcodes::CHOOSE2 => stack.choose2(i),
+            opcodes::CHOOSE  => stack.choose(i)?,
+            opcodes::CHOOSE2 => stack.choose2(i)?,
 
-            opcodes::ADD     => stack.add(i),
-            opcodes::MUL     => stack.mul(i),
-            opcodes::INV     => stack.inv(i),
-            opcodes::NEG     => stack.neg(i),
-            opcodes::NOT     => stack.not(i),
+            opcodes::ADD     => stack.add(i)?,
+            opcodes::MUL     => stack.mul(i)?,
+            opcodes::INV     => stack.inv(i)?,
+            opcodes::NEG     => stack.neg(i)?,
+            opcodes::NOT     => stack.not(i)?,
+            opcodes::EXP     => stack.exp(i)?,
 
-            opcodes::EQ      => stack.eq(i),
-            opcodes::CMP     => stack.cmp(i),
-            opcodes::BINACC  => stack.binacc(i),
+            opcodes::EQ      => stack.eq(i)?,
+            opcodes::ISZERO  => stack.iszero(i)?,
+            opcodes::CMP     => stack.cmp(i)?,
+            opcodes::BINACC  => stack.binacc(i)?,
 
-            opcodes::HASHR   => stack.hashr(i),
+            opcodes::HASHR   => stack.hashr(i)?,
+            opcodes::MPATH   => stack.mpath(i)?,
 
-            _ => panic!("operation {} is not supported", program[i])
+            _ => return Err(format!("operation {} is not supported", program[i]))
         }
         i += 1;
     }
 
-    // make sure all secret inputs have been consumed
-    assert!(stack.secret_inputs_a.len() == 0 && stack.secret_inputs_b.len() == 0,
-        "not all secret inputs have been consumed");
+    // make sure all public tape and secret inputs have been consumed
+    if !stack.public_tape.is_empty() {
+        return Err(String::from("not all public tape inputs have been consumed"));
+    }
+    if !stack.secret_inputs_a.is_empty() || !stack.secret_inputs_b.is_empty() {
+        return Err(String::from("not all secret inputs have been consumed"));
+    }
 
     // keep only the registers used during program execution
     stack.user_registers.truncate(stack.max_depth);
@@ -127,5 +145,5 @@ pub fn execute<T>(program: &[T], inputs: &ProgramInputs<T>, extension_factor: us
     registers.push(stack.aux_register);
     registers.append(&mut stack.user_registers);
 
-    return registers;
+    return Ok((registers, stack.depth));
 }
\ No newline at end of file
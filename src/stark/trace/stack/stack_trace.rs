@@ -8,6 +8,7 @@ use super::{ MAX_USER_STACK_DEPTH };
 pub struct StackTrace<T: FiniteField + Hasher> {
     pub aux_register    : Vec<T>,
     pub user_registers  : Vec<Vec<T>>,
+    pub public_tape     : Vec<T>,
     pub secret_inputs_a : Vec<T>,
     pub secret_inputs_b : Vec<T>,
     pub max_depth       : usize,
@@ -21,95 +22,117 @@ impl <T> StackTrace<T>
 {
     // OPERATIONS
     // --------------------------------------------------------------------------------------------
-    pub fn noop(&mut self, step: usize) {
+    pub fn noop(&mut self, step: usize) -> Result<(), String> {
         self.copy_state(step, 0);
+        return Ok(());
     }
 
-    pub fn assert(&mut self, step: usize) {
-        assert!(self.depth >= 1, "stack underflow at step {}", step);
+    pub fn assert(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
         let value = self.user_registers[0][step];
-        assert!(value == T::ONE, "ASSERT failed at step {}", step);
+        if value != T::ONE {
+            return Err(format!("ASSERT failed at step {}", step));
+        }
         self.shift_left(step, 1, 1);
+        return Ok(());
     }
 
-    pub fn push(&mut self, step: usize, value: T) {
-        self.shift_right(step, 0, 1);
+    pub fn push(&mut self, step: usize, value: T) -> Result<(), String> {
+        self.shift_right(step, 0, 1)?;
         self.user_registers[0][step + 1] = value;
+        return Ok(());
     }
 
-    pub fn read(&mut self, step: usize) {
-        assert!(self.secret_inputs_a.len() > 0, "ran out of secret inputs at step {}", step);
-        self.shift_right(step, 0, 1);
-        let value = self.secret_inputs_a.pop().unwrap();
+    pub fn read(&mut self, step: usize) -> Result<(), String> {
+        let value = self.read_secret_a(step)?;
+        self.shift_right(step, 0, 1)?;
         self.user_registers[0][step + 1] = value;
+        return Ok(());
     }
 
-    pub fn read2(&mut self, step: usize) {
-        assert!(self.secret_inputs_a.len() > 0, "ran out of secret inputs at step {}", step);
-        assert!(self.secret_inputs_b.len() > 0, "ran out of secret inputs at step {}", step);
-        self.shift_right(step, 0, 2);
-        let value_a = self.secret_inputs_a.pop().unwrap();
-        let value_b = self.secret_inputs_b.pop().unwrap();
+    pub fn read2(&mut self, step: usize) -> Result<(), String> {
+        let value_a = self.read_secret_a(step)?;
+        let value_b = self.read_secret_b(step)?;
+        self.shift_right(step, 0, 2)?;
         self.user_registers[0][step + 1] = value_b;
         self.user_registers[1][step + 1] = value_a;
+        return Ok(());
+    }
+
+    pub fn readp(&mut self, step: usize) -> Result<(), String> {
+        let value = match self.public_tape.pop() {
+            Some(value) => value,
+            None => return Err(format!("ran out of public tape inputs at step {}", step))
+        };
+        self.shift_right(step, 0, 1)?;
+        self.user_registers[0][step + 1] = value;
+        return Ok(());
     }
 
-    pub fn dup(&mut self, step: usize) {
-        assert!(self.depth >= 1, "stack underflow at step {}", step);
-        self.shift_right(step, 0, 1);
+    pub fn dup(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
+        self.shift_right(step, 0, 1)?;
         self.user_registers[0][step + 1] = self.user_registers[0][step];
+        return Ok(());
     }
 
-    pub fn dup2(&mut self, step: usize) {
-        assert!(self.depth >= 2, "stack underflow at step {}", step);
-        self.shift_right(step, 0, 2);
+    pub fn dup2(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 2)?;
+        self.shift_right(step, 0, 2)?;
         self.user_registers[0][step + 1] = self.user_registers[0][step];
         self.user_registers[1][step + 1] = self.user_registers[1][step];
+        return Ok(());
     }
 
-    pub fn dup4(&mut self, step: usize) {
-        assert!(self.depth >= 4, "stack underflow at step {}", step);
-        self.shift_right(step, 0, 4);
+    pub fn dup4(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 4)?;
+        self.shift_right(step, 0, 4)?;
         self.user_registers[0][step + 1] = self.user_registers[0][step];
         self.user_registers[1][step + 1] = self.user_registers[1][step];
         self.user_registers[2][step + 1] = self.user_registers[2][step];
         self.user_registers[3][step + 1] = self.user_registers[3][step];
+        return Ok(());
     }
 
-    pub fn pad2(&mut self, step: usize) {
-        self.shift_right(step, 0, 2);
+    pub fn pad2(&mut self, step: usize) -> Result<(), String> {
+        self.shift_right(step, 0, 2)?;
         self.user_registers[0][step + 1] = T::ZERO;
         self.user_registers[1][step + 1] = T::ZERO;
+        return Ok(());
     }
 
-    pub fn drop(&mut self, step: usize) {
-        assert!(self.depth >= 1, "stack underflow at step {}", step);
+    pub fn drop(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
         self.shift_left(step, 1, 1);
+        return Ok(());
     }
 
-    pub fn drop4(&mut self, step: usize) {
-        assert!(self.depth >= 4, "stack underflow at step {}", step);
+    pub fn drop4(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 4)?;
         self.shift_left(step, 4, 4);
+        return Ok(());
     }
 
-    pub fn swap(&mut self, step: usize) {
-        assert!(self.depth >= 2, "stack underflow at step {}", step);
+    pub fn swap(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 2)?;
         self.user_registers[0][step + 1] = self.user_registers[1][step];
         self.user_registers[1][step + 1] = self.user_registers[0][step];
         self.copy_state(step, 2);
+        return Ok(());
     }
 
-    pub fn swap2(&mut self, step: usize) {
-        assert!(self.depth >= 4, "stack underflow at step {}", step);
+    pub fn swap2(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 4)?;
         self.user_registers[0][step + 1] = self.user_registers[2][step];
         self.user_registers[1][step + 1] = self.user_registers[3][step];
         self.user_registers[2][step + 1] = self.user_registers[0][step];
         self.user_registers[3][step + 1] = self.user_registers[1][step];
         self.copy_state(step, 4);
+        return Ok(());
     }
 
-    pub fn swap4(&mut self, step: usize) {
-        assert!(self.depth >= 8, "stack underflow at step {}", step);
+    pub fn swap4(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 8)?;
         self.user_registers[0][step + 1] = self.user_registers[4][step];
         self.user_registers[1][step + 1] = self.user_registers[5][step];
         self.user_registers[2][step + 1] = self.user_registers[6][step];
@@ -119,19 +142,21 @@ impl <T> StackTrace<T>
         self.user_registers[6][step + 1] = self.user_registers[2][step];
         self.user_registers[7][step + 1] = self.user_registers[3][step];
         self.copy_state(step, 8);
+        return Ok(());
     }
 
-    pub fn roll4(&mut self, step: usize) {
-        assert!(self.depth >= 4, "stack underflow at step {}", step);
+    pub fn roll4(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 4)?;
         self.user_registers[0][step + 1] = self.user_registers[3][step];
         self.user_registers[1][step + 1] = self.user_registers[0][step];
         self.user_registers[2][step + 1] = self.user_registers[1][step];
         self.user_registers[3][step + 1] = self.user_registers[2][step];
         self.copy_state(step, 4);
+        return Ok(());
     }
 
-    pub fn roll8(&mut self, step: usize) {
-        assert!(self.depth >= 8, "stack underflow at step {}", step);
+    pub fn roll8(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 8)?;
         self.user_registers[0][step + 1] = self.user_registers[7][step];
         self.user_registers[1][step + 1] = self.user_registers[0][step];
         self.user_registers[2][step + 1] = self.user_registers[1][step];
@@ -141,10 +166,11 @@ impl <T> StackTrace<T>
         self.user_registers[6][step + 1] = self.user_registers[5][step];
         self.user_registers[7][step + 1] = self.user_registers[6][step];
         self.copy_state(step, 8);
+        return Ok(());
     }
 
-    pub fn choose(&mut self, step: usize) {
-        assert!(self.depth >= 3, "stack underflow at step {}", step);
+    pub fn choose(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 3)?;
         let condition = self.user_registers[2][step];
         if condition == T::ONE {
             self.user_registers[0][step + 1] = self.user_registers[0][step];
@@ -153,13 +179,14 @@ impl <T> StackTrace<T>
             self.user_registers[0][step + 1] = self.user_registers[1][step];
         }
         else {
-            assert!(false, "CHOOSE on a non-binary condition at step {}", step);
+            return Err(format!("CHOOSE on a non-binary condition at step {}", step));
         }
         self.shift_left(step, 3, 2);
+        return Ok(());
     }
 
-    pub fn choose2(&mut self, step: usize) {
-        assert!(self.depth >= 6, "stack underflow at step {}", step);
+    pub fn choose2(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 6)?;
         let condition = self.user_registers[4][step];
         if condition == T::ONE {
             self.user_registers[0][step + 1] = self.user_registers[0][step];
@@ -170,52 +197,82 @@ impl <T> StackTrace<T>
             self.user_registers[1][step + 1] = self.user_registers[3][step];
         }
         else {
-            assert!(false, "CHOOSE2 on a non-binary condition at step {}", step);
+            return Err(format!("CHOOSE2 on a non-binary condition at step {}", step));
         }
         self.shift_left(step, 6, 4);
+        return Ok(());
     }
 
-    pub fn add(&mut self, step: usize) {
-        assert!(self.depth >= 2, "stack underflow at step {}", step);
+    pub fn add(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 2)?;
         let x = self.user_registers[0][step];
         let y = self.user_registers[1][step];
         self.user_registers[0][step + 1] = T::add(x, y);
         self.shift_left(step, 2, 1);
+        return Ok(());
     }
 
-    pub fn mul(&mut self, step: usize) {
-        assert!(self.depth >= 2, "stack underflow at step {}", step);
+    pub fn mul(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 2)?;
         let x = self.user_registers[0][step];
         let y = self.user_registers[1][step];
         self.user_registers[0][step + 1] = T::mul(x, y);
         self.shift_left(step, 2, 1);
+        return Ok(());
     }
 
-    pub fn inv(&mut self, step: usize) {
-        assert!(self.depth >= 1, "stack underflow at step {}", step);
+    pub fn inv(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
         let x = self.user_registers[0][step];
-        assert!(x != T::ZERO, "cannot compute INV of {} at step {}", T::ZERO, step);
+        if x == T::ZERO {
+            return Err(format!("cannot compute INV of {} at step {}", T::ZERO, step));
+        }
         self.user_registers[0][step + 1] = T::inv(x);
         self.copy_state(step, 1);
+        return Ok(());
     }
 
-    pub fn neg(&mut self, step: usize) {
-        assert!(self.depth >= 1, "stack underflow at step {}", step);
+    pub fn neg(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
         let x = self.user_registers[0][step];
         self.user_registers[0][step + 1] = T::neg(x);
         self.copy_state(step, 1);
+        return Ok(());
     }
 
-    pub fn not(&mut self, step: usize) {
-        assert!(self.depth >= 1, "stack underflow at step {}", step);
+    pub fn not(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
         let x = self.user_registers[0][step];
-        assert!(x == T::ZERO || x == T::ONE, "cannot compute NOT of a non-binary value at step {}", step);
+        if x != T::ZERO && x != T::ONE {
+            return Err(format!("cannot compute NOT of a non-binary value at step {}", step));
+        }
         self.user_registers[0][step + 1] = T::sub(T::ONE, x);
         self.copy_state(step, 1);
+        return Ok(());
     }
 
-    pub fn eq(&mut self, step: usize) {
-        assert!(self.depth >= 2, "stack underflow at step {}", step);
+    pub fn exp(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 4)?;
+        let bit = self.read_secret_a(step)?;
+        check_binary_input(step, bit)?;
+
+        let power_of_two = self.user_registers[0][step];
+        let acc = self.user_registers[1][step];
+        let base = self.user_registers[2][step];
+        let result = self.user_registers[3][step];
+
+        self.aux_register[step] = bit;
+        self.user_registers[0][step + 1] = T::mul(power_of_two, T::from_usize(2));
+        self.user_registers[1][step + 1] = T::add(acc, T::mul(bit, power_of_two));
+        self.user_registers[2][step + 1] = T::mul(base, base);
+        self.user_registers[3][step + 1] = if bit == T::ONE { T::mul(result, base) } else { result };
+
+        self.copy_state(step, 4);
+        return Ok(());
+    }
+
+    pub fn eq(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 2)?;
         let x = self.user_registers[0][step];
         let y = self.user_registers[1][step];
         if x == y {
@@ -227,18 +284,29 @@ impl <T> StackTrace<T>
             self.user_registers[0][step + 1] = T::ZERO;
         }
         self.shift_left(step, 2, 1);
+        return Ok(());
     }
 
-    pub fn cmp(&mut self, step: usize) {
-        assert!(self.depth >= 7, "stack underflow at step {}", step);
-        assert!(self.secret_inputs_a.len() > 0, "ran out of secret inputs at step {}", step);
-        assert!(self.secret_inputs_b.len() > 0, "ran out of secret inputs at step {}", step);
-        let a_bit = self.secret_inputs_a.pop().unwrap();
-        assert!(a_bit == T::ZERO || a_bit == T::ONE,
-            "expected binary input at step {} but received: {}", step, a_bit);
-        let b_bit = self.secret_inputs_b.pop().unwrap();
-        assert!(b_bit == T::ZERO || b_bit == T::ONE,
-            "expected binary input at step {} but received: {}", step, b_bit);
+    pub fn iszero(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 1)?;
+        let x = self.user_registers[0][step];
+        if x == T::ZERO {
+            self.aux_register[step] = T::ONE;
+            self.user_registers[0][step + 1] = T::ONE;
+        } else {
+            self.aux_register[step] = T::inv(x);
+            self.user_registers[0][step + 1] = T::ZERO;
+        }
+        self.copy_state(step, 1);
+        return Ok(());
+    }
+
+    pub fn cmp(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 7)?;
+        let a_bit = self.read_secret_a(step)?;
+        check_binary_input(step, a_bit)?;
+        let b_bit = self.read_secret_b(step)?;
+        check_binary_input(step, b_bit)?;
 
         let bit_gt = T::mul(a_bit, T::sub(T::ONE, b_bit));
         let bit_lt = T::mul(b_bit, T::sub(T::ONE, a_bit));
@@ -258,13 +326,12 @@ impl <T> StackTrace<T>
         self.user_registers[6][step + 1] = T::add(self.user_registers[6][step], T::mul(a_bit, power_of_two));
 
         self.copy_state(step, 7);
+        return Ok(());
     }
 
-    pub fn binacc(&mut self, step: usize) {
-        assert!(self.depth >= 2, "stack underflow at step {}", step);
-        assert!(self.secret_inputs_a.len() > 0, "ran out of secret inputs at step {}", step);
-
-        let bit = self.secret_inputs_a.pop().unwrap();
+    pub fn binacc(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, 2)?;
+        let bit = self.read_secret_a(step)?;
         let power_of_two = self.user_registers[0][step];    // TODO: make sure it is power of 2
         let acc = self.user_registers[1][step];
 
@@ -273,10 +340,11 @@ impl <T> StackTrace<T>
         self.user_registers[1][step + 1] = T::add(acc, T::mul(bit, power_of_two));
 
         self.copy_state(step, 2);
+        return Ok(());
     }
 
-    pub fn hashr(&mut self, step: usize) {
-        assert!(self.depth >= HASH_STATE_WIDTH, "stack underflow at step {}", step);
+    pub fn hashr(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, HASH_STATE_WIDTH)?;
         let mut state = [
             self.user_registers[0][step],
             self.user_registers[1][step],
@@ -296,6 +364,43 @@ impl <T> StackTrace<T>
         self.user_registers[5][step + 1] = state[5];
 
         self.copy_state(step, HASH_STATE_WIDTH);
+        return Ok(());
+    }
+
+    pub fn mpath(&mut self, step: usize) -> Result<(), String> {
+        self.check_depth(step, HASH_STATE_WIDTH + 1)?;
+
+        // the next node of the authentication path is read from input tapes A and B
+        let sibling_0 = self.read_secret_a(step)?;
+        let sibling_1 = self.read_secret_b(step)?;
+
+        // the current node is located in the slots where results of hashing are placed
+        let node_1 = self.user_registers[4][step];
+        let node_0 = self.user_registers[5][step];
+
+        // the least significant bit of the index determines whether the current node is
+        // the first or the second value to be hashed
+        let index = self.user_registers[6][step];
+        let bit = T::from(index.as_u8() & 1);
+
+        self.user_registers[0][step + 1] = T::ZERO;
+        self.user_registers[1][step + 1] = T::ZERO;
+        if bit == T::ZERO {
+            self.user_registers[2][step + 1] = sibling_1;
+            self.user_registers[3][step + 1] = sibling_0;
+            self.user_registers[4][step + 1] = node_1;
+            self.user_registers[5][step + 1] = node_0;
+        }
+        else {
+            self.user_registers[2][step + 1] = node_1;
+            self.user_registers[3][step + 1] = node_0;
+            self.user_registers[4][step + 1] = sibling_1;
+            self.user_registers[5][step + 1] = sibling_0;
+        }
+        self.user_registers[6][step + 1] = T::div(T::sub(index, bit), T::from_usize(2));
+
+        self.copy_state(step, HASH_STATE_WIDTH + 1);
+        return Ok(());
     }
 
     // HELPER METHODS
@@ -326,10 +431,12 @@ impl <T> StackTrace<T>
         self.depth -= pos_count;
     }
 
-    fn shift_right(&mut self, step: usize, start: usize, pos_count: usize) {
-        
+    fn shift_right(&mut self, step: usize, start: usize, pos_count: usize) -> Result<(), String> {
+
+        if self.depth + pos_count > MAX_USER_STACK_DEPTH {
+            return Err(format!("stack overflow at step {}", step));
+        }
         self.depth += pos_count;
-        assert!(self.depth <= MAX_USER_STACK_DEPTH, "stack overflow at step {}", step);
 
         if self.depth > self.max_depth {
             self.max_depth += pos_count;
@@ -342,6 +449,32 @@ impl <T> StackTrace<T>
             let slot_value = self.user_registers[i][step];
             self.user_registers[i + pos_count][step + 1] = slot_value;
         }
+
+        return Ok(());
+    }
+
+    /// Makes sure the stack holds at least `depth` values.
+    fn check_depth(&self, step: usize, depth: usize) -> Result<(), String> {
+        if self.depth < depth {
+            return Err(format!("stack underflow at step {}", step));
+        }
+        return Ok(());
+    }
+
+    /// Removes the next value from secret input tape A.
+    fn read_secret_a(&mut self, step: usize) -> Result<T, String> {
+        return match self.secret_inputs_a.pop() {
+            Some(value) => Ok(value),
+            None => Err(format!("ran out of secret inputs at step {}", step))
+        };
+    }
+
+    /// Removes the next value from secret input tape B.
+    fn read_secret_b(&mut self, step: usize) -> Result<T, String> {
+        return match self.secret_inputs_b.pop() {
+            Some(value) => Ok(value),
+            None => Err(format!("ran out of secret inputs at step {}", step))
+        };
     }
 
     /// Extends the stack by the specified number of registers
@@ -353,4 +486,15 @@ impl <T> StackTrace<T>
             self.user_registers.push(register);
         }
     }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Makes sure a value read from a secret input tape is either 0 or 1.
+fn check_binary_input<T: FiniteField>(step: usize, value: T) -> Result<(), String> {
+    if value != T::ZERO && value != T::ONE {
+        return Err(format!("expected binary input at step {} but received: {}", step, value));
+    }
+    return Ok(());
 }
\ No newline at end of file
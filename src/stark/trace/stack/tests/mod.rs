@@ -13,7 +13,7 @@ mod comparisons;
 #[test]
 fn noop() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.noop(0);
+    stack.noop(0).unwrap();
     assert_eq!(vec![1, 2, 3, 4, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -23,7 +23,7 @@ fn noop() {
 #[test]
 fn assert() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.assert(0);
+    stack.assert(0).unwrap();
     assert_eq!(vec![2, 3, 4, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -31,10 +31,9 @@ fn assert() {
 }
 
 #[test]
-#[should_panic(expected = "ASSERT failed at step 0")]
 fn assert_fail() {
     let mut stack = init_stack(&[2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.assert(0);
+    assert_eq!(Err(String::from("ASSERT failed at step 0")), stack.assert(0));
 }
 
 // INPUT OPERATIONS
@@ -43,7 +42,7 @@ fn assert_fail() {
 #[test]
 fn push() {
     let mut stack = init_stack(&[], &[], &[], TRACE_LENGTH);
-    stack.push(0, 3);
+    stack.push(0, 3).unwrap();
     assert_eq!(vec![3, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -54,13 +53,13 @@ fn push() {
 fn read() {
     let mut stack = init_stack(&[1], &[2, 3], &[], TRACE_LENGTH);
 
-    stack.read(0);
+    stack.read(0).unwrap();
     assert_eq!(vec![2, 1, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
     assert_eq!(2, stack.max_depth);
 
-    stack.read(1);
+    stack.read(1).unwrap();
     assert_eq!(vec![3, 2, 1, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(3, stack.depth);
@@ -71,26 +70,45 @@ fn read() {
 fn read2() {
     let mut stack = init_stack(&[1], &[2, 4], &[3, 5], TRACE_LENGTH);
 
-    stack.read2(0);
+    stack.read2(0).unwrap();
     assert_eq!(vec![3, 2, 1, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
     assert_eq!(3, stack.max_depth);
 
-    stack.read2(1);
+    stack.read2(1).unwrap();
     assert_eq!(vec![5, 4, 3, 2, 1, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(5, stack.depth);
     assert_eq!(5, stack.max_depth);
 }
 
+#[test]
+fn readp() {
+    let mut stack = init_stack(&[1], &[], &[], TRACE_LENGTH);
+    stack.public_tape = vec![3, 2];
+
+    stack.readp(0).unwrap();
+    assert_eq!(vec![2, 1, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
+
+    assert_eq!(2, stack.depth);
+    assert_eq!(2, stack.max_depth);
+
+    stack.readp(1).unwrap();
+    assert_eq!(vec![3, 2, 1, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
+
+    assert_eq!(3, stack.depth);
+    assert_eq!(3, stack.max_depth);
+    assert_eq!(0, stack.public_tape.len());
+}
+
 // STACK MANIPULATION OPERATIONS
 // ================================================================================================
 
 #[test]
 fn dup() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.dup(0);
+    stack.dup(0).unwrap();
     assert_eq!(vec![1, 1, 2, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -100,7 +118,7 @@ fn dup() {
 #[test]
 fn dup2() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.dup2(0);
+    stack.dup2(0).unwrap();
     assert_eq!(vec![1, 2, 1, 2, 3, 4, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(6, stack.depth);
@@ -110,7 +128,7 @@ fn dup2() {
 #[test]
 fn dup4() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.dup4(0);
+    stack.dup4(0).unwrap();
     assert_eq!(vec![1, 2, 3, 4, 1, 2, 3, 4], get_stack_state(&stack, 1));
 
     assert_eq!(8, stack.depth);
@@ -120,7 +138,7 @@ fn dup4() {
 #[test]
 fn pad2() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.pad2(0);
+    stack.pad2(0).unwrap();
     assert_eq!(vec![0, 0, 1, 2, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -130,7 +148,7 @@ fn pad2() {
 #[test]
 fn drop() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.drop(0);
+    stack.drop(0).unwrap();
     assert_eq!(vec![2, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -140,7 +158,7 @@ fn drop() {
 #[test]
 fn drop4() {
     let mut stack = init_stack(&[1, 2, 3, 4, 5], &[], &[], TRACE_LENGTH);
-    stack.drop4(0);
+    stack.drop4(0).unwrap();
     assert_eq!(vec![5, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -150,7 +168,7 @@ fn drop4() {
 #[test]
 fn swap() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.swap(0);
+    stack.swap(0).unwrap();
     assert_eq!(vec![2, 1, 3, 4, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -160,7 +178,7 @@ fn swap() {
 #[test]
 fn swap2() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.swap2(0);
+    stack.swap2(0).unwrap();
     assert_eq!(vec![3, 4, 1, 2, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -170,7 +188,7 @@ fn swap2() {
 #[test]
 fn swap4() {
     let mut stack = init_stack(&[1, 2, 3, 4, 5, 6, 7, 8], &[], &[], TRACE_LENGTH);
-    stack.swap4(0);
+    stack.swap4(0).unwrap();
     assert_eq!(vec![5, 6, 7, 8, 1, 2, 3, 4], get_stack_state(&stack, 1));
 
     assert_eq!(8, stack.depth);
@@ -180,7 +198,7 @@ fn swap4() {
 #[test]
 fn roll4() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.roll4(0);
+    stack.roll4(0).unwrap();
     assert_eq!(vec![4, 1, 2, 3, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -190,7 +208,7 @@ fn roll4() {
 #[test]
 fn roll8() {
     let mut stack = init_stack(&[1, 2, 3, 4, 5, 6, 7, 8], &[], &[], TRACE_LENGTH);
-    stack.roll8(0);
+    stack.roll8(0).unwrap();
     assert_eq!(vec![8, 1, 2, 3, 4, 5, 6, 7], get_stack_state(&stack, 1));
 
     assert_eq!(8, stack.depth);
@@ -204,14 +222,14 @@ fn roll8() {
 fn choose() {
     // choose on true
     let mut stack = init_stack(&[2, 3, 0], &[], &[], TRACE_LENGTH);
-    stack.choose(0);
+    stack.choose(0).unwrap();
     assert_eq!(vec![3, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
     assert_eq!(3, stack.max_depth);
 
     let mut stack = init_stack(&[2, 3, 0, 4], &[], &[], TRACE_LENGTH);
-    stack.choose(0);
+    stack.choose(0).unwrap();
     assert_eq!(vec![3, 4, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -219,7 +237,7 @@ fn choose() {
 
     // choose on false
     let mut stack = init_stack(&[2, 3, 1, 4], &[], &[], TRACE_LENGTH);
-    stack.choose(0);
+    stack.choose(0).unwrap();
     assert_eq!(vec![2, 4, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -227,17 +245,16 @@ fn choose() {
 }
 
 #[test]
-#[should_panic(expected = "CHOOSE on a non-binary condition at step 0")]
 fn choose_fail() {
     let mut stack = init_stack(&[2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.choose(0);
+    assert_eq!(Err(String::from("CHOOSE on a non-binary condition at step 0")), stack.choose(0));
 }
 
 #[test]
 fn choose2() {
     // choose on true
     let mut stack = init_stack(&[2, 3, 4, 5, 0, 6, 7], &[], &[], TRACE_LENGTH);
-    stack.choose2(0);
+    stack.choose2(0).unwrap();
     assert_eq!(vec![4, 5, 7, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -245,7 +262,7 @@ fn choose2() {
 
     // choose on false
     let mut stack = init_stack(&[2, 3, 4, 5, 1, 6, 7], &[], &[], TRACE_LENGTH);
-    stack.choose2(0);
+    stack.choose2(0).unwrap();
     assert_eq!(vec![2, 3, 7, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -253,10 +270,9 @@ fn choose2() {
 }
 
 #[test]
-#[should_panic(expected = "CHOOSE2 on a non-binary condition at step 0")]
 fn choose2_fail() {
     let mut stack = init_stack(&[2, 3, 4, 5, 6, 8, 8], &[], &[], TRACE_LENGTH);
-    stack.choose2(0);
+    assert_eq!(Err(String::from("CHOOSE2 on a non-binary condition at step 0")), stack.choose2(0));
 }
 
 // ARITHMETIC AND BOOLEAN OPERATIONS
@@ -265,7 +281,7 @@ fn choose2_fail() {
 #[test]
 fn add() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.add(0);
+    stack.add(0).unwrap();
     assert_eq!(vec![3, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -275,7 +291,7 @@ fn add() {
 #[test]
 fn mul() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.mul(0);
+    stack.mul(0).unwrap();
     assert_eq!(vec![6, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -285,7 +301,7 @@ fn mul() {
 #[test]
 fn inv() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.inv(0);
+    stack.inv(0).unwrap();
     assert_eq!(vec![F128::inv(2), 3, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -293,16 +309,15 @@ fn inv() {
 }
 
 #[test]
-#[should_panic(expected = "cannot compute INV of 0 at step 0")]
 fn inv_zero() {
     let mut stack = init_stack(&[0], &[], &[], TRACE_LENGTH);
-    stack.inv(0);
+    assert_eq!(Err(String::from("cannot compute INV of 0 at step 0")), stack.inv(0));
 }
 
 #[test]
 fn neg() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.neg(0);
+    stack.neg(0).unwrap();
     assert_eq!(vec![F128::neg(2), 3, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -312,13 +327,13 @@ fn neg() {
 #[test]
 fn not() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.not(0);
+    stack.not(0).unwrap();
     assert_eq!(vec![0, 2, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
     assert_eq!(2, stack.max_depth);
 
-    stack.not(1);
+    stack.not(1).unwrap();
     assert_eq!(vec![1, 2, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(2, stack.depth);
@@ -326,10 +341,35 @@ fn not() {
 }
 
 #[test]
-#[should_panic(expected = "cannot compute NOT of a non-binary value at step 0")]
 fn not_fail() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.not(0);
+    assert_eq!(Err(String::from("cannot compute NOT of a non-binary value at step 0")), stack.not(0));
+}
+
+#[test]
+fn exp() {
+    let mut stack = init_stack(&[1, 0, 3, 1, 5], &[1, 0, 1], &[], TRACE_LENGTH);
+
+    stack.exp(0).unwrap();
+    assert_eq!(vec![2, 1, 9, 3, 5, 0, 0, 0], get_stack_state(&stack, 1));
+    assert_eq!(1, stack.aux_register[0]);
+
+    stack.exp(1).unwrap();
+    assert_eq!(vec![4, 1, 81, 3, 5, 0, 0, 0], get_stack_state(&stack, 2));
+    assert_eq!(0, stack.aux_register[1]);
+
+    stack.exp(2).unwrap();
+    assert_eq!(vec![8, 5, 6561, 243, 5, 0, 0, 0], get_stack_state(&stack, 3));
+    assert_eq!(1, stack.aux_register[2]);
+
+    assert_eq!(5, stack.depth);
+    assert_eq!(5, stack.max_depth);
+}
+
+#[test]
+fn exp_fail() {
+    let mut stack = init_stack(&[1, 0, 3, 1], &[2], &[], TRACE_LENGTH);
+    assert_eq!(Err(String::from("expected binary input at step 0 but received: 2")), stack.exp(0));
 }
 
 // CRYPTOGRAPHIC OPERATIONS
@@ -340,11 +380,11 @@ fn hashr() {
     let mut stack = init_stack(&[0, 0, 1, 2, 3, 4], &[], &[], TRACE_LENGTH);
     let mut expected = vec![0, 0, 1, 2, 3, 4, 0, 0];
 
-    stack.hashr(0);
+    stack.hashr(0).unwrap();
     <F128 as Hasher>::apply_round(&mut expected[..F128::STATE_WIDTH], 0);
     assert_eq!(expected, get_stack_state(&stack, 1));
 
-    stack.hashr(1);
+    stack.hashr(1).unwrap();
     <F128 as Hasher>::apply_round(&mut expected[..F128::STATE_WIDTH], 1);
     assert_eq!(expected, get_stack_state(&stack, 2));
 
@@ -352,6 +392,31 @@ fn hashr() {
     assert_eq!(6, stack.max_depth);
 }
 
+#[test]
+fn mpath() {
+    // index is even: current node should be the first value to be hashed
+    let mut stack = init_stack(&[0, 0, 0, 0, 1, 2, 6, 7], &[3], &[4], TRACE_LENGTH);
+    stack.mpath(0).unwrap();
+    assert_eq!(vec![0, 0, 4, 3, 1, 2, 3, 7], get_stack_state(&stack, 1));
+
+    assert_eq!(8, stack.depth);
+    assert_eq!(8, stack.max_depth);
+
+    // index is odd: current node should be the second value to be hashed
+    let mut stack = init_stack(&[0, 0, 0, 0, 1, 2, 7, 7], &[3], &[4], TRACE_LENGTH);
+    stack.mpath(0).unwrap();
+    assert_eq!(vec![0, 0, 1, 2, 4, 3, 3, 7], get_stack_state(&stack, 1));
+
+    assert_eq!(8, stack.depth);
+    assert_eq!(8, stack.max_depth);
+}
+
+#[test]
+fn mpath_fail() {
+    let mut stack = init_stack(&[0, 0, 0, 0, 1, 2, 6], &[], &[], TRACE_LENGTH);
+    assert_eq!(Err(String::from("ran out of secret inputs at step 0")), stack.mpath(0));
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 
@@ -375,6 +440,7 @@ fn init_stack(public_inputs: &[F128], secret_inputs_a: &[F128], secret_inputs_b:
     return super::StackTrace {
         aux_register,
         user_registers,
+        public_tape: Vec::new(),
         secret_inputs_a,
         secret_inputs_b,
         max_depth: public_inputs.len(),
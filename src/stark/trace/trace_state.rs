@@ -9,6 +9,7 @@ use crate::stark::{
     OP_BITS_RANGE,
     OP_ACC_RANGE,
     PROG_HASH_RANGE,
+    TAPE_ACC_RANGE,
     NUM_LD_OPS
 };
 
@@ -71,6 +72,10 @@ impl <T> TraceState<T>
         return &self.registers[PROG_HASH_RANGE];
     }
 
+    pub fn get_tape_acc(&self) -> &[T] {
+        return &self.registers[TAPE_ACC_RANGE];
+    }
+
     pub fn get_op_bits(&self) -> &[T] {
         return &self.registers[OP_BITS_RANGE];
     }
@@ -146,10 +151,11 @@ impl <T> fmt::Display for TraceState<T>
     where T: FiniteField + Accumulator
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{}]\t{:?}\t{:?}\t{:?}",
+        write!(f, "[{}]\t{:?}\t{:?}\t{:?}\t{:?}",
             self.get_op_code(), 
             self.get_op_bits(),
             self.get_op_acc(),
+            self.get_tape_acc(),
             self.get_stack())
     }
 }
\ No newline at end of file
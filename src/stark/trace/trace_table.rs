@@ -1,9 +1,11 @@
+use std::{ mem, ops::Range };
+use crossbeam_utils::thread;
 use crate::math::{ FiniteField, fft, polynom, parallel };
 use crate::crypto::{ MerkleTree, HashFunction };
 use crate::processor::opcodes;
 use crate::utils::{ uninit_vector, filled_vector, as_bytes };
-use crate::stark::{ ProgramInputs, CompositionCoefficients, Accumulator, Hasher, utils };
-use crate::stark::{ MAX_REGISTER_COUNT, DECODER_WIDTH, PROG_HASH_RANGE };
+use crate::stark::{ ProgramInputs, SegmentState, CompositionCoefficients, Accumulator, Hasher, utils };
+use crate::stark::{ MAX_REGISTER_COUNT, MAX_CONSTRAINT_DEGREE, DECODER_WIDTH, PROG_HASH_RANGE, TAPE_ACC_RANGE };
 use super::{ TraceState, decoder, stack };
 
 // TYPES AND INTERFACES
@@ -11,7 +13,17 @@ use super::{ TraceState, decoder, stack };
 pub struct TraceTable<T> {
     registers   : Vec<Vec<T>>,
     polys       : Vec<Vec<T>>,
+    trace_length: usize,
     ext_factor  : usize,
+    blinding    : Option<Blinding<T>>,
+}
+
+/// Random values used to hide the execution trace when a proof is generated in zero-knowledge
+/// mode; these are generated when the trace table is extended.
+struct Blinding<T> {
+    randomizer_poly : Vec<T>,
+    randomizer      : Vec<T>,
+    salt_seed       : [u8; 32],
 }
 
 // TRACE TABLE IMPLEMENTATION
@@ -20,18 +32,33 @@ impl <T> TraceTable<T>
     where T: FiniteField + Accumulator + Hasher
 {
     /// Returns a trace table resulting from the execution of the specified program. Space for the
-    /// trace table is allocated in accordance with the specified `extension_factor`.
-    pub fn new(program: &[T], inputs: &ProgramInputs<T>, extension_factor: usize) -> TraceTable<T> {
-        
+    /// trace table is allocated in accordance with the specified `extension_factor`. Returns an
+    /// error if the program fails during execution (e.g. on stack underflow or a failed ASSERT).
+    pub fn new(program: &[T], inputs: &ProgramInputs<T>, extension_factor: usize) -> Result<TraceTable<T>, String> {
+        let public_inputs = inputs.get_public_inputs();
+        let init_state = SegmentState::from_inputs(public_inputs);
+        let (trace, _) = TraceTable::new_segment(program, &init_state, public_inputs.len(), inputs, extension_factor)?;
+        return Ok(trace);
+    }
+
+    /// Returns a trace table resulting from the execution of a single segment of a program.
+    /// The execution starts in `init_state` with the top `init_depth` values of the state's stack
+    /// considered to be on the stack; public inputs of `inputs` are ignored, and public tape and
+    /// secret inputs must contain exactly the values consumed by the segment. Returns the trace
+    /// table together with the depth of the stack after the last step of the segment, or an error
+    /// if the segment fails during execution.
+    pub fn new_segment(program: &[T], init_state: &SegmentState<T>, init_depth: usize, inputs: &ProgramInputs<T>, extension_factor: usize) -> Result<(TraceTable<T>, usize), String> {
+
         assert!(program.len() > 1, "program length must be greater than 1");
         assert!(program.len().is_power_of_two(), "program length must be a power of 2");
         assert!(program[0] == T::from(opcodes::BEGIN), "first operation of a program must be BEGIN");
         assert!(program[program.len() - 1] == T::from(opcodes::NOOP), "last operation of a program must be NOOP");
         assert!(extension_factor.is_power_of_two(), "trace extension factor must be a power of 2");
 
-        // create different segments of the trace
-        let decoder_registers = decoder::process(program, extension_factor);
-        let stack_registers = stack::execute(program, inputs, extension_factor);
+        // create different segments of the trace; the stack is executed first so that execution
+        // errors are reported before any other work is done
+        let (stack_registers, depth) = stack::execute(program, init_state.stack(), init_depth, inputs, extension_factor)?;
+        let decoder_registers = decoder::process(program, init_state.op_acc(), init_state.tape_acc(), inputs.get_public_tape(), extension_factor);
 
         // move all trace registers into a single vector
         let mut registers = Vec::new();
@@ -42,23 +69,27 @@ impl <T> TraceTable<T>
             "execution trace cannot have more than {} registers", MAX_REGISTER_COUNT);
 
         let polys = Vec::with_capacity(registers.len());
-        return TraceTable { registers, polys, ext_factor: extension_factor };
+        let trace_length = program.len();
+        let trace = TraceTable { registers, polys, trace_length, ext_factor: extension_factor, blinding: None };
+        return Ok((trace, depth));
     }
 
     /// Returns hash value of the executed program.
     pub fn get_program_hash(&self) -> Vec<T> {
-        let last_step = if self.is_extended() {
-            self.domain_size() - self.extension_factor()
-        }
-        else {
-            self.unextended_length() - 1
-        };
+        return self.get_last_step_values(PROG_HASH_RANGE);
+    }
 
-        let mut result = vec![T::ZERO; PROG_HASH_RANGE.end - PROG_HASH_RANGE.start];
-        for (i, j) in PROG_HASH_RANGE.enumerate() {
-            result[i] = self.registers[j][last_step];
-        }
-        return result;
+    /// Returns hash value of the public input tape consumed by the executed program.
+    pub fn get_public_tape_hash(&self) -> Vec<T> {
+        return self.get_last_step_values(TAPE_ACC_RANGE);
+    }
+
+    /// Returns the state of the VM at the specified `step`; this includes full states of program
+    /// hash and public tape accumulators, and values of all user stack registers (padded with
+    /// zeros if the stack is shallower than the min stack depth).
+    pub fn get_segment_state(&self, step: usize) -> SegmentState<T> {
+        let state = self.get_state(step);
+        return SegmentState::new(state.get_op_acc(), state.get_tape_acc(), state.get_user_stack());
     }
 
     /// Returns state of the trace table at the specified `step`.
@@ -68,8 +99,10 @@ impl <T> TraceTable<T>
         return result;
     }
 
-    /// Copies trace table state at the specified `step` to the passed in `state` object.
+    /// Copies trace table state at the specified `step` to the passed in `state` object; can
+    /// not be called if the trace table has been extended lazily.
     pub fn fill_state(&self, state: &mut TraceState<T>, step: usize) {
+        debug_assert!(!self.is_lazily_extended(), "trace table has been extended lazily");
         for i in 0..self.registers.len() {
             state.set_register(i, self.registers[i][step]);
         }
@@ -77,12 +110,12 @@ impl <T> TraceTable<T>
 
     /// Returns the number of states in the un-extended trace table.
     pub fn unextended_length(&self) -> usize {
-        return self.registers[0].capacity() / self.ext_factor;
+        return self.trace_length;
     }
 
     /// Returns the number of states in the extended trace table.
     pub fn domain_size(&self) -> usize {
-        return self.registers[0].capacity();
+        return self.trace_length * self.ext_factor;
     }
 
     /// Returns `extension_factor` for the trace table.
@@ -90,6 +123,18 @@ impl <T> TraceTable<T>
         return self.ext_factor;
     }
 
+    /// Returns the number of coefficients in trace polynomials; this is greater than the length
+    /// of the un-extended trace if the trace table was blinded when it was extended.
+    pub fn randomized_length(&self) -> usize {
+        assert!(self.is_extended(), "trace table has not been extended yet");
+        return self.polys[0].len();
+    }
+
+    /// Returns `true` if the trace table was blinded when it was extended.
+    pub fn is_blinded(&self) -> bool {
+        return self.blinding.is_some();
+    }
+
     /// Returns the number of registers in the trace table.
     pub fn register_count(&self) -> usize {
         return self.registers.len();
@@ -117,61 +162,235 @@ impl <T> TraceTable<T>
         return &self.registers[index + DECODER_WIDTH];
     }
 
-    /// Returns values of all registers at the specified `positions`.
-    pub fn get_register_values_at(&self, positions: &[usize]) -> Vec<Vec<T>> {
-        let mut result = Vec::with_capacity(positions.len());
-        for &i in positions.iter() {
-            let row = self.registers.iter().map(|r| r[i]).collect();
-            result.push(row);
+    /// Returns values of all registers at the specified `positions`. If the trace table has been
+    /// extended lazily, the values are computed by evaluating trace polynomials at the
+    /// corresponding points of the extended domain; positions are distributed across
+    /// `num_threads` threads.
+    pub fn get_register_values_at(&self, positions: &[usize], num_threads: usize) -> Vec<Vec<T>> {
+        if !self.is_lazily_extended() {
+            return positions.iter().map(|&i| self.registers.iter().map(|r| r[i]).collect()).collect();
         }
+
+        let lde_root = T::get_root_of_unity(self.domain_size());
+        let mut result = vec![Vec::new(); positions.len()];
+        let batch_size = positions.len().div_ceil(num_threads);
+        thread::scope(|s| {
+            for (positions, rows) in positions.chunks(batch_size).zip(result.chunks_mut(batch_size)) {
+                s.spawn(move |_| {
+                    for (&i, row) in positions.iter().zip(rows.iter_mut()) {
+                        *row = self.eval_polys_at(T::mul(T::GENERATOR, T::exp(lde_root, T::from_usize(i))));
+                    }
+                });
+            }
+        }).unwrap();
         return result;
     }
 
+    /// Returns values of the randomizer and salts of trace Merkle tree leaves at the specified
+    /// `positions`; can be called only if the trace table has been blinded.
+    pub fn get_blinding_values_at(&self, positions: &[usize]) -> (Vec<T>, Vec<[u8; 32]>) {
+        let blinding = self.blinding.as_ref().expect("trace table has not been blinded");
+        let randomizer_values = positions.iter().map(|&i| blinding.randomizer[i]).collect();
+        let salts = positions.iter().map(|&i| utils::get_salt(&blinding.salt_seed, i)).collect();
+        return (randomizer_values, salts);
+    }
+
     /// Returns `true` if the trace table has been extended.
     pub fn is_extended(&self) -> bool {
-        return self.registers[0].len() == self.registers[0].capacity();
+        return !self.polys.is_empty();
+    }
+
+    /// Returns `true` if the trace table has been extended lazily; in such a case, only trace
+    /// polynomials are kept in memory (see `extend_lazily()`).
+    pub fn is_lazily_extended(&self) -> bool {
+        return self.is_extended() && self.registers[0].is_empty();
     }
 
     /// Extends all registers of the trace table by the `extension_factor` specified during
-    /// trace table construction. A trace table can be extended only once.
-    pub fn extend(&mut self, twiddles: &[T]) {
+    /// trace table construction. The registers are extended over a coset of the extended domain
+    /// (offset by the generator of the field) so that the extended trace does not contain any
+    /// of the values of the original trace. A trace table can be extended only once.
+    /// 
+    /// If `randomized_length` is greater than the length of the trace, the trace table is also
+    /// blinded: a random polynomial multiplied by Z(x) = x^trace_length - 1 is added to each
+    /// register polynomial so that the polynomial has `randomized_length` coefficients. This does
+    /// not change register values at the steps of the trace, but makes register values anywhere
+    /// else look random. Also, a random polynomial of the same degree as the composition
+    /// polynomial is generated; this polynomial is committed to together with the trace and is
+    /// merged into the composition polynomial to hide it.
+    ///
+    /// Registers are extended independently of each other, and are distributed across
+    /// `num_threads` threads.
+    pub fn extend(&mut self, twiddles: &[T], randomized_length: usize, num_threads: usize) {
+        self.extend_registers(twiddles, randomized_length, false, num_threads);
+    }
+
+    /// Same as `extend()`, but registers are not evaluated over the extended domain: register
+    /// traces are released, and only trace polynomials are kept. This reduces memory needed to
+    /// hold the trace table by a factor close to the extension factor. Afterwards, the extended
+    /// trace can be computed one chunk at a time (see `fill_lde_chunk()`).
+    pub fn extend_lazily(&mut self, twiddles: &[T], randomized_length: usize, num_threads: usize) {
+        self.extend_registers(twiddles, randomized_length, true, num_threads);
+    }
+
+    /// Returns the number of chunks into which the extended trace is split when it is computed
+    /// chunk by chunk. Chunk k contains states at steps k, k + n, k + 2n etc., where n is the
+    /// number of chunks; so, the states of a chunk are evaluations of trace polynomials over a
+    /// coset of a domain of `randomized_length` elements. A step and its next step (the step
+    /// `extension_factor` steps later) are always in the same chunk.
+    pub fn lde_chunk_count(&self) -> usize {
+        return self.domain_size() / self.randomized_length();
+    }
+
+    /// Evaluates trace polynomials at all steps of the extended trace which belong to the chunk
+    /// at the specified `index`, and writes the results into `chunk`: a vector of
+    /// `randomized_length` values for each register. `twiddles` must be computed for a domain
+    /// of `randomized_length` elements. Registers are distributed across `num_threads` threads.
+    pub fn fill_lde_chunk(&self, chunk: &mut [Vec<T>], index: usize, twiddles: &[T], num_threads: usize) {
+        assert!(self.is_extended(), "trace table has not been extended yet");
+        assert!(chunk.len() == self.register_count(), "chunk must contain {} registers", self.register_count());
+
+        // chunk k is a coset of the chunk domain offset by generator * lde_root^k
+        let lde_root = T::get_root_of_unity(self.domain_size());
+        let offset = T::mul(T::GENERATOR, T::exp(lde_root, T::from_usize(index)));
+
+        let batch_size = chunk.len().div_ceil(num_threads);
+        thread::scope(|s| {
+            for (registers, polys) in chunk.chunks_mut(batch_size).zip(self.polys.chunks(batch_size)) {
+                s.spawn(move |_| {
+                    for (register, poly) in registers.iter_mut().zip(polys.iter()) {
+                        register.copy_from_slice(poly);
+                        polynom::eval_fft_coset(register, twiddles, offset, true);
+                    }
+                });
+            }
+        }).unwrap();
+    }
+
+    fn extend_registers(&mut self, twiddles: &[T], randomized_length: usize, lazily: bool, num_threads: usize) {
         assert!(!self.is_extended(), "trace table has already been extended");
         assert!(twiddles.len() * 2 == self.domain_size(), "invalid number of twiddles");
 
+        let trace_length = self.unextended_length();
+        assert!(randomized_length.is_power_of_two() && randomized_length >= trace_length,
+            "randomized length must be a power of 2 and cannot be smaller than trace length");
+        assert!(randomized_length * MAX_CONSTRAINT_DEGREE <= self.domain_size(),
+            "randomized length cannot be greater than {}", self.domain_size() / MAX_CONSTRAINT_DEGREE);
+
         // build inverse twiddles needed for FFT interpolation
-        let root = T::get_root_of_unity(self.unextended_length());
-        let inv_twiddles = fft::get_inv_twiddles(root, self.unextended_length());
+        let root = T::get_root_of_unity(trace_length);
+        let inv_twiddles = fft::get_inv_twiddles(root, trace_length);
         
-        // extend all registers
+        // extend all registers; registers are split into groups of equal size, and each group
+        // is extended in a separate thread; when extending lazily, the registers are only
+        // interpolated
         let domain_size = self.domain_size();
-        for register in self.registers.iter_mut() {
-            debug_assert!(register.capacity() == domain_size, "invalid capacity for register");
-            // interpolate register trace into a polynomial
-            polynom::interpolate_fft_twiddles(register, &inv_twiddles, true);
-
-            // save the polynomial for later use
-            self.polys.push(register.clone());
-
-            // evaluate the polynomial over extended domain
-            unsafe { register.set_len(register.capacity()); }
-            polynom::eval_fft_twiddles(register, &twiddles, true);
+        let mut polys = vec![Vec::new(); self.registers.len()];
+        let batch_size = self.registers.len().div_ceil(num_threads);
+        thread::scope(|s| {
+            let batches = self.registers.chunks_mut(batch_size).zip(polys.chunks_mut(batch_size));
+            for (registers, polys) in batches {
+                let inv_twiddles = &inv_twiddles;
+                s.spawn(move |_| {
+                    for (register, poly) in registers.iter_mut().zip(polys.iter_mut()) {
+                        debug_assert!(register.capacity() == domain_size, "invalid capacity for register");
+                        if lazily {
+                            // the register becomes the polynomial, and its extra capacity is released
+                            interpolate_register(register, inv_twiddles, randomized_length);
+                            *poly = mem::take(register);
+                            poly.shrink_to_fit();
+                        }
+                        else {
+                            *poly = extend_register(register, inv_twiddles, twiddles, randomized_length);
+                        }
+                    }
+                });
+            }
+        }).unwrap();
+
+        // save the polynomials for later use
+        self.polys = polys;
+
+        // generate the randomizer such that its degree is one greater than the degree of the
+        // composition polynomial, and evaluate it over extended domain
+        if randomized_length > trace_length {
+            let composition_degree = utils::get_composition_degree(trace_length, randomized_length);
+            let randomizer_poly = T::rand_vector(composition_degree + 2);
+            let mut randomizer = vec![T::ZERO; domain_size];
+            randomizer[..randomizer_poly.len()].copy_from_slice(&randomizer_poly);
+            polynom::eval_fft_coset(&mut randomizer, &twiddles, T::GENERATOR, true);
+            self.blinding = Some(Blinding { randomizer_poly, randomizer, salt_seed: rand::random() });
         }
     }
 
     /// Puts the trace table into a Merkle tree such that each state of the table becomes
     /// a distinct leaf in the tree; all registers at a given step are hashed together to
-    /// form a single leaf value.
-    pub fn build_merkle_tree(&self, hash: HashFunction) -> MerkleTree {
-        let mut trace_state = vec![T::ZERO; self.register_count()];
+    /// form a single leaf value. If the trace table has been blinded, value of the randomizer
+    /// is appended to the registers, and the result is hashed together with a salt. Both
+    /// hashing of trace states and building of the tree are distributed across `num_threads`
+    /// threads.
+    pub fn build_merkle_tree(&self, hash: HashFunction, num_threads: usize) -> MerkleTree {
         let mut hashed_states = uninit_vector::<[u8; 32]>(self.domain_size());
-        // TODO: this loop should be parallelized
-        for i in 0..self.domain_size() {
-            for j in 0..trace_state.len() {
-                trace_state[j] = self.registers[j][i];
+        let state_width = self.register_count() + if self.is_blinded() { 1 } else { 0 };
+
+        if self.is_lazily_extended() {
+            // the extended trace is not kept in memory; so, it is computed one chunk at a time,
+            // and states of each chunk are hashed into leaves at the corresponding steps
+            let num_chunks = self.lde_chunk_count();
+            let chunk_size = self.randomized_length();
+            let chunk_twiddles = fft::get_twiddles(T::get_root_of_unity(chunk_size), chunk_size);
+            let mut chunk = vec![uninit_vector(chunk_size); self.register_count()];
+            let batch_size = chunk_size.div_ceil(num_threads);
+            for k in 0..num_chunks {
+                self.fill_lde_chunk(&mut chunk, k, &chunk_twiddles, num_threads);
+                let batches = thread::scope(|s| {
+                    let mut handles = Vec::new();
+                    for first_j in (0..chunk_size).step_by(batch_size) {
+                        let chunk = &chunk;
+                        handles.push(s.spawn(move |_| {
+                            let mut trace_state = vec![T::ZERO; state_width];
+                            let mut batch = Vec::with_capacity(batch_size);
+                            for j in first_j..usize::min(first_j + batch_size, chunk_size) {
+                                for (value, register) in trace_state.iter_mut().zip(chunk.iter()) {
+                                    *value = register[j];
+                                }
+                                let step = k + num_chunks * j;
+                                let mut hashed_state = [0u8; 32];
+                                self.hash_state(&mut trace_state, step, hash, &mut hashed_state);
+                                batch.push(hashed_state);
+                            }
+                            return batch;
+                        }));
+                    }
+                    return handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>();
+                }).unwrap();
+
+                // states of a chunk are hashed into leaves which are not consecutive; so, the
+                // leaves are copied into their positions once all batches of the chunk are hashed
+                for (j, hashed_state) in batches.into_iter().flatten().enumerate() {
+                    hashed_states[k + num_chunks * j] = hashed_state;
+                }
             }
-            hash(as_bytes(&trace_state), &mut hashed_states[i]);
         }
-        return MerkleTree::new(hashed_states, hash);
+        else {
+            let batch_size = self.domain_size().div_ceil(num_threads);
+            thread::scope(|s| {
+                for (batch_idx, batch) in hashed_states.chunks_mut(batch_size).enumerate() {
+                    s.spawn(move |_| {
+                        let mut trace_state = vec![T::ZERO; state_width];
+                        for (j, result) in batch.iter_mut().enumerate() {
+                            let step = batch_idx * batch_size + j;
+                            for (value, register) in trace_state.iter_mut().zip(self.registers.iter()) {
+                                *value = register[step];
+                            }
+                            self.hash_state(&mut trace_state, step, hash, result);
+                        }
+                    });
+                }
+            }).unwrap();
+        }
+
+        return MerkleTree::new_concurrent(hashed_states, hash, num_threads);
     }
 
     /// Evaluates trace polynomials at the specified point `z`; can be called only after
@@ -197,6 +416,7 @@ impl <T> TraceTable<T>
 
         let trace_length = self.unextended_length();
         assert!(self.is_extended(), "trace table has not been extended yet");
+        let randomized_length = self.randomized_length();
         
         let g = T::get_root_of_unity(trace_length);
         let next_z = T::mul(z, g);
@@ -205,8 +425,8 @@ impl <T> TraceTable<T>
         let trace_state1 = self.eval_polys_at(z);
         let trace_state2 = self.eval_polys_at(next_z);
 
-        let mut t1_composition = vec![T::ZERO; trace_length];
-        let mut t2_composition = vec![T::ZERO; trace_length];
+        let mut t1_composition = vec![T::ZERO; randomized_length];
+        let mut t2_composition = vec![T::ZERO; randomized_length];
 
         // combine trace polynomials into 2 composition polynomials T1(x) and T2(x)
         for i in 0..self.polys.len() {
@@ -231,24 +451,113 @@ impl <T> TraceTable<T>
 
         // adjust the degree of the polynomial to match the degree parameter by computing
         // C(x) = T(x) * k_1 + T(x) * x^incremental_degree * k_2
-        let poly_size = utils::get_composition_degree(trace_length).next_power_of_two();
+        let poly_size = utils::get_composition_degree(trace_length, randomized_length).next_power_of_two();
         let mut composition_poly = filled_vector(poly_size, self.domain_size(), T::ZERO);
-        let incremental_degree = utils::get_incremental_trace_degree(trace_length);
+        let incremental_degree = utils::get_incremental_trace_degree(trace_length, randomized_length);
         // this is equivalent to T(x) * k_1
         parallel::mul_acc(
-            &mut composition_poly[..trace_length],
+            &mut composition_poly[..randomized_length],
             &t1_composition,
             cc.t1_degree,
             1);
         // this is equivalent to T(x) * x^incremental_degree * k_2
         parallel::mul_acc(
-            &mut composition_poly[incremental_degree..(incremental_degree + trace_length)],
+            &mut composition_poly[incremental_degree..(incremental_degree + randomized_length)],
             &t1_composition,
             cc.t2_degree,
             1);
         
         return (composition_poly, trace_state1, trace_state2);
     }
+
+    /// Divides out deep point `z` from the randomizer as R'(x) = (R(x) - R(z)) / (x - z), adds
+    /// R'(x) multiplied by a pseudo-random coefficient into the `result`, and returns R(z).
+    /// Since the degree of R'(x) is the same as the degree of the composition polynomial, this
+    /// makes evaluations of the composition polynomial look random. Can be called only if the
+    /// trace table has been blinded.
+    pub fn merge_randomizer_into(&self, result: &mut [T], z: T, cc: &CompositionCoefficients<T>) -> T {
+        let blinding = self.blinding.as_ref().expect("trace table has not been blinded");
+        let mut randomizer_poly = blinding.randomizer_poly.clone();
+
+        // evaluate the randomizer at point z
+        let z_value = polynom::eval(&randomizer_poly, z);
+
+        // compute R'(x) = (R(x) - R(z)) / (x - z)
+        randomizer_poly[0] = T::sub(randomizer_poly[0], z_value);
+        polynom::syn_div_in_place(&mut randomizer_poly, z);
+
+        // add R'(x) * cc into the result
+        parallel::mul_acc(&mut result[..randomizer_poly.len()], &randomizer_poly, cc.randomizer, 1);
+
+        return z_value;
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    /// Hashes `trace_state` at the specified `step` into `result`. The first values of the
+    /// state must be set to register values at the step; if the trace table has been blinded,
+    /// the last value is set to the value of the randomizer at the step, and the state is hashed
+    /// together with a salt.
+    fn hash_state(&self, trace_state: &mut [T], step: usize, hash: HashFunction, result: &mut [u8; 32]) {
+        match &self.blinding {
+            None => hash.digest(as_bytes(trace_state), result),
+            Some(blinding) => {
+                trace_state[trace_state.len() - 1] = blinding.randomizer[step];
+                let salt = utils::get_salt(&blinding.salt_seed, step);
+                utils::hash_salted(hash, as_bytes(trace_state), &salt, result);
+            }
+        }
+    }
+
+    /// Returns values of registers in the specified `range` at the last step of the execution
+    /// trace; if the trace has been extended, the values are computed from trace polynomials
+    /// because the extended trace is evaluated over a coset which does not contain trace steps.
+    fn get_last_step_values(&self, range: Range<usize>) -> Vec<T> {
+        let trace_length = self.unextended_length();
+        if self.is_extended() {
+            let trace_root = T::get_root_of_unity(trace_length);
+            let x = T::exp(trace_root, T::from_usize(trace_length - 1));
+            return range.map(|i| polynom::eval(&self.polys[i], x)).collect();
+        }
+        return range.map(|i| self.registers[i][trace_length - 1]).collect();
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Interpolates the `register` trace into a polynomial, randomizes the polynomial to have
+/// `randomized_length` coefficients (if needed), and evaluates it over a coset of the extended
+/// domain; the evaluations replace the register trace, and the polynomial is returned.
+fn extend_register<T: FiniteField>(register: &mut Vec<T>, inv_twiddles: &[T], twiddles: &[T], randomized_length: usize) -> Vec<T> {
+    interpolate_register(register, inv_twiddles, randomized_length);
+    let poly = register.clone();
+
+    // evaluate the polynomial over a coset of extended domain
+    unsafe { register.set_len(register.capacity()); }
+    polynom::eval_fft_coset(register, &twiddles, T::GENERATOR, true);
+
+    return poly;
+}
+
+/// Interpolates the `register` trace into a polynomial in place, and randomizes the polynomial
+/// to have `randomized_length` coefficients (if needed).
+fn interpolate_register<T: FiniteField>(register: &mut Vec<T>, inv_twiddles: &[T], randomized_length: usize) {
+    let trace_length = register.len();
+
+    // interpolate register trace into a polynomial
+    polynom::interpolate_fft_twiddles(register, &inv_twiddles, true);
+
+    // if needed, randomize the polynomial by adding R(x) * (x^trace_length - 1) to it
+    if randomized_length > trace_length {
+        register.resize(randomized_length, T::ZERO);
+        let r = T::rand_vector(randomized_length - trace_length);
+        for (i, &coefficient) in r.iter().enumerate() {
+            register[i] = T::sub(register[i], coefficient);
+            register[i + trace_length] = T::add(register[i + trace_length], coefficient);
+        }
+    }
 }
 
 // TESTS
@@ -256,8 +565,8 @@ impl <T> TraceTable<T>
 #[cfg(test)]
 mod tests {
 
-    use crate::{ crypto::hash::blake3, processor::opcodes::f128 as opcodes };
-    use crate::stark::{ TraceTable, ProgramInputs, CompositionCoefficients, MAX_CONSTRAINT_DEGREE };
+    use crate::{ crypto::HashFunction, processor::opcodes::f128 as opcodes };
+    use crate::stark::{ TraceTable, ProgramInputs, CompositionCoefficients, Prng, MAX_CONSTRAINT_DEGREE };
     use crate::math::{ F128, FiniteField, polynom, parallel, fft };
 
     const EXT_FACTOR: usize = 32;
@@ -266,17 +575,57 @@ mod tests {
     fn eval_polys_at() {
         let mut trace = build_trace_table();
         let lde_root = F128::get_root_of_unity(trace.domain_size());
-        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()));
+        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()), trace.unextended_length(), 2);
 
-        let g = F128::get_root_of_unity(trace.unextended_length());
+        // extended trace is evaluated over a coset of the LDE domain
+        let x = F128::mul(F128::GENERATOR, lde_root);
 
-        let v1 = trace.eval_polys_at(g);
-        let s1 = trace.get_state(1 * EXT_FACTOR);
+        let v1 = trace.eval_polys_at(x);
+        let s1 = trace.get_state(1);
         assert_eq!(v1, s1.registers());
 
-        let v2 = trace.eval_polys_at(F128::exp(g, 2));
+        let v2 = trace.eval_polys_at(F128::mul(F128::GENERATOR, F128::exp(lde_root, 2 * EXT_FACTOR as u128)));
         let s2 = trace.get_state(2 * EXT_FACTOR);
         assert_eq!(v2, s2.registers());
+
+        // program hash is not affected by the extension
+        assert_eq!(build_trace_table().get_program_hash(), trace.get_program_hash());
+    }
+
+    #[test]
+    fn extend_lazily() {
+        let mut trace = build_trace_table();
+        let lde_root = F128::get_root_of_unity(trace.domain_size());
+        let lde_twiddles = fft::get_twiddles(lde_root, trace.domain_size());
+        trace.extend(&lde_twiddles, trace.unextended_length(), 2);
+
+        let mut lazy_trace = build_trace_table();
+        lazy_trace.extend_lazily(&lde_twiddles, lazy_trace.unextended_length(), 3);
+        assert!(lazy_trace.is_lazily_extended());
+        assert_eq!(trace.domain_size(), lazy_trace.domain_size());
+        assert_eq!(trace.get_program_hash(), lazy_trace.get_program_hash());
+
+        // chunk k contains states at steps k, k + num_chunks, k + 2 * num_chunks etc.
+        let num_chunks = lazy_trace.lde_chunk_count();
+        let chunk_size = lazy_trace.randomized_length();
+        assert_eq!(trace.domain_size(), num_chunks * chunk_size);
+        let chunk_twiddles = fft::get_twiddles(F128::get_root_of_unity(chunk_size), chunk_size);
+        let mut chunk = vec![vec![0; chunk_size]; lazy_trace.register_count()];
+        for k in 0..num_chunks {
+            lazy_trace.fill_lde_chunk(&mut chunk, k, &chunk_twiddles, 2);
+            for j in 0..chunk_size {
+                let state = trace.get_state(k + num_chunks * j);
+                let values = chunk.iter().map(|register| register[j]).collect::<Vec<_>>();
+                assert_eq!(state.registers(), &values[..]);
+            }
+        }
+
+        // register values and Merkle tree are the same as for the fully extended trace
+        let positions = [0, 1, 31, 100, 511];
+        assert_eq!(trace.get_register_values_at(&positions, 1), lazy_trace.get_register_values_at(&positions, 2));
+        let tree = trace.build_merkle_tree(HashFunction::Blake3, 2);
+        let lazy_tree = lazy_trace.build_merkle_tree(HashFunction::Blake3, 3);
+        assert_eq!(tree.root(), lazy_tree.root());
     }
 
     #[test]
@@ -284,12 +633,12 @@ mod tests {
 
         let mut trace = build_trace_table();
         let lde_root = F128::get_root_of_unity(trace.domain_size());
-        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()));
+        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()), trace.unextended_length(), 2);
 
         // compute trace composition polynomial
-        let t_tree = trace.build_merkle_tree(blake3);
-        let z = F128::prng(*t_tree.root());
-        let cc = CompositionCoefficients::new(*t_tree.root());
+        let t_tree = trace.build_merkle_tree(HashFunction::Blake3, 2);
+        let z = Prng::new(*t_tree.root(), HashFunction::Blake3).draw_element();
+        let cc = CompositionCoefficients::new(*t_tree.root(), HashFunction::Blake3);
         let target_degree = (trace.unextended_length() - 2) * MAX_CONSTRAINT_DEGREE - 1;
 
         let g = F128::get_root_of_unity(trace.unextended_length());
@@ -354,6 +703,6 @@ mod tests {
             opcodes::ADD,   opcodes::NOOP, opcodes::NOOP, opcodes::NOOP,
         ];
         let inputs = ProgramInputs::from_public(&[1, 0]);
-        return TraceTable::new(&program, &inputs, EXT_FACTOR);
+        return TraceTable::new(&program, &inputs, EXT_FACTOR).unwrap();
     }
 }
\ No newline at end of file
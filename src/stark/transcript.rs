@@ -0,0 +1,163 @@
+use crate::math::{ FiniteField };
+use crate::crypto::{ HashFunction };
+use crate::utils::{ as_bytes };
+use super::{ ProofOptions, SegmentState, Prng };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Fiat-Shamir transcript used to derive all pseudo-random challenges of the proof. The transcript
+/// is seeded with all public data of a proof (proof options, trace length, program hash, public
+/// inputs, hash of the public tape, and outputs). Afterwards, every commitment made by the prover
+/// is absorbed into the transcript in order, and challenges are drawn from the current state of
+/// the transcript. Prover and verifier must absorb the same data in the same order.
+pub struct Transcript {
+    state   : [u8; 32],
+    counter : u64,
+    hash    : HashFunction,
+}
+
+// TRANSCRIPT IMPLEMENTATION
+// ================================================================================================
+impl Transcript {
+
+    /// Creates a new transcript and absorbs public data of the proof into it.
+    pub fn new<T: FiniteField>(
+        program_hash    : &[u8; 32],
+        inputs          : &[T],
+        tape_hash       : &[T],
+        outputs         : &[T],
+        trace_length    : usize,
+        options         : &ProofOptions) -> Transcript
+    {
+        let mut transcript = Transcript { state: [0u8; 32], counter: 0, hash: options.hash_function() };
+
+        let options_bytes = bincode::serialize(options).unwrap();
+        transcript.absorb(&options_bytes);
+        transcript.absorb(&(trace_length as u64).to_le_bytes());
+        transcript.absorb(program_hash);
+        transcript.absorb(as_bytes(inputs));
+        transcript.absorb(as_bytes(tape_hash));
+        transcript.absorb(as_bytes(outputs));
+
+        return transcript;
+    }
+
+    /// Creates a new transcript and absorbs public data of a segment proof into it; public data
+    /// of a segment consists of its initial and final VM states instead of program hash, inputs,
+    /// hash of the public tape, and outputs.
+    pub fn for_segment<T: FiniteField>(
+        init_state      : &SegmentState<T>,
+        last_state      : &SegmentState<T>,
+        trace_length    : usize,
+        options         : &ProofOptions) -> Transcript
+    {
+        let mut transcript = Transcript { state: [0u8; 32], counter: 0, hash: options.hash_function() };
+
+        let options_bytes = bincode::serialize(options).unwrap();
+        transcript.absorb(&options_bytes);
+        transcript.absorb(&(trace_length as u64).to_le_bytes());
+        for state in [init_state, last_state].iter() {
+            transcript.absorb(as_bytes(state.op_acc()));
+            transcript.absorb(as_bytes(state.tape_acc()));
+            transcript.absorb(as_bytes(state.stack()));
+        }
+
+        return transcript;
+    }
+
+    /// Updates the state of the transcript to be hash(state || data).
+    pub fn absorb(&mut self, data: &[u8]) {
+        let mut input = Vec::with_capacity(self.state.len() + data.len());
+        input.extend_from_slice(&self.state);
+        input.extend_from_slice(data);
+        self.hash.digest(&input, &mut self.state);
+        self.counter = 0;
+    }
+
+    /// Returns a pseudo-random seed derived from the current state of the transcript. Seeds drawn
+    /// successively without absorbing any data in between are different from each other.
+    pub fn draw(&mut self) -> [u8; 32] {
+        self.counter += 1;
+        let mut input = [0u8; 40];
+        input[..32].copy_from_slice(&self.state);
+        input[32..].copy_from_slice(&self.counter.to_le_bytes());
+
+        let mut result = [0u8; 32];
+        self.hash.digest(&input, &mut result);
+        return result;
+    }
+
+    /// Returns a pseudo-random field element; the element is the first element produced by
+    /// `Prng` seeded with the next seed drawn from the transcript.
+    pub fn draw_element<T: FiniteField>(&mut self) -> T {
+        let seed = self.draw();
+        return Prng::new(seed, self.hash).draw_element();
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ F128, ProofOptions, SegmentState };
+    use super::Transcript;
+
+    #[test]
+    fn draw() {
+        let options = ProofOptions::default();
+        let mut transcript1 = Transcript::new::<F128>(&[1u8; 32], &[1, 2], &[0; 4], &[3], 16, &options);
+        let mut transcript2 = Transcript::new::<F128>(&[1u8; 32], &[1, 2], &[0; 4], &[3], 16, &options);
+
+        // the same data results in the same challenges, but successive challenges are different
+        let seed1 = transcript1.draw();
+        assert_eq!(seed1, transcript2.draw());
+        assert_ne!(seed1, transcript1.draw());
+
+        // absorbing different data results in different challenges
+        transcript1.absorb(&[1u8; 32]);
+        transcript2.absorb(&[2u8; 32]);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+    }
+
+    #[test]
+    fn public_data() {
+        let options = ProofOptions::default();
+        let mut transcript1 = Transcript::new::<F128>(&[1u8; 32], &[1, 2], &[0; 4], &[3], 16, &options);
+
+        // different outputs
+        let mut transcript2 = Transcript::new::<F128>(&[1u8; 32], &[1, 2], &[0; 4], &[4], 16, &options);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+
+        // different inputs
+        let mut transcript2 = Transcript::new::<F128>(&[1u8; 32], &[1, 3], &[0; 4], &[3], 16, &options);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+
+        // different program hash
+        let mut transcript2 = Transcript::new::<F128>(&[2u8; 32], &[1, 2], &[0; 4], &[3], 16, &options);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+
+        // different trace length
+        let mut transcript2 = Transcript::new::<F128>(&[1u8; 32], &[1, 2], &[0; 4], &[3], 32, &options);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+    }
+
+    #[test]
+    fn segment_data() {
+        let options = ProofOptions::default();
+        let init_state = SegmentState::<F128>::from_inputs(&[1, 2]);
+        let last_state = SegmentState::new(&[1, 2, 3, 4], &[5, 6, 7, 8], &[3, 4]);
+        let mut transcript1 = Transcript::for_segment(&init_state, &last_state, 16, &options);
+
+        // different initial state
+        let other_state = SegmentState::from_inputs(&[1, 3]);
+        let mut transcript2 = Transcript::for_segment(&other_state, &last_state, 16, &options);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+
+        // different capacity of the program hash accumulator in the last state
+        let other_state = SegmentState::new(&[1, 2, 3, 5], &[5, 6, 7, 8], &[3, 4]);
+        let mut transcript2 = Transcript::for_segment(&init_state, &other_state, 16, &options);
+        assert_ne!(transcript1.draw(), transcript2.draw());
+    }
+}
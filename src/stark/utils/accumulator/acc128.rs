@@ -59,7 +59,7 @@ impl Accumulator for F128 {
         state.copy_from_slice(&result);
     }
 
-    fn get_extended_constants(extension_factor: usize) -> (Vec<Vec<F128>>, Vec<Vec<F128>>) {
+    fn get_extended_constants(extension_factor: usize, domain_offset: F128) -> (Vec<Vec<F128>>, Vec<Vec<F128>>) {
         let root = F128::get_root_of_unity(NUM_ROUNDS);
         let inv_twiddles = fft::get_inv_twiddles(root, NUM_ROUNDS);
     
@@ -78,7 +78,7 @@ impl Accumulator for F128 {
             polys.push(extended_constant.clone());
     
             unsafe { extended_constant.set_len(extended_constant.capacity()); }
-            polynom::eval_fft_twiddles(&mut extended_constant, &twiddles, true);
+            polynom::eval_fft_coset(&mut extended_constant, &twiddles, domain_offset, true);
     
             evaluations.push(extended_constant);
         }
@@ -59,7 +59,7 @@ impl Accumulator for F64 {
         state.copy_from_slice(&result);
     }
 
-    fn get_extended_constants(extension_factor: usize) -> (Vec<Vec<F64>>, Vec<Vec<F64>>) {
+    fn get_extended_constants(extension_factor: usize, domain_offset: F64) -> (Vec<Vec<F64>>, Vec<Vec<F64>>) {
         let root = F64::get_root_of_unity(NUM_ROUNDS);
         let inv_twiddles = fft::get_inv_twiddles(root, NUM_ROUNDS);
     
@@ -78,7 +78,7 @@ impl Accumulator for F64 {
             polys.push(extended_constant.clone());
     
             unsafe { extended_constant.set_len(extended_constant.capacity()); }
-            polynom::eval_fft_twiddles(&mut extended_constant, &twiddles, true);
+            polynom::eval_fft_coset(&mut extended_constant, &twiddles, domain_offset, true);
     
             evaluations.push(extended_constant);
         }
@@ -58,6 +58,26 @@ pub trait Accumulator: FiniteField {
         Self::apply_mds(state);
     }
 
+    /// Absorbs a single `value` into the `state` using one round of the modified Rescue function.
+    /// Unlike `apply_round()`, this function always uses constants of the first round, and the
+    /// value is simply added to the first element of the state between the two halves of the round.
+    /// This makes the result independent of the steps at which the values were absorbed.
+    fn absorb(state: &mut [Self], value: Self) {
+
+        // apply first half of Rescue round
+        Self::add_constants(state, 0, 0);
+        Self::apply_sbox(state);
+        Self::apply_mds(state);
+
+        // inject value into the state
+        state[0] = Self::add(state[0], value);
+
+        // apply second half of Rescue round
+        Self::add_constants(state, 0, Self::STATE_WIDTH);
+        Self::apply_inv_sbox(state);
+        Self::apply_mds(state);
+    }
+
     fn add_constants(state: &mut[Self], idx: usize, offset: usize);
 
     fn apply_sbox(state: &mut [Self]);
@@ -66,5 +86,5 @@ pub trait Accumulator: FiniteField {
     fn apply_mds(state: &mut[Self]);
     fn apply_inv_mds(state: &mut[Self]);
 
-    fn get_extended_constants(extension_factor: usize) -> (Vec<Vec<Self>>, Vec<Vec<Self>>);
+    fn get_extended_constants(extension_factor: usize, domain_offset: Self) -> (Vec<Vec<Self>>, Vec<Vec<Self>>);
 }
\ No newline at end of file
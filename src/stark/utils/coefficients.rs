@@ -1,18 +1,21 @@
 use crate::math::{ FiniteField };
-use crate::stark::{ MAX_REGISTER_COUNT, MAX_PUBLIC_INPUTS, MAX_OUTPUTS, MAX_TRANSITION_CONSTRAINTS };
+use crate::crypto::{ HashFunction };
+use crate::stark::{ MAX_REGISTER_COUNT, MAX_STACK_DEPTH, MAX_TRANSITION_CONSTRAINTS };
+use super::{ Prng };
 
 // CONSTANTS
 // ================================================================================================
-const DECODER_WIDTH: usize = 10;
-const NUM_CONSTRAINTS: usize = MAX_PUBLIC_INPUTS + MAX_OUTPUTS + MAX_TRANSITION_CONSTRAINTS + 2 * DECODER_WIDTH;
+const DECODER_WIDTH: usize = 14;
+// boundary constraints of a segment may bind all stack registers at the first and the last steps
+const NUM_CONSTRAINTS: usize = 2 * (DECODER_WIDTH + MAX_STACK_DEPTH) + MAX_TRANSITION_CONSTRAINTS;
 
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct ConstraintCoefficients<T>
     where T: FiniteField
 {
-    pub i_boundary  : [T; 2 * (DECODER_WIDTH + MAX_PUBLIC_INPUTS) ],
-    pub f_boundary  : [T; 2 * (DECODER_WIDTH + MAX_OUTPUTS)],
+    pub i_boundary  : [T; 2 * (DECODER_WIDTH + MAX_STACK_DEPTH) ],
+    pub f_boundary  : [T; 2 * (DECODER_WIDTH + MAX_STACK_DEPTH)],
     pub transition  : [T; 2 * MAX_TRANSITION_CONSTRAINTS],
 }
 
@@ -24,6 +27,7 @@ pub struct CompositionCoefficients<T>
     pub t1_degree   : T,
     pub t2_degree   : T,
     pub constraints : T,
+    pub randomizer  : T,
 }
 
 // IMPLEMENTATIONS
@@ -31,19 +35,19 @@ pub struct CompositionCoefficients<T>
 impl <T> ConstraintCoefficients<T>
     where T: FiniteField
 {
-    pub fn new(seed: [u8; 32]) -> ConstraintCoefficients<T> {
+    pub fn new(seed: [u8; 32], hash: HashFunction) -> ConstraintCoefficients<T> {
 
         // generate a pseudo-random list of coefficients
-        let coefficients = T::prng_vector(seed, 2 * NUM_CONSTRAINTS);
+        let coefficients = Prng::new(seed, hash).draw_elements(2 * NUM_CONSTRAINTS);
 
         // copy coefficients to their respective segments
-        let end_index = 2 * (DECODER_WIDTH + MAX_PUBLIC_INPUTS);
-        let mut i_boundary = [T::ZERO; 2 * (DECODER_WIDTH + MAX_PUBLIC_INPUTS)];
+        let end_index = 2 * (DECODER_WIDTH + MAX_STACK_DEPTH);
+        let mut i_boundary = [T::ZERO; 2 * (DECODER_WIDTH + MAX_STACK_DEPTH)];
         i_boundary.copy_from_slice(&coefficients[..end_index]);
 
         let start_index = end_index;
-        let end_index = start_index + 2 * (DECODER_WIDTH + MAX_OUTPUTS);
-        let mut f_boundary = [T::ZERO; 2 * (DECODER_WIDTH + MAX_OUTPUTS)];
+        let end_index = start_index + 2 * (DECODER_WIDTH + MAX_STACK_DEPTH);
+        let mut f_boundary = [T::ZERO; 2 * (DECODER_WIDTH + MAX_STACK_DEPTH)];
         f_boundary.copy_from_slice(&coefficients[start_index..end_index]);
 
         let start_index = end_index;
@@ -57,9 +61,9 @@ impl <T> ConstraintCoefficients<T>
 impl <T> CompositionCoefficients<T>
     where T: FiniteField
 {
-    pub fn new(seed: [u8; 32]) -> CompositionCoefficients<T> {
+    pub fn new(seed: [u8; 32], hash: HashFunction) -> CompositionCoefficients<T> {
         // generate a pseudo-random list of coefficients
-        let coefficients = T::prng_vector(seed, 1 + 4 * MAX_REGISTER_COUNT + 3);
+        let coefficients = Prng::new(seed, hash).draw_elements(1 + 4 * MAX_REGISTER_COUNT + 4);
 
         // skip the first value because it is used up by deep point z
         let start_index = 1;
@@ -78,7 +82,8 @@ impl <T> CompositionCoefficients<T>
         let t1_degree = coefficients[index];
         let t2_degree = coefficients[index + 1];
         let constraints = coefficients[index + 2];
+        let randomizer = coefficients[index + 3];
 
-        return CompositionCoefficients { trace1, trace2, t1_degree, t2_degree, constraints };
+        return CompositionCoefficients { trace1, trace2, t1_degree, t2_degree, constraints, randomizer };
     }
 }
\ No newline at end of file
@@ -61,7 +61,7 @@ impl Hasher for F128 {
         state.copy_from_slice(&result);
     }
 
-    fn get_extended_constants(extension_factor: usize) -> (Vec<Vec<F128>>, Vec<Vec<F128>>) {
+    fn get_extended_constants(extension_factor: usize, domain_offset: F128) -> (Vec<Vec<F128>>, Vec<Vec<F128>>) {
         let root = F128::get_root_of_unity(CYCLE_LENGTH);
         let inv_twiddles = fft::get_inv_twiddles(root, CYCLE_LENGTH);
     
@@ -80,7 +80,7 @@ impl Hasher for F128 {
             polys.push(extended_constant.clone());
     
             unsafe { extended_constant.set_len(extended_constant.capacity()); }
-            polynom::eval_fft_twiddles(&mut extended_constant, &twiddles, true);
+            polynom::eval_fft_coset(&mut extended_constant, &twiddles, domain_offset, true);
     
             evaluations.push(extended_constant);
         }
@@ -50,5 +50,5 @@ pub trait Hasher: FiniteField {
     fn apply_mds(state: &mut[Self]);
     fn apply_inv_mds(state: &mut[Self]);
 
-    fn get_extended_constants(extension_factor: usize) -> (Vec<Vec<Self>>, Vec<Vec<Self>>);
+    fn get_extended_constants(extension_factor: usize, domain_offset: Self) -> (Vec<Vec<Self>>, Vec<Vec<Self>>);
 }
\ No newline at end of file
@@ -1,7 +1,6 @@
 use std::mem;
-use rand::prelude::*;
-use rand::distributions::Uniform;
 use crate::math::{ FiniteField };
+use crate::crypto::{ HashFunction, hash };
 use super::{ ProofOptions, MAX_CONSTRAINT_DEGREE };
 
 // RE-EXPORTS
@@ -18,27 +17,31 @@ pub use coefficients::{ ConstraintCoefficients, CompositionCoefficients };
 mod proof_of_work;
 pub use proof_of_work::{ find_pow_nonce, verify_pow_nonce };
 
-pub fn get_composition_degree(trace_length: usize) -> usize {
-    return (MAX_CONSTRAINT_DEGREE - 1) * trace_length - 1;
+mod prng;
+pub use prng::{ Prng };
+
+/// Returns degree of the composition polynomial for an execution trace of `trace_length` steps
+/// with trace polynomials of `randomized_length` coefficients; when trace polynomials are not
+/// randomized, this is the same as (MAX_CONSTRAINT_DEGREE - 1) * trace_length - 1.
+pub fn get_composition_degree(trace_length: usize, randomized_length: usize) -> usize {
+    return MAX_CONSTRAINT_DEGREE * randomized_length - trace_length - 1;
 }
 
 // PUBLIC FUNCTIONS
 // ================================================================================================
 
-pub fn get_incremental_trace_degree(trace_length: usize) -> usize {
-    let composition_degree = get_composition_degree(trace_length);
-    return composition_degree - (trace_length - 2);
+pub fn get_incremental_trace_degree(trace_length: usize, randomized_length: usize) -> usize {
+    let composition_degree = get_composition_degree(trace_length, randomized_length);
+    return composition_degree - (randomized_length - 2);
 }
 
 pub fn compute_query_positions(seed: &[u8; 32], domain_size: usize, options: &ProofOptions) -> Vec<usize> {
-    let range = Uniform::from(0..domain_size);
-    let mut index_iter = StdRng::from_seed(*seed).sample_iter(range);
+    let mut prng = Prng::new(*seed, options.hash_function());
     let num_queries = options.num_queries();
 
     let mut result = Vec::new();
     for _ in 0..1000 {
-        let value = index_iter.next().unwrap();
-        if value % options.extension_factor() == 0 { continue; }
+        let value = prng.draw_index(domain_size);
         if result.contains(&value) { continue; }
         result.push(value);
         if result.len() >= num_queries { break; }
@@ -60,4 +63,25 @@ pub fn map_trace_to_constraint_positions<T: FiniteField>(positions: &[usize]) ->
         if !result.contains(&cp) { result.push(cp); }
     }
     return result;
-}
\ No newline at end of file
+}
+
+/// Returns a salt for the leaf at the specified `index` of a Merkle tree; all salts of a tree are
+/// derived from a secret `seed` so that they don't need to be kept in memory.
+pub fn get_salt(seed: &[u8; 32], index: usize) -> [u8; 32] {
+    let mut buf = [0u8; 40];
+    buf[..32].copy_from_slice(seed);
+    buf[32..].copy_from_slice(&(index as u64).to_le_bytes());
+    let mut result = [0u8; 32];
+    hash::blake3(&buf, &mut result);
+    return result;
+}
+
+/// Hashes `values` together with the `salt` into `result`; in zero-knowledge mode, leaves of trace
+/// and constraint Merkle trees are computed this way so that authentication paths reveal nothing
+/// about the values of leaves which were not queried.
+pub fn hash_salted(hash: HashFunction, values: &[u8], salt: &[u8; 32], result: &mut [u8]) {
+    let mut buf = Vec::with_capacity(values.len() + salt.len());
+    buf.extend_from_slice(values);
+    buf.extend_from_slice(salt);
+    hash.digest(&buf, result);
+}
@@ -0,0 +1,122 @@
+use std::mem;
+use crate::math::{ FiniteField };
+use crate::crypto::{ HashFunction };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Pseudo-random generator used to derive all challenges of a proof (constraint and composition
+/// coefficients, DEEP point, FRI coordinates, and query positions) from a seed. The generator is
+/// defined entirely in terms of the hash function of the proof: the i-th block of its output is
+/// hash(seed || i), where i is encoded as an 8-byte little-endian integer and starts at 0. Every
+/// value is drawn from a new block:
+///
+/// * a field element is read from the first bytes of the block as a little-endian integer; if the
+///   result is not smaller than the field modulus, the block is discarded and the next one is used;
+/// * an index in the range [0, n), where n is a power of 2, is read from the first 8 bytes of the
+///   block as a little-endian integer with all but the lower log2(n) bits cleared.
+pub struct Prng {
+    seed    : [u8; 32],
+    counter : u64,
+    hash    : HashFunction,
+}
+
+// PRNG IMPLEMENTATION
+// ================================================================================================
+impl Prng {
+
+    pub fn new(seed: [u8; 32], hash: HashFunction) -> Prng {
+        return Prng { seed, counter: 0, hash };
+    }
+
+    /// Returns the next pseudo-random field element.
+    pub fn draw_element<T: FiniteField>(&mut self) -> T {
+        let element_size = mem::size_of::<T>();
+        loop {
+            let block = self.next_block();
+            let value = T::from_bytes(&block[..element_size]);
+            if value < T::MODULUS {
+                return value;
+            }
+        }
+    }
+
+    /// Returns a vector of `length` pseudo-random field elements.
+    pub fn draw_elements<T: FiniteField>(&mut self, length: usize) -> Vec<T> {
+        return (0..length).map(|_| self.draw_element()).collect();
+    }
+
+    /// Returns the next pseudo-random index in the range [0, `bound`); `bound` must be a power of 2.
+    pub fn draw_index(&mut self, bound: usize) -> usize {
+        assert!(bound.is_power_of_two(), "bound must be a power of 2");
+        let block = self.next_block();
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&block[..8]);
+        return (u64::from_le_bytes(value) & (bound as u64 - 1)) as usize;
+    }
+
+    fn next_block(&mut self) -> [u8; 32] {
+        let mut input = [0u8; 40];
+        input[..32].copy_from_slice(&self.seed);
+        input[32..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+
+        let mut result = [0u8; 32];
+        self.hash.digest(&input, &mut result);
+        return result;
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ F64, F128, FiniteField, crypto::{ HashFunction, hash } };
+    use super::Prng;
+
+    #[test]
+    fn draw_element() {
+        // elements are read from hash(seed || counter)
+        let seed = [7u8; 32];
+        let mut input = [0u8; 40];
+        input[..32].copy_from_slice(&seed);
+        let mut block = [0u8; 32];
+        hash::blake3(&input, &mut block);
+
+        let mut prng = Prng::new(seed, HashFunction::Blake3);
+        assert_eq!(F128::from_bytes(&block[..16]), prng.draw_element::<F128>());
+
+        input[32] = 1;
+        hash::blake3(&input, &mut block);
+        assert_eq!(F64::from_bytes(&block[..8]), prng.draw_element::<F64>());
+
+        // the same seed results in the same elements, and the elements are valid
+        let elements1 = Prng::new(seed, HashFunction::Sha3).draw_elements::<F128>(64);
+        let elements2 = Prng::new(seed, HashFunction::Sha3).draw_elements::<F128>(64);
+        assert_eq!(elements1, elements2);
+        assert!(elements1.iter().all(|&e| e < F128::MODULUS));
+
+        // different hash functions result in different elements
+        let elements3 = Prng::new(seed, HashFunction::Blake3).draw_elements::<F128>(64);
+        assert_ne!(elements1, elements3);
+    }
+
+    #[test]
+    fn draw_index() {
+        let seed = [7u8; 32];
+        let mut input = [0u8; 40];
+        input[..32].copy_from_slice(&seed);
+        let mut block = [0u8; 32];
+        hash::sha3(&input, &mut block);
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&block[..8]);
+
+        let mut prng = Prng::new(seed, HashFunction::Sha3);
+        assert_eq!((u64::from_le_bytes(value) % 1024) as usize, prng.draw_index(1024));
+
+        for _ in 0..100 {
+            assert!(prng.draw_index(16) < 16);
+        }
+    }
+}
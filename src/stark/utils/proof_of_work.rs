@@ -1,57 +1,99 @@
-use std::slice;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use crossbeam_utils::thread;
+use crate::crypto::{ HashFunction };
 use crate::stark::{ ProofOptions };
 
+/// Finds the smallest nonce such that hash(seed || nonce) has at least `grinding_factor` trailing
+/// zeros, and returns the hash together with the nonce. The nonce space is partitioned across
+/// all prover threads: thread i tries nonces i + 1, i + 1 + num_threads, etc. A thread stops as
+/// soon as its nonces exceed the smallest valid nonce found so far; so, the result does not
+/// depend on the number of threads.
 pub fn find_pow_nonce(seed: [u8; 32], options: &ProofOptions) -> ([u8; 32], u64) {
 
     let hash = options.hash_function();
     let grinding_factor = options.grinding_factor();
+    let num_threads = options.num_threads() as u64;
 
-    // copy seed into inputs and also get a reference to inputs as to an array of bytes
-    let mut inputs = [0u64; 8];
-    let input_bytes: &mut [u8] = unsafe {
-        slice::from_raw_parts_mut(inputs.as_ptr() as *mut u8, inputs.len() * 8)
-    };
-    input_bytes[0..32].copy_from_slice(&seed);
-
-    // create buffer to hold outputs and also get a reference to it as to any array of bytes
-    let output = [0u64; 4];
-    let mut output_bytes = unsafe {
-        slice::from_raw_parts_mut(output.as_ptr() as *mut u8, output.len() * 8)
-    };
-
-    loop {
-        inputs[4] += 1;
-        hash(&input_bytes, &mut output_bytes);
-        if output[0].trailing_zeros() >= grinding_factor { break; }
-    }
-
-    let mut result = [0; 32];
-    result.copy_from_slice(output_bytes);
+    let best_nonce = AtomicU64::new(u64::MAX);
+    thread::scope(|s| {
+        for i in 0..num_threads {
+            let best_nonce = &best_nonce;
+            s.spawn(move |_| {
+                let mut nonce = i + 1;
+                while nonce < best_nonce.load(Ordering::Relaxed) {
+                    let output = hash_nonce(&seed, nonce, hash);
+                    if get_leading_u64(&output).trailing_zeros() >= grinding_factor {
+                        best_nonce.fetch_min(nonce, Ordering::Relaxed);
+                        break;
+                    }
+                    nonce = nonce.saturating_add(num_threads);
+                }
+            });
+        }
+    }).unwrap();
 
-    return (result, inputs[4]);
+    let nonce = best_nonce.into_inner();
+    return (hash_nonce(&seed, nonce, hash), nonce);
 }
 
 pub fn verify_pow_nonce(seed: [u8; 32], nonce: u64, options: &ProofOptions) -> Result<[u8; 32], String> {
 
-    let hash = options.hash_function();
+    let output = hash_nonce(&seed, nonce, options.hash_function());
+    if get_leading_u64(&output).trailing_zeros() < options.grinding_factor() {
+        return Err(String::from("seed proof-of-work verification failed"));
+    }
 
+    return Ok(output);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Computes hash(seed || nonce || padding), where nonce is encoded as an 8-byte little-endian
+/// integer, and the input is padded with zeros to 64 bytes so that it can be hashed with a single
+/// invocation of the hash function.
+fn hash_nonce(seed: &[u8; 32], nonce: u64, hash: HashFunction) -> [u8; 32] {
     // append nonce to seed for hashing
     let mut input_bytes = [0; 64];
-    input_bytes[0..32].copy_from_slice(&seed);
+    input_bytes[0..32].copy_from_slice(seed);
     input_bytes[32..40].copy_from_slice(&nonce.to_le_bytes());
 
-    let output = [0u64; 4];
-    let mut output_bytes: &mut [u8] = unsafe {
-        slice::from_raw_parts_mut(output.as_ptr() as *mut u8, output.len() * 8)
-    };
+    let mut result = [0; 32];
+    hash.merge(&input_bytes, &mut result);
+    return result;
+}
 
-    hash(&input_bytes, &mut output_bytes);
-    if output[0].trailing_zeros() < options.grinding_factor() {
-        return Err(String::from("seed proof-of-work verification failed"));
-    }
+/// Interprets the first 8 bytes of the `output` as a little-endian integer.
+fn get_leading_u64(output: &[u8; 32]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&output[..8]);
+    return u64::from_le_bytes(bytes);
+}
 
-    let mut result = [0; 32];
-    result.copy_from_slice(output_bytes);
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ ProofOptions, crypto::HashFunction };
 
-    return Ok(result);
-}
\ No newline at end of file
+    #[test]
+    fn find_verify_pow_nonce() {
+        let seed = [3u8; 32];
+        let options = ProofOptions::new(32, 50, 8, HashFunction::Blake3);
+
+        let (result, nonce) = super::find_pow_nonce(seed, &options.clone().with_num_threads(1));
+        assert_eq!(Ok(result), super::verify_pow_nonce(seed, nonce, &options));
+
+        // the nonce is the smallest valid nonce
+        for i in 1..nonce {
+            assert!(super::verify_pow_nonce(seed, i, &options).is_err());
+        }
+
+        // the result does not depend on the number of threads
+        for &num_threads in [2, 3, 8].iter() {
+            let options = options.clone().with_num_threads(num_threads);
+            assert_eq!((result, nonce), super::find_pow_nonce(seed, &options));
+        }
+    }
+}
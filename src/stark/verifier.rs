@@ -1,26 +1,221 @@
-use std::mem;
-use crate::{ math::{ FiniteField }, crypto::{ MerkleTree } };
-use super::{ StarkProof, TraceState, ConstraintEvaluator, CompositionCoefficients, Accumulator, Hasher, fri, utils };
+use std::{ cmp, mem, collections::HashMap, thread::available_parallelism };
+use crossbeam_utils::thread;
+use crate::{ math::{ FiniteField }, crypto::{ MerkleTree }, utils::{ as_bytes } };
+use super::{ StarkProof, SegmentState, SegmentProof, VerifierPolicy, TraceState, ConstraintEvaluator, CompositionCoefficients, Transcript, Prng, Accumulator, Hasher, fri, utils };
+use super::constraints::{ hash_public_tape, parse_program_hash };
+use super::{ MIN_STACK_DEPTH };
 
-// VERIFIER FUNCTION
+// TYPES AND INTERFACES
 // ================================================================================================
 
-pub fn verify<T>(program_hash: &[u8; 32], inputs: &[T], outputs: &[T], proof: &StarkProof<T>) -> Result<bool, String>
+/// Values which depend only on the shape of a proof: trace length, domain size, randomized trace
+/// length, and stack depth. These are computed once and then shared by all proofs of the same shape.
+struct VerifierContext<T>
+    where T: FiniteField + Accumulator + Hasher
+{
+    evaluator           : ConstraintEvaluator<T>,
+    lde_root            : T,
+    trace_root          : T,
+    x_at_last_step      : T,
+    incremental_degree  : T,
+    max_degree          : usize,
+}
+
+/// Program hash, public inputs, public tape, outputs, and a proof of a single item of a batch
+/// of proofs verified together.
+pub type BatchItem<'a, T> = (&'a [u8; 32], &'a [T], &'a [T], &'a [T], &'a StarkProof<T>);
+
+/// Trace length, domain size, randomized trace length, and stack depth of a proof.
+type ProofShape = (usize, usize, usize, usize);
+
+// VERIFIER FUNCTIONS
+// ================================================================================================
+
+pub fn verify<T>(program_hash: &[u8; 32], inputs: &[T], tape: &[T], outputs: &[T], proof: &StarkProof<T>, policy: &VerifierPolicy) -> Result<bool, String>
+    where T: FiniteField + Accumulator + Hasher
+{
+    let tape_hash = hash_public_tape(tape);
+    let transcript = Transcript::new(program_hash, inputs, &tape_hash, outputs, proof.trace_length(), proof.options());
+
+    // boundary constraints bind the first step of the trace to the inputs, and the last step
+    // of the trace to the program hash, hash of the public tape, and the outputs
+    let init_state = SegmentState::from_inputs(inputs);
+    let last_state = SegmentState::from_outputs(&parse_program_hash(program_hash), &tape_hash, outputs);
+    return verify_with(proof, transcript, &init_state, &last_state, policy);
+}
+
+/// Verifies that a program with the specified `program_hash` was executed in segments with the
+/// provided `inputs` and `tape`, and that the result is equal to the `outputs`. The first segment
+/// must start in the state defined by the `inputs`, every other segment must start in the state
+/// in which the previous segment ended, and the state after the last segment must contain the
+/// program hash, hash of the public tape, and the outputs. Each segment proof must satisfy the
+/// specified `policy`.
+pub fn verify_chain<T>(program_hash: &[u8; 32], inputs: &[T], tape: &[T], outputs: &[T], segments: &[SegmentProof<T>], policy: &VerifierPolicy) -> Result<bool, String>
+    where T: FiniteField + Accumulator + Hasher
+{
+    if segments.is_empty() {
+        return Err(String::from("expected at least one segment proof, but received none"));
+    }
+
+    // segment states must hold values of all user stack registers of a segment; otherwise, values
+    // of registers which are not bound by boundary constraints could change between segments. So,
+    // the inputs are padded with zeros to the stack depth of the first segment, and all other
+    // states must match stack depths of the segments on both sides of the state
+    let mut init_stack = inputs.to_vec();
+    init_stack.resize(cmp::max(inputs.len(), get_user_stack_depth(segments[0].proof())), T::ZERO);
+
+    // verify proofs of all segments chaining the state of each segment into the next one
+    let mut init_state = SegmentState::from_inputs(&init_stack);
+    for (i, segment) in segments.iter().enumerate() {
+        let last_state = segment.state();
+        let proof = segment.proof();
+        let stack_depth = get_user_stack_depth(proof);
+        if init_state.stack().len() != stack_depth || last_state.stack().len() != stack_depth {
+            return Err(format!("verification of segment {} failed: expected {} stack values in segment states, but received {} and {}",
+                i, stack_depth, init_state.stack().len(), last_state.stack().len()));
+        }
+        let transcript = Transcript::for_segment(&init_state, last_state, proof.trace_length(), proof.options());
+        if let Err(msg) = verify_with(proof, transcript, &init_state, last_state, policy) {
+            return Err(format!("verification of segment {} failed: {}", i, msg));
+        }
+        init_state = last_state.clone();
+    }
+
+    // make sure the state after the last segment matches public data of the program
+    let last_state = &init_state;
+    if last_state.program_hash() != &parse_program_hash::<T>(program_hash)[..] {
+        return Err(String::from("program hash of the last segment does not match the program hash"));
+    }
+    if last_state.tape_acc() != &hash_public_tape(tape)[..] {
+        return Err(String::from("public tape hash of the last segment does not match the public tape"));
+    }
+    if outputs.len() > last_state.stack().len() || &last_state.stack()[..outputs.len()] != outputs {
+        return Err(String::from("stack of the last segment does not match the outputs"));
+    }
+
+    return Ok(true);
+}
+
+/// Verifies a batch of proofs; each item of the batch consists of a program hash, public inputs,
+/// public tape, outputs, and a proof, and is verified in the same way as by the `verify()`
+/// function. Values which depend only on the shape of a proof are computed once for all proofs of
+/// the same shape, and proofs are verified in parallel using `num_threads` threads (0 means that
+/// all available CPU cores are used). Results are returned in the same order as the items.
+pub fn verify_many<T>(items: &[BatchItem<T>], policy: &VerifierPolicy, num_threads: usize) -> Vec<Result<bool, String>>
+    where T: FiniteField + Accumulator + Hasher
+{
+    // determine boundary states of all proofs and reject proofs which cannot be verified against
+    // these states before any other verification work is done
+    let mut checks = Vec::with_capacity(items.len());
+    for &(program_hash, inputs, tape, outputs, proof) in items.iter() {
+        let tape_hash = hash_public_tape(tape);
+        let init_state = SegmentState::from_inputs(inputs);
+        let last_state = SegmentState::from_outputs(&parse_program_hash(program_hash), &tape_hash, outputs);
+        let check = check_proof(proof, &init_state, &last_state, policy);
+        checks.push((tape_hash, init_state, last_state, check));
+    }
+
+    // build a verifier context for each distinct shape of the proofs which passed the checks
+    let mut contexts = HashMap::new();
+    for (&(_, _, _, _, proof), (_, _, _, check)) in items.iter().zip(checks.iter()) {
+        if check.is_ok() {
+            contexts.entry(get_proof_shape(proof)).or_insert_with(|| VerifierContext::new(proof));
+        }
+    }
+
+    // split the batch into contiguous chunks and verify each chunk in a separate thread
+    let num_threads = match num_threads {
+        0 => available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n
+    };
+    let chunk_size = cmp::max(1, items.len().div_ceil(num_threads));
+    let mut results: Vec<Result<bool, String>> = vec![Ok(false); items.len()];
+
+    thread::scope(|s| {
+        let chunks = items.chunks(chunk_size).zip(checks.chunks(chunk_size));
+        for ((items, checks), results) in chunks.zip(results.chunks_mut(chunk_size)) {
+            let contexts = &contexts;
+            s.spawn(move |_| {
+                for (i, &(program_hash, inputs, _, outputs, proof)) in items.iter().enumerate() {
+                    let (tape_hash, init_state, last_state, check) = &checks[i];
+                    results[i] = match check {
+                        Ok(_) => {
+                            let context = &contexts[&get_proof_shape(proof)];
+                            let transcript = Transcript::new(program_hash, inputs, tape_hash, outputs, proof.trace_length(), proof.options());
+                            verify_in_context(proof, transcript, init_state, last_state, context)
+                        },
+                        Err(msg) => Err(msg.clone())
+                    };
+                }
+            });
+        }
+    }).unwrap();
+
+    return results;
+}
+
+/// Verifies the `proof` using the `transcript` initialized with public data of the proof;
+/// boundary constraints bind the first and the last steps of the trace to the specified states.
+fn verify_with<T>(proof: &StarkProof<T>, transcript: Transcript, init_state: &SegmentState<T>, last_state: &SegmentState<T>, policy: &VerifierPolicy) -> Result<bool, String>
+    where T: FiniteField + Accumulator + Hasher
+{
+    if let Err(msg) = check_proof(proof, init_state, last_state, policy) {
+        return Err(msg);
+    }
+    let context = VerifierContext::new(proof);
+    return verify_in_context(proof, transcript, init_state, last_state, &context);
+}
+
+/// Makes sure the `proof` was generated with acceptable options, and that its stack is deep
+/// enough to hold the stacks of `init_state` and `last_state`.
+fn check_proof<T>(proof: &StarkProof<T>, init_state: &SegmentState<T>, last_state: &SegmentState<T>, policy: &VerifierPolicy) -> Result<(), String>
+    where T: FiniteField + Accumulator + Hasher
+{
+    if let Err(msg) = policy.check(proof.options()) {
+        return Err(format!("proof options are not acceptable: {}", msg));
+    }
+
+    // the stack of the proof must be deep enough to hold the values bound by boundary constraints
+    let user_stack_depth = get_user_stack_depth(proof);
+    if init_state.stack().len() > user_stack_depth || last_state.stack().len() > user_stack_depth {
+        return Err(format!("stack depth of the proof is too small: expected at least {}, but was {}",
+            cmp::max(init_state.stack().len(), last_state.stack().len()), user_stack_depth));
+    }
+
+    return Ok(());
+}
+
+/// Verifies the `proof` in the same way as `verify_with()` function, but assumes that the proof
+/// has already been checked against the verifier policy, and uses values from the `context`
+/// instead of computing them from the proof.
+fn verify_in_context<T>(proof: &StarkProof<T>, mut transcript: Transcript, init_state: &SegmentState<T>, last_state: &SegmentState<T>, context: &VerifierContext<T>) -> Result<bool, String>
     where T: FiniteField + Accumulator + Hasher
 {
     let options = proof.options();
     let hash_fn = options.hash_function();
 
-    // 1 ----- Verify proof of work and determine query positions ---------------------------------
-    let degree_proof = proof.degree_proof();
-    let mut fri_roots: Vec<u8> = Vec::new();
-    for layer in degree_proof.layers.iter() {
-        layer.root.iter().for_each(|&v| fri_roots.push(v));
+    // 1 ----- Re-create the transcript and draw all pseudo-random challenges --------------------
+
+    // coefficients for constraint combination are derived after the trace commitment
+    transcript.absorb(as_bytes(proof.trace_cap()));
+    let constraint_seed = transcript.draw();
+
+    // DEEP point z and composition coefficients are derived after the constraint commitment
+    transcript.absorb(as_bytes(proof.constraint_cap()));
+    let composition_seed = transcript.draw();
+
+    // x coordinates for FRI layers are derived after the DEEP values and each FRI layer commitment
+    let deep_values = proof.deep_values();
+    transcript.absorb(as_bytes(&deep_values.trace_at_z1));
+    transcript.absorb(as_bytes(&deep_values.trace_at_z2));
+    if let Some(blinding) = proof.blinding() {
+        transcript.absorb(as_bytes(&[blinding.randomizer_at_z]));
     }
-    degree_proof.rem_root.iter().for_each(|&v| fri_roots.push(v));
+    let degree_proof = proof.degree_proof();
+    let fri_coordinates = fri::draw_coordinates(degree_proof, &mut transcript);
 
-    let mut seed = [0u8; 32];
-    hash_fn(&fri_roots, &mut seed);
+    // 2 ----- Verify proof of work and determine query positions ---------------------------------
+    let seed = transcript.draw();
     let seed = match utils::verify_pow_nonce(seed, proof.pow_nonce(), &options) {
         Ok(seed) => seed,
         Err(msg) => return Err(msg)
@@ -29,47 +224,97 @@ pub fn verify<T>(program_hash: &[u8; 32], inputs: &[T], outputs: &[T], proof: &S
     let t_positions = utils::compute_query_positions(&seed, proof.domain_size(), options);
     let c_positions = utils::map_trace_to_constraint_positions::<T>(&t_positions);
 
-    // 2 ----- Verify trace and constraint Merkle proofs ------------------------------------------
-    if !MerkleTree::verify_batch(proof.trace_root(), &t_positions, &proof.trace_proof(), hash_fn) {
+    // 3 ----- Verify trace and constraint Merkle proofs ------------------------------------------
+    if !MerkleTree::verify_batch(proof.trace_cap(), &t_positions, &proof.trace_proof(), hash_fn) {
         return Err(String::from("verification of trace Merkle proof failed"));
     }
 
-    if !MerkleTree::verify_batch(proof.constraint_root(), &c_positions, &proof.constraint_proof(), hash_fn) {
+    if !MerkleTree::verify_batch(proof.constraint_cap(), &c_positions, &proof.constraint_proof(), hash_fn) {
         return Err(String::from("verification of constraint Merkle proof failed"));
     }
 
-    // 3 ----- Compute constraint evaluations at DEEP point z -------------------------------------
-    // derive DEEP point z from the root of the constraint tree
-    let z = T::prng(*proof.constraint_root());
+    // 4 ----- Compute constraint evaluations at DEEP point z -------------------------------------
+    // derive DEEP point z from the transcript
+    let z = Prng::new(composition_seed, hash_fn).draw_element();
 
     // evaluate constraints at z
     let constraint_evaluation_at_z = evaluate_constraints(
-        ConstraintEvaluator::from_proof(proof, program_hash, inputs, outputs),
+        context.evaluator.rebind(&constraint_seed, hash_fn, init_state, last_state),
         proof.get_state_at_z1(),
         proof.get_state_at_z2(),
-        z
+        z,
+        context.x_at_last_step
     );
 
-    // 4 ----- Compute composition polynomial evaluations -----------------------------------------
-    // derive coefficient for linear combination from the root of constraint tree
-    let coefficients = CompositionCoefficients::<T>::new(*proof.constraint_root());
+    // 5 ----- Compute composition polynomial evaluations -----------------------------------------
+    // derive coefficient for linear combination from the transcript
+    let coefficients = CompositionCoefficients::<T>::new(composition_seed, hash_fn);
 
     // compute composition values separately for trace and constraints, and then add them together
-    let t_composition = compose_registers(&proof, &t_positions, z, &coefficients);
-    let c_composition = compose_constraints(&proof, &t_positions, &c_positions, z, constraint_evaluation_at_z, &coefficients);
-    let evaluations = t_composition.iter().zip(c_composition).map(|(&t, c)| T::add(t, c)).collect::<Vec<T>>();
+    let t_composition = compose_registers(&proof, &t_positions, z, &coefficients, context);
+    let c_composition = match compose_constraints(&proof, &t_positions, &c_positions, z, constraint_evaluation_at_z, &coefficients, context) {
+        Ok(composition) => composition,
+        Err(msg) => return Err(msg)
+    };
+    let mut evaluations = t_composition.iter().zip(c_composition).map(|(&t, c)| T::add(t, c)).collect::<Vec<T>>();
+
+    // in zero-knowledge mode, the randomizer is also a part of the composition
+    if proof.blinding().is_some() {
+        let r_composition = compose_randomizer(&proof, &t_positions, z, &coefficients, context);
+        evaluations = evaluations.iter().zip(r_composition).map(|(&e, r)| T::add(e, r)).collect();
+    }
     
-    // 5 ----- Verify low-degree proof -------------------------------------------------------------
-    let max_degree = utils::get_composition_degree(proof.trace_length());
-    return match fri::verify(&degree_proof, &evaluations, &t_positions, max_degree, options) {
+    // 6 ----- Verify low-degree proof -------------------------------------------------------------
+    return match fri::verify(&degree_proof, &evaluations, &t_positions, &fri_coordinates, proof.domain_size(), context.max_degree, options) {
         Ok(result) => Ok(result),
         Err(msg) => Err(format!("verification of low-degree proof failed: {}", msg))
     }
 }
 
+// VERIFIER CONTEXT IMPLEMENTATION
+// ================================================================================================
+impl <T> VerifierContext<T>
+    where T: FiniteField + Accumulator + Hasher
+{
+    /// Returns a context for verifying proofs of the same shape as the `proof`. Constraint
+    /// coefficients and boundary states of the evaluator are placeholders: they are rebound for
+    /// each verified proof.
+    fn new(proof: &StarkProof<T>) -> VerifierContext<T> {
+        let trace_length = proof.trace_length();
+        let randomized_length = proof.options().randomized_trace_length(trace_length);
+        let no_state = SegmentState::from_inputs(&[]);
+        let evaluator = ConstraintEvaluator::from_proof(proof, &[0; 32], &no_state, &no_state);
+
+        return VerifierContext {
+            x_at_last_step      : evaluator.get_x_at_last_step(),
+            evaluator           : evaluator,
+            lde_root            : T::get_root_of_unity(proof.domain_size()),
+            trace_root          : T::get_root_of_unity(trace_length),
+            incremental_degree  : T::from_usize(utils::get_incremental_trace_degree(trace_length, randomized_length)),
+            max_degree          : utils::get_composition_degree(trace_length, randomized_length),
+        };
+    }
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
-fn evaluate_constraints<T>(evaluator: ConstraintEvaluator<T>, state1: TraceState<T>, state2: TraceState<T>, x: T) -> T
+fn get_proof_shape<T>(proof: &StarkProof<T>) -> ProofShape
+    where T: FiniteField + Accumulator
+{
+    let trace_length = proof.trace_length();
+    let randomized_length = proof.options().randomized_trace_length(trace_length);
+    return (trace_length, proof.domain_size(), randomized_length, proof.stack_depth());
+}
+
+/// Returns the number of user stack registers in the trace of the `proof`; stacks shallower than
+/// the min stack depth are padded with zeros.
+fn get_user_stack_depth<T>(proof: &StarkProof<T>) -> usize
+    where T: FiniteField + Accumulator
+{
+    return cmp::max(proof.stack_depth(), MIN_STACK_DEPTH) - 1;
+}
+
+fn evaluate_constraints<T>(evaluator: ConstraintEvaluator<T>, state1: TraceState<T>, state2: TraceState<T>, x: T, x_at_last_step: T) -> T
     where T: FiniteField + Accumulator + Hasher
 {
     let (i_value, f_value) = evaluator.evaluate_boundaries(&state1, x);
@@ -80,7 +325,7 @@ fn evaluate_constraints<T>(evaluator: ConstraintEvaluator<T>, state1: TraceState
     let mut result = T::div(i_value, z);
 
     // Z(x) = x - x_at_last_step
-    let z = T::sub(x, evaluator.get_x_at_last_step());
+    let z = T::sub(x, x_at_last_step);
     result = T::add(result, T::div(f_value, z));
 
     // Z(x) = (x^steps - 1) / (x - x_at_last_step)
@@ -90,22 +335,20 @@ fn evaluate_constraints<T>(evaluator: ConstraintEvaluator<T>, state1: TraceState
     return result;
 }
 
-fn compose_registers<T>(proof: &StarkProof<T>, positions: &[usize], z: T, cc: &CompositionCoefficients<T>) -> Vec<T>
-    where T: FiniteField + Accumulator
+fn compose_registers<T>(proof: &StarkProof<T>, positions: &[usize], z: T, cc: &CompositionCoefficients<T>, context: &VerifierContext<T>) -> Vec<T>
+    where T: FiniteField + Accumulator + Hasher
 {    
-    let lde_root = T::get_root_of_unity(proof.domain_size());
-    let trace_root = T::get_root_of_unity(proof.trace_length());
-    let next_z = T::mul(z, trace_root);
+    let lde_root = context.lde_root;
+    let next_z = T::mul(z, context.trace_root);
 
     let trace_at_z1 = proof.get_state_at_z1().registers().to_vec();
     let trace_at_z2 = proof.get_state_at_z2().registers().to_vec();
     let evaluations = proof.trace_evaluations();
-
-    let incremental_degree = T::from_usize(utils::get_incremental_trace_degree(proof.trace_length()));
+    let incremental_degree = context.incremental_degree;
 
     let mut result = Vec::with_capacity(evaluations.len());
     for (registers, &position) in evaluations.into_iter().zip(positions) {
-        let x = T::exp(lde_root, T::from_usize(position));
+        let x = T::mul(T::GENERATOR, T::exp(lde_root, T::from_usize(position)));
         
         let mut composition = T::ZERO;
         for (i, &value) in registers.iter().enumerate() {
@@ -131,27 +374,29 @@ fn compose_registers<T>(proof: &StarkProof<T>, positions: &[usize], z: T, cc: &C
     return result;
 }
 
-fn compose_constraints<T>(proof: &StarkProof<T>, t_positions: &[usize], c_positions: &[usize], z: T, evaluation_at_z: T, cc: &CompositionCoefficients<T>) -> Vec<T>
-    where T: FiniteField + Accumulator
+fn compose_constraints<T>(proof: &StarkProof<T>, t_positions: &[usize], c_positions: &[usize], z: T, evaluation_at_z: T, cc: &CompositionCoefficients<T>, context: &VerifierContext<T>) -> Result<Vec<T>, String>
+    where T: FiniteField + Accumulator + Hasher
 {
     // build constraint evaluation values from the leaves of constraint Merkle proof
     let mut evaluations: Vec<T> = Vec::with_capacity(t_positions.len());
     let element_size = mem::size_of::<T>();
     let elements_per_leaf = 32 / element_size;
-    let leaves = proof.constraint_proof().values;
+    let leaves = proof.constraint_evaluations();
     for &position in t_positions.iter() {
-        let leaf_idx = c_positions.iter().position(|&v| v == position / elements_per_leaf).unwrap();
+        let leaf = match c_positions.iter().position(|&v| v == position / elements_per_leaf) {
+            Some(leaf_idx) if leaf_idx < leaves.len() => &leaves[leaf_idx],
+            _ => return Err(format!("constraint evaluation for position {} is missing", position))
+        };
         let element_start = (position % elements_per_leaf) * element_size;
-        let element_bytes = &leaves[leaf_idx][element_start..(element_start + element_size)];
-        evaluations.push(T::from_bytes(element_bytes));
+        evaluations.push(T::from_bytes(&leaf[element_start..(element_start + element_size)]));
     }
 
-    let lde_root = T::get_root_of_unity(proof.domain_size());
+    let lde_root = context.lde_root;
 
     // divide out deep point from the evaluations
     let mut result = Vec::with_capacity(evaluations.len());
     for (evaluation, &position) in evaluations.into_iter().zip(t_positions) {
-        let x = T::exp(lde_root, T::from_usize(position));
+        let x = T::mul(T::GENERATOR, T::exp(lde_root, T::from_usize(position)));
 
         // compute C(x) = (P(x) - P(z)) / (x - z)
         let composition = T::div(T::sub(evaluation, evaluation_at_z), T::sub(x, z));
@@ -159,5 +404,25 @@ fn compose_constraints<T>(proof: &StarkProof<T>, t_positions: &[usize], c_positi
         result.push(T::mul(composition, cc.constraints));
     }
 
+    return Ok(result);
+}
+
+fn compose_randomizer<T>(proof: &StarkProof<T>, positions: &[usize], z: T, cc: &CompositionCoefficients<T>, context: &VerifierContext<T>) -> Vec<T>
+    where T: FiniteField + Accumulator + Hasher
+{
+    let blinding = proof.blinding().expect("proof does not contain blinding values");
+    let lde_root = context.lde_root;
+
+    // divide out deep point from the randomizer values
+    let mut result = Vec::with_capacity(positions.len());
+    for (&value, &position) in blinding.randomizer_values.iter().zip(positions) {
+        let x = T::mul(T::GENERATOR, T::exp(lde_root, T::from_usize(position)));
+
+        // compute R(x) = (M(x) - M(z)) / (x - z)
+        let composition = T::div(T::sub(value, blinding.randomizer_at_z), T::sub(x, z));
+        // multiply by pseudo-random coefficient for linear combination
+        result.push(T::mul(composition, cc.randomizer));
+    }
+
     return result;
 }
\ No newline at end of file